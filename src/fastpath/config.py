@@ -5,8 +5,10 @@ Values can be overridden via environment variables.
 
 Environment Variables:
     FASTPATH_VIPS_PATH: Base path for VIPS installation (default: C:/vips)
-    FASTPATH_L1_CACHE_MB: Rust L1 tile cache size in MB, decoded RGB (default: 4096)
-    FASTPATH_L2_CACHE_MB: Rust L2 compressed cache size in MB, JPEG bytes (default: 32768)
+    FASTPATH_L1_CACHE_MB: Rust L1 tile cache size in MB, decoded RGB
+        (default: unset = the scheduler's 4096)
+    FASTPATH_L2_CACHE_MB: Rust L2 compressed cache size in MB, JPEG bytes
+        (default: unset = the scheduler's 32768)
     FASTPATH_PREFETCH_DISTANCE: Tiles to prefetch ahead (default: 3)
     FASTPATH_VIPS_CONCURRENCY: VIPS internal thread count (default: 24)
 """
@@ -33,6 +35,17 @@ def _get_env_int(name: str, default: int) -> int:
     return default
 
 
+def _get_env_optional_int(name: str) -> int | None:
+    """Get an integer from environment variable, or None if unset or invalid."""
+    value = os.environ.get(name)
+    if value is not None:
+        try:
+            return int(value)
+        except ValueError:
+            logger.warning("Invalid integer for %s: %r, ignoring", name, value)
+    return None
+
+
 def _get_env_str(name: str, default: str) -> str:
     """Get a string from environment variable with fallback."""
     return os.environ.get(name, default)
@@ -58,11 +71,13 @@ VIPS_REQUIRED_DLLS: tuple[str, ...] = ("libopenslide-1.dll", "libvips-42.dll")
 # Tile Cache Configuration
 # =============================================================================
 
-#: Rust L1 tile cache size in MB (decoded RGB tiles, cleared on slide switch)
-L1_CACHE_SIZE_MB: int = _get_env_int("FASTPATH_L1_CACHE_MB", 4096)
+#: Rust L1 tile cache size in MB (decoded RGB tiles, cleared on slide switch).
+#: None leaves the scheduler default, which isn't checked against system RAM.
+L1_CACHE_SIZE_MB: int | None = _get_env_optional_int("FASTPATH_L1_CACHE_MB")
 
-#: Rust L2 compressed tile cache size in MB (JPEG bytes, persists across slide switches)
-L2_CACHE_SIZE_MB: int = _get_env_int("FASTPATH_L2_CACHE_MB", 32768)
+#: Rust L2 compressed tile cache size in MB (JPEG bytes, persists across slide
+#: switches). None leaves the scheduler default, as for L1.
+L2_CACHE_SIZE_MB: int | None = _get_env_optional_int("FASTPATH_L2_CACHE_MB")
 
 #: Number of tiles to prefetch in pan direction
 PREFETCH_DISTANCE: int = _get_env_int("FASTPATH_PREFETCH_DISTANCE", 3)
@@ -141,13 +156,13 @@ def _validate_config() -> None:
     """Validate configuration values and log warnings for out-of-range settings."""
     global L1_CACHE_SIZE_MB, L2_CACHE_SIZE_MB, PREFETCH_DISTANCE
 
-    if L1_CACHE_SIZE_MB < 1:
+    if L1_CACHE_SIZE_MB is not None and L1_CACHE_SIZE_MB < 1:
         logger.warning(
             "L1_CACHE_SIZE_MB=%d is too low, clamping to 1", L1_CACHE_SIZE_MB
         )
         L1_CACHE_SIZE_MB = 1
 
-    if L2_CACHE_SIZE_MB < 1:
+    if L2_CACHE_SIZE_MB is not None and L2_CACHE_SIZE_MB < 1:
         logger.warning(
             "L2_CACHE_SIZE_MB=%d is too low, clamping to 1", L2_CACHE_SIZE_MB
         )
@@ -230,8 +230,8 @@ class SlideContext:
             raise ValueError("Region width and height must be positive")
         if level < 0:
             return np.full((h, w, 3), 255, dtype=np.uint8)
-        data = self._rust_reader.decode_region(level, x, y, w, h)
-        return np.frombuffer(data, dtype=np.uint8).reshape((h, w, 3))
+        # (h, w, 3) buffer over the Rust-side pixels; no copy
+        return np.asarray(self._rust_reader.decode_region_buffer(level, x, y, w, h))
 
     # ------------------------------------------------------------------
     # Original WSI access
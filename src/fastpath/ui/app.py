@@ -24,7 +24,7 @@ from fastpath.ui.models import TileModel, RecentFilesModel
 from fastpath.ui.navigator import SlideNavigator
 from fastpath.ui.settings import Settings
 from fastpath.ui.preprocess import PreprocessController
-from fastpath_core import RustTileScheduler, is_debug_build
+from fastpath_core import RustTileScheduler, init_logging, is_debug_build
 
 logger = logging.getLogger(__name__)
 
@@ -608,6 +608,9 @@ def run_app(args: list[str] | None = None) -> int:
 
     plugin_manager.set_annotation_manager(annotation_manager)
 
+    # Forward Rust-side tile errors and timing to Python logging
+    init_logging("debug" if logger.isEnabledFor(logging.DEBUG) else "info")
+
     # Create Rust scheduler with configured cache and prefetch settings
     rust_scheduler = RustTileScheduler(
         cache_size_mb=L1_CACHE_SIZE_MB,
@@ -4,16 +4,37 @@
 //! without decoding to RGB. Uses a dedicated 3-thread rayon pool to avoid
 //! competing with interactive viewport prefetch I/O.
 
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread::JoinHandle;
+use std::time::Duration;
 
 use parking_lot::Mutex;
+use log::{info, warn};
 
 use crate::cache::{CompressedTileCache, SlideTileCoord};
 use crate::decoder::CompressedTileData;
-use crate::slide_pool::SlidePool;
+use crate::pack::TilePack;
+use crate::prefetch::{morton_code, TileOrder};
+use crate::slide_pool::{SlideEntry, SlidePool};
+
+/// How often a paused worker re-checks the pause/cancel flags.
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Tiles read before they are handed to L2 in one `insert_many`.
+const L2_INSERT_BATCH: usize = 256;
+
+/// Block while `paused` is set. Returns false if cancelled while waiting.
+fn wait_while_paused(paused: &AtomicBool, cancelled: &AtomicBool) -> bool {
+    while paused.load(Ordering::Acquire) {
+        if cancelled.load(Ordering::Acquire) {
+            return false;
+        }
+        std::thread::sleep(PAUSE_POLL_INTERVAL);
+    }
+    !cancelled.load(Ordering::Acquire)
+}
 
 /// Background preloader that fills L2 cache with tiles from multiple slides.
 pub struct BulkPreloader {
@@ -21,12 +42,228 @@ pub struct BulkPreloader {
     pool: Arc<SlidePool>,
     rayon_pool: Arc<rayon::ThreadPool>,
     cancelled: Arc<AtomicBool>,
+    /// When set, workers park between tiles until resumed (or cancelled).
+    paused: Arc<AtomicBool>,
+    /// Order tiles are queued within each level (read at `start()`).
+    tile_order: Mutex<TileOrder>,
+    /// Leading slides re-checked for evicted tiles after a run (read at `start()`).
+    verify_slides: AtomicUsize,
+    /// Fraction of L2 capacity past which lower-priority slides stop being
+    /// filled (f64 bits; 1.0 = no limit; read at `start()`).
+    max_l2_fill_ratio: AtomicU64,
     handle: Mutex<Option<JoinHandle<()>>>,
 }
 
+/// State shared by the worker thread of one preload run.
+struct PreloadRun {
+    l2_cache: Arc<CompressedTileCache>,
+    pool: Arc<SlidePool>,
+    cancelled: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    rayon_pool: Arc<rayon::ThreadPool>,
+    tile_order: TileOrder,
+    max_l2_fill_ratio: f64,
+}
+
+impl PreloadRun {
+    /// Whether L2 holds more than `max_l2_fill_ratio` of its capacity.
+    fn over_budget(&self) -> bool {
+        if self.max_l2_fill_ratio >= 1.0 {
+            return false;
+        }
+        let budget = self.l2_cache.max_bytes() as f64 * self.max_l2_fill_ratio;
+        self.l2_cache.stats().size_bytes as f64 > budget
+    }
+
+    /// Tiles of a slide not currently in L2 (in the configured order), and
+    /// how many were skipped because they are.
+    fn missing_tiles(&self, slide_id: u64, entry: &SlideEntry) -> (Vec<SlideTileCoord>, usize) {
+        let mut tile_work: Vec<SlideTileCoord> = Vec::new();
+        let mut skipped = 0usize;
+
+        for level_info in &entry.metadata.levels {
+            for row in 0..level_info.rows {
+                for col in 0..level_info.cols {
+                    let l2_coord = SlideTileCoord::new(slide_id, level_info.level, col, row);
+
+                    // Skip tiles already in L2
+                    if self.l2_cache.contains(&l2_coord) {
+                        skipped += 1;
+                        continue;
+                    }
+
+                    tile_work.push(l2_coord);
+                }
+            }
+        }
+
+        if self.tile_order == TileOrder::Morton {
+            tile_work.sort_by_key(|c| (c.level(), morton_code(c.col(), c.row())));
+        }
+        (tile_work, skipped)
+    }
+
+    /// Read `tiles` on the preload pool, handing each to `sink`.
+    ///
+    /// Tiles not yet started when `stop` is set are skipped. Returns
+    /// (loaded, failed) counts.
+    fn read_tiles(
+        &self,
+        pack: &TilePack,
+        tiles: &[SlideTileCoord],
+        stop: &AtomicBool,
+        sink: impl Fn(SlideTileCoord, CompressedTileData) + Sync,
+    ) -> (usize, usize) {
+        let loaded = AtomicUsize::new(0);
+        let failed = AtomicUsize::new(0);
+
+        self.rayon_pool.install(|| {
+            use rayon::prelude::*;
+            tiles.par_iter().for_each(|l2_coord| {
+                if !wait_while_paused(&self.paused, &self.cancelled)
+                    || stop.load(Ordering::Relaxed)
+                {
+                    return;
+                }
+
+                let tile_ref = match pack.tile_ref(l2_coord.level(), l2_coord.col(), l2_coord.row())
+                {
+                    Some(tile_ref) => tile_ref,
+                    None => {
+                        failed.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                };
+
+                match pack.read_tile_bytes(tile_ref) {
+                    Ok(bytes) => {
+                        let compressed = CompressedTileData {
+                            jpeg_bytes: bytes,
+                            width: 0,
+                            height: 0,
+                        };
+                        sink(*l2_coord, compressed);
+                        loaded.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(_) => {
+                        failed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        });
+
+        (loaded.into_inner(), failed.into_inner())
+    }
+
+    /// Read every tile of a slide that isn't already in L2.
+    ///
+    /// With `budgeted`, L2 fill is checked after every insert batch and the
+    /// slide is abandoned once it passes `max_l2_fill_ratio`. Returns false
+    /// in that case.
+    fn preload_slide(&self, slide_id: u64, path: &Path, budgeted: bool) -> bool {
+        let slide_name = slide_name(path);
+
+        // Load metadata + resolver from pool
+        let entry = match self.pool.load_or_get(slide_id, path) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Skipping {}: {:?}", slide_name, e);
+                return true;
+            }
+        };
+
+        let (tile_work, skipped) = self.missing_tiles(slide_id, &entry);
+        if tile_work.is_empty() {
+            info!(
+                "{}: 0 tiles loaded, 0 failed, {} skipped (all cached)",
+                slide_name, skipped
+            );
+            return true;
+        }
+
+        // Tiles reach L2 in groups so moka maintenance runs once per group
+        let batch = Mutex::new(Vec::with_capacity(L2_INSERT_BATCH));
+        let full_l2 = AtomicBool::new(false);
+        let (loaded, failed) = self.read_tiles(&entry.pack, &tile_work, &full_l2, |coord, tile| {
+            let full = {
+                let mut batch = batch.lock();
+                batch.push((coord, tile));
+                (batch.len() >= L2_INSERT_BATCH)
+                    .then(|| std::mem::replace(&mut *batch, Vec::with_capacity(L2_INSERT_BATCH)))
+            };
+            if let Some(full) = full {
+                self.l2_cache.insert_many(full);
+                if budgeted && self.over_budget() {
+                    full_l2.store(true, Ordering::Relaxed);
+                }
+            }
+        });
+        self.l2_cache.insert_many(batch.into_inner());
+
+        info!(
+            "{}: {} tiles loaded, {} failed, {} skipped",
+            slide_name, loaded, failed, skipped
+        );
+        !full_l2.into_inner()
+    }
+
+    /// Re-load a priority slide's tiles that were evicted during the run.
+    ///
+    /// Plain re-inserts would mostly be refused: TinyLFU only admits a
+    /// newcomer over hotter residents. So room is made first by dropping
+    /// the oldest tiles of `lower` slides, lowest priority first.
+    fn reload_evicted(&self, slide_id: u64, path: &Path, lower: &[(u64, PathBuf)]) {
+        let Ok(entry) = self.pool.load_or_get(slide_id, path) else {
+            return;
+        };
+        // Settle the run's pending evictions before checking residency
+        self.l2_cache.run_pending_tasks();
+        let (missing, _) = self.missing_tiles(slide_id, &entry);
+        if missing.is_empty() {
+            return;
+        }
+
+        let tiles = Mutex::new(Vec::with_capacity(missing.len()));
+        self.read_tiles(&entry.pack, &missing, &AtomicBool::new(false), |coord, tile| {
+            tiles.lock().push((coord, tile));
+        });
+        let tiles = tiles.into_inner();
+
+        let needed: u64 = tiles.iter().map(|(_, t)| t.jpeg_bytes.len() as u64).sum();
+        let mut free = self.l2_cache.free_bytes();
+        for (lower_id, _) in lower.iter().rev() {
+            if free >= needed {
+                break;
+            }
+            free += self.l2_cache.evict_partition_oldest(*lower_id, needed - free);
+        }
+
+        let reloaded = tiles.len();
+        self.l2_cache.insert_many(tiles);
+        info!(
+            "{} (verify): {} evicted tiles re-loaded",
+            slide_name(path),
+            reloaded
+        );
+    }
+}
+
+fn slide_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
+
 impl BulkPreloader {
     /// Create a new bulk preloader with a dedicated 3-thread rayon pool.
-    pub fn new(l2_cache: Arc<CompressedTileCache>, pool: Arc<SlidePool>) -> Self {
+    ///
+    /// See [`set_max_l2_fill_ratio`](Self::set_max_l2_fill_ratio) for
+    /// `max_l2_fill_ratio`.
+    pub fn new(
+        l2_cache: Arc<CompressedTileCache>,
+        pool: Arc<SlidePool>,
+        max_l2_fill_ratio: f64,
+    ) -> Self {
         let rayon_pool = Arc::new(
             rayon::ThreadPoolBuilder::new()
                 .num_threads(3)
@@ -40,6 +277,10 @@ impl BulkPreloader {
             pool,
             rayon_pool,
             cancelled: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(false)),
+            tile_order: Mutex::new(TileOrder::default()),
+            verify_slides: AtomicUsize::new(1),
+            max_l2_fill_ratio: AtomicU64::new(max_l2_fill_ratio.clamp(0.0, 1.0).to_bits()),
             handle: Mutex::new(None),
         }
     }
@@ -52,7 +293,15 @@ impl BulkPreloader {
     /// demand when the user views them.
     ///
     /// `slides` should be pre-sorted in priority order (outward expansion
-    /// from the current slide index).
+    /// from the current slide index). After the pass, the first
+    /// `verify_slides` slides are re-checked and any tiles that later slides
+    /// evicted from L2 are re-loaded (at the expense of the lowest-priority
+    /// slides), so the slides the user is most likely to view next end the
+    /// run fully resident.
+    ///
+    /// The first slide (the one being viewed) is always filled completely.
+    /// Once L2 passes `max_l2_fill_ratio`, the pass stops and the remaining
+    /// slides are left uncached.
     pub fn start(&self, slides: Vec<(u64, PathBuf)>) {
         // Cancel previous run
         self.cancel();
@@ -64,128 +313,84 @@ impl BulkPreloader {
         // Reset cancelled flag
         self.cancelled.store(false, Ordering::Release);
 
-        let l2_cache = Arc::clone(&self.l2_cache);
-        let pool = Arc::clone(&self.pool);
-        let cancelled = Arc::clone(&self.cancelled);
-        let rayon_pool = Arc::clone(&self.rayon_pool);
+        let run = PreloadRun {
+            l2_cache: Arc::clone(&self.l2_cache),
+            pool: Arc::clone(&self.pool),
+            cancelled: Arc::clone(&self.cancelled),
+            paused: Arc::clone(&self.paused),
+            rayon_pool: Arc::clone(&self.rayon_pool),
+            tile_order: *self.tile_order.lock(),
+            max_l2_fill_ratio: self.max_l2_fill_ratio(),
+        };
+        let verify_slides = self.verify_slides.load(Ordering::Relaxed);
 
         let handle = std::thread::Builder::new()
             .name("bulk-preload-main".into())
             .spawn(move || {
-                for (slide_id, path) in &slides {
-                    if cancelled.load(Ordering::Acquire) {
-                        eprintln!("[BULK PRELOAD] Cancelled");
+                for (i, (slide_id, path)) in slides.iter().enumerate() {
+                    if !wait_while_paused(&run.paused, &run.cancelled) {
+                        info!("Cancelled");
                         return;
                     }
-
-                    let slide_name = path
-                        .file_name()
-                        .map(|n| n.to_string_lossy().to_string())
-                        .unwrap_or_else(|| path.to_string_lossy().to_string());
-
-                    // Load metadata + resolver from pool
-                    let entry = match pool.load_or_get(*slide_id, path) {
-                        Ok(e) => e,
-                        Err(e) => {
-                            eprintln!(
-                                "[BULK PRELOAD] Skipping {}: {:?}",
-                                slide_name, e
-                            );
-                            continue;
-                        }
-                    };
-
-                    let pack = &entry.pack;
-
-                    // Enumerate all tiles across all levels
-                    let mut tile_work: Vec<SlideTileCoord> = Vec::new();
-                    let mut skipped = 0usize;
-
-                    for level_info in &entry.metadata.levels {
-                        for row in 0..level_info.rows {
-                            for col in 0..level_info.cols {
-                                let l2_coord = SlideTileCoord::new(
-                                    *slide_id,
-                                    level_info.level,
-                                    col,
-                                    row,
-                                );
-
-                                // Skip tiles already in L2
-                                if l2_cache.contains(&l2_coord) {
-                                    skipped += 1;
-                                    continue;
-                                }
-
-                                tile_work.push(l2_coord);
-                            }
-                        }
-                    }
-
-                    if tile_work.is_empty() {
-                        eprintln!(
-                            "[BULK PRELOAD] {}: 0 tiles loaded, 0 failed, {} skipped (all cached)",
-                            slide_name, skipped
+                    // The viewed slide is filled regardless of the budget
+                    let budgeted = i > 0;
+                    let within_budget = !(budgeted && run.over_budget())
+                        && run.preload_slide(*slide_id, path, budgeted);
+                    if !within_budget {
+                        info!(
+                            "L2 past {:.0}% full, stopping with {} of {} slides unfinished",
+                            run.max_l2_fill_ratio * 100.0,
+                            slides.len() - i,
+                            slides.len()
                         );
-                        continue;
+                        break;
                     }
+                }
 
-                    let loaded = AtomicUsize::new(0);
-                    let failed = AtomicUsize::new(0);
-                    let cancelled_ref = &cancelled;
-
-                    rayon_pool.install(|| {
-                        use rayon::prelude::*;
-                        tile_work.par_iter().for_each(|l2_coord| {
-                            if cancelled_ref.load(Ordering::Acquire) {
-                                return;
-                            }
-
-                            let tile_ref = match pack.tile_ref(
-                                l2_coord.level(),
-                                l2_coord.col(),
-                                l2_coord.row(),
-                            ) {
-                                Some(tile_ref) => tile_ref,
-                                None => {
-                                    failed.fetch_add(1, Ordering::Relaxed);
-                                    return;
-                                }
-                            };
-
-                            match pack.read_tile_bytes(tile_ref) {
-                                Ok(bytes) => {
-                                    let compressed = CompressedTileData {
-                                        jpeg_bytes: bytes,
-                                        width: 0,
-                                        height: 0,
-                                    };
-                                    l2_cache.insert(*l2_coord, compressed);
-                                    loaded.fetch_add(1, Ordering::Relaxed);
-                                }
-                                Err(_) => {
-                                    failed.fetch_add(1, Ordering::Relaxed);
-                                }
-                            }
-                        });
-                    });
-
-                    eprintln!(
-                        "[BULK PRELOAD] {}: {} tiles loaded, {} failed, {} skipped",
-                        slide_name,
-                        loaded.load(Ordering::Relaxed),
-                        failed.load(Ordering::Relaxed),
-                        skipped
-                    );
+                // Later slides may have evicted the priority slides' tiles
+                let verify_slides = verify_slides.min(slides.len());
+                for (slide_id, path) in &slides[..verify_slides] {
+                    if !wait_while_paused(&run.paused, &run.cancelled) {
+                        info!("Cancelled");
+                        return;
+                    }
+                    run.reload_evicted(*slide_id, path, &slides[verify_slides..]);
                 }
 
-                eprintln!("[BULK PRELOAD] Complete");
+                info!("Complete");
             })
             .expect("failed to spawn bulk preload thread");
 
         *self.handle.lock() = Some(handle);
     }
 
+    /// Set how many of the highest-priority slides are re-verified after a run.
+    ///
+    /// 0 disables the check. Defaults to 1 (the current slide).
+    pub fn set_verify_slides(&self, count: usize) {
+        self.verify_slides.store(count, Ordering::Relaxed);
+    }
+
+    /// Number of highest-priority slides re-verified after a run.
+    pub fn verify_slides(&self) -> usize {
+        self.verify_slides.load(Ordering::Relaxed)
+    }
+
+    /// Stop filling lower-priority slides once L2 holds more than `ratio` of
+    /// its capacity, so they don't evict the viewed slide's tiles.
+    ///
+    /// Clamped to 0.0..=1.0; 1.0 (the default) fills until the list is done.
+    /// Takes effect at the next `start()`.
+    pub fn set_max_l2_fill_ratio(&self, ratio: f64) {
+        self.max_l2_fill_ratio
+            .store(ratio.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// L2 fill fraction past which lower-priority slides are skipped.
+    pub fn max_l2_fill_ratio(&self) -> f64 {
+        f64::from_bits(self.max_l2_fill_ratio.load(Ordering::Relaxed))
+    }
+
     /// Cancel any running bulk preload and wait for the worker to exit.
     pub fn cancel(&self) {
         self.cancelled.store(true, Ordering::Release);
@@ -194,6 +399,30 @@ impl BulkPreloader {
         }
     }
 
+    /// Set the order tiles are queued within each level for future runs.
+    pub fn set_tile_order(&self, order: TileOrder) {
+        *self.tile_order.lock() = order;
+    }
+
+    /// Pause a running (or future) preload between tiles.
+    ///
+    /// The worker keeps its place and continues on `resume()`. The paused
+    /// state survives `start()` so an interactive session stays quiet.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Release);
+    }
+
+    /// Resume a paused preload.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Release);
+    }
+
+    /// Whether the preloader is currently paused.
+    #[cfg(test)]
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
     /// Whether a bulk preload is currently running.
     pub fn is_running(&self) -> bool {
         let guard = self.handle.lock();
@@ -225,7 +454,9 @@ impl Drop for BulkPreloader {
 mod tests {
     use super::*;
     use crate::cache::compute_slide_id;
-    use crate::test_utils::{create_test_fastpath_with_tiles, compute_test_slide_id};
+    use crate::test_utils::{
+        compute_test_slide_id, create_test_fastpath_sized_tiles, create_test_fastpath_with_tiles,
+    };
     use std::fs;
     use tempfile::TempDir;
 
@@ -238,7 +469,7 @@ mod tests {
 
         let l2_cache = Arc::new(CompressedTileCache::new(64));
         let pool = Arc::new(SlidePool::new());
-        let preloader = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool));
+        let preloader = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool), 1.0);
 
         let slide_id = compute_test_slide_id(&slide_dir);
         preloader.start(vec![(slide_id, slide_dir)]);
@@ -269,7 +500,7 @@ mod tests {
         let slide_id = compute_test_slide_id(&slide_dir);
 
         // Pre-populate L2 with all tiles via a first run
-        let preloader = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool));
+        let preloader = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool), 1.0);
         preloader.start(vec![(slide_id, slide_dir.clone())]);
         preloader.wait();
         l2_cache.stats(); // flush moka
@@ -278,7 +509,7 @@ mod tests {
         l2_cache.reset_stats();
 
         // Second run should skip all tiles (already in L2)
-        let preloader2 = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool));
+        let preloader2 = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool), 1.0);
         preloader2.start(vec![(slide_id, slide_dir)]);
         preloader2.wait();
 
@@ -306,7 +537,7 @@ mod tests {
 
         let l2_cache = Arc::new(CompressedTileCache::new(64));
         let pool = Arc::new(SlidePool::new());
-        let preloader = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool));
+        let preloader = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool), 1.0);
 
         preloader.start(slides);
         // Cancel immediately — should not load all slides
@@ -333,7 +564,7 @@ mod tests {
 
         let l2_cache = Arc::new(CompressedTileCache::new(64));
         let pool = Arc::new(SlidePool::new());
-        let preloader = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool));
+        let preloader = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool), 1.0);
 
         // Bad slide first, then good slide
         preloader.start(vec![(bad_id, bad_dir), (good_id, slide_dir)]);
@@ -344,11 +575,178 @@ mod tests {
         assert!(l2_cache.contains(&SlideTileCoord::new(good_id, 0, 0, 0)));
     }
 
+    #[test]
+    fn test_preload_pause_and_resume() {
+        let temp = TempDir::new().unwrap();
+        let slide_dir = temp.path().join("slide.fastpath");
+        fs::create_dir_all(&slide_dir).unwrap();
+        create_test_fastpath_with_tiles(&slide_dir);
+        let slide_id = compute_test_slide_id(&slide_dir);
+
+        let l2_cache = Arc::new(CompressedTileCache::new(64));
+        let pool = Arc::new(SlidePool::new());
+        let preloader = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool), 1.0);
+
+        preloader.pause();
+        preloader.start(vec![(slide_id, slide_dir)]);
+        std::thread::sleep(Duration::from_millis(50));
+
+        // Paused before the first slide: nothing loaded, worker still alive
+        assert!(preloader.is_running());
+        assert_eq!(l2_cache.stats().num_tiles, 0);
+
+        preloader.resume();
+        preloader.wait();
+        assert!(l2_cache.contains(&SlideTileCoord::new(slide_id, 1, 1, 1)));
+    }
+
+    #[test]
+    fn test_cancel_while_paused_does_not_hang() {
+        let temp = TempDir::new().unwrap();
+        let slide_dir = temp.path().join("slide.fastpath");
+        fs::create_dir_all(&slide_dir).unwrap();
+        create_test_fastpath_with_tiles(&slide_dir);
+        let slide_id = compute_test_slide_id(&slide_dir);
+
+        let l2_cache = Arc::new(CompressedTileCache::new(64));
+        let pool = Arc::new(SlidePool::new());
+        let preloader = BulkPreloader::new(l2_cache, pool, 1.0);
+
+        preloader.pause();
+        preloader.start(vec![(slide_id, slide_dir)]);
+        preloader.cancel();
+        assert!(!preloader.is_running());
+    }
+
+    #[test]
+    fn test_preload_morton_order_covers_all_tiles() {
+        let temp = TempDir::new().unwrap();
+        let slide_dir = temp.path().join("slide.fastpath");
+        fs::create_dir_all(&slide_dir).unwrap();
+        create_test_fastpath_with_tiles(&slide_dir);
+        let slide_id = compute_test_slide_id(&slide_dir);
+
+        let l2_cache = Arc::new(CompressedTileCache::new(64));
+        let pool = Arc::new(SlidePool::new());
+        let preloader = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool), 1.0);
+        preloader.set_tile_order(TileOrder::Morton);
+
+        preloader.start(vec![(slide_id, slide_dir)]);
+        preloader.wait();
+
+        assert_eq!(l2_cache.stats().num_tiles, 5);
+    }
+
+    #[test]
+    fn test_priority_slide_reloaded_after_eviction() {
+        let temp = TempDir::new().unwrap();
+
+        // 4 tiles x 200KB per slide; a 1MB L2 holds one slide, not three
+        let mut slides = Vec::new();
+        for i in 0..3 {
+            let slide_dir = temp.path().join(format!("slide{}.fastpath", i));
+            fs::create_dir_all(&slide_dir).unwrap();
+            create_test_fastpath_sized_tiles(&slide_dir, 200 * 1024);
+            slides.push((compute_test_slide_id(&slide_dir), slide_dir));
+        }
+        let slide_tiles = |slide_id: u64| {
+            (0..2).flat_map(move |row| (0..2).map(move |col| SlideTileCoord::new(slide_id, 0, col, row)))
+        };
+        let resident = |cache: &CompressedTileCache, slide_id: u64| {
+            slide_tiles(slide_id).filter(|c| cache.contains(c)).count()
+        };
+        // TinyLFU only evicts for hotter newcomers: make the lower-priority
+        // slides' tiles look recently requested (misses count as accesses).
+        // moka starts counting once the cache has been half full, so fill
+        // and drop a throwaway slide first.
+        let fresh_cache = || {
+            let cache = Arc::new(CompressedTileCache::new(1));
+            let tile = CompressedTileData {
+                jpeg_bytes: vec![0u8; 200 * 1024].into(),
+                width: 0,
+                height: 0,
+            };
+            for col in 0..3 {
+                cache.insert(SlideTileCoord::new(u64::MAX, 0, col, 0), tile.clone());
+            }
+            cache.stats();
+            cache.evict_partition_oldest(u64::MAX, u64::MAX);
+            for (slide_id, _) in &slides[1..] {
+                for coord in slide_tiles(*slide_id) {
+                    cache.get(&coord);
+                }
+            }
+            cache
+        };
+        let priority_id = slides[0].0;
+
+        // Without verification, later slides evict the priority slide's tiles
+        let l2_cache = fresh_cache();
+        let preloader =
+            BulkPreloader::new(Arc::clone(&l2_cache), Arc::new(SlidePool::new()), 1.0);
+        preloader.set_verify_slides(0);
+        preloader.start(slides.clone());
+        preloader.wait();
+        l2_cache.stats();
+        assert!(resident(&l2_cache, priority_id) < 4);
+
+        // With verification (the default), they are re-loaded at the end
+        let l2_cache = fresh_cache();
+        let preloader =
+            BulkPreloader::new(Arc::clone(&l2_cache), Arc::new(SlidePool::new()), 1.0);
+        assert_eq!(preloader.verify_slides(), 1);
+        preloader.start(slides.clone());
+        preloader.wait();
+        l2_cache.stats();
+        assert_eq!(resident(&l2_cache, priority_id), 4);
+        // Room came from the lowest-priority slide first
+        assert!(resident(&l2_cache, slides[2].0) < resident(&l2_cache, slides[1].0));
+    }
+
+    #[test]
+    fn test_fill_ratio_stops_lower_priority_slides() {
+        let temp = TempDir::new().unwrap();
+
+        // 4 tiles x 200KB per slide in a 2MB L2
+        let mut slides = Vec::new();
+        for i in 0..3 {
+            let slide_dir = temp.path().join(format!("slide{}.fastpath", i));
+            fs::create_dir_all(&slide_dir).unwrap();
+            create_test_fastpath_sized_tiles(&slide_dir, 200 * 1024);
+            slides.push((compute_test_slide_id(&slide_dir), slide_dir));
+        }
+        let run = |ratio: f64| {
+            let l2_cache = Arc::new(CompressedTileCache::new(2));
+            let preloader =
+                BulkPreloader::new(Arc::clone(&l2_cache), Arc::new(SlidePool::new()), ratio);
+            preloader.start(slides.clone());
+            preloader.wait();
+            l2_cache.stats();
+            slides
+                .iter()
+                .map(|(slide_id, _)| {
+                    (0..2)
+                        .flat_map(|row| (0..2).map(move |col| (col, row)))
+                        .filter(|&(col, row)| {
+                            l2_cache.contains(&SlideTileCoord::new(*slide_id, 0, col, row))
+                        })
+                        .count()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // The viewed slide fills past a 25% budget; the others are skipped
+        assert_eq!(run(0.25), vec![4, 0, 0]);
+        // With a 50% budget the second slide fits, then the run stops
+        assert_eq!(run(0.5), vec![4, 4, 0]);
+        assert_eq!(run(1.0)[..2], [4, 4]);
+    }
+
     #[test]
     fn test_preload_empty_list() {
         let l2_cache = Arc::new(CompressedTileCache::new(64));
         let pool = Arc::new(SlidePool::new());
-        let preloader = BulkPreloader::new(l2_cache, pool);
+        let preloader = BulkPreloader::new(l2_cache, pool, 1.0);
 
         // Empty list — no crash, no thread spawned
         preloader.start(vec![]);
@@ -365,7 +763,7 @@ mod tests {
 
         let l2_cache = Arc::new(CompressedTileCache::new(64));
         let pool = Arc::new(SlidePool::new());
-        let preloader = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool));
+        let preloader = BulkPreloader::new(Arc::clone(&l2_cache), Arc::clone(&pool), 1.0);
 
         assert!(!preloader.is_running());
 
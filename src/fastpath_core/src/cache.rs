@@ -1,12 +1,23 @@
 //! Thread-safe tile cache using moka (TinyLFU eviction).
 
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::hash::{Hash, Hasher};
-use std::sync::atomic::{AtomicU64, Ordering};
-
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use crossbeam_channel::{Receiver, Sender};
+use crossbeam_epoch::{self as epoch, Atomic, Owned};
+use moka::notification::RemovalCause;
 use moka::sync::Cache;
+use parking_lot::{Mutex, RwLock};
 
-use crate::decoder::{CompressedTileData, TileData};
+use crate::decoder::{CompressedTileData, SampleFormat, TileData};
+use crate::imaging::ImageFormat;
 
 /// Tile coordinate key.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -56,7 +67,6 @@ impl SlideTileCoord {
     }
 
     #[inline]
-    #[allow(dead_code)]
     pub fn slide_id(&self) -> u64 {
         self.slide_id
     }
@@ -77,6 +87,303 @@ impl SlideTileCoord {
     }
 }
 
+/// Cache keys that address a pyramid tile (used by `TrackedCache::debug`).
+pub trait TileKey {
+    fn tile_coord(&self) -> TileCoord;
+}
+
+impl TileKey for TileCoord {
+    fn tile_coord(&self) -> TileCoord {
+        *self
+    }
+}
+
+impl TileKey for L1Key {
+    fn tile_coord(&self) -> TileCoord {
+        self.coord
+    }
+}
+
+impl TileKey for SlideTileCoord {
+    fn tile_coord(&self) -> TileCoord {
+        TileCoord::new(self.level(), self.col(), self.row())
+    }
+}
+
+/// Cache keys that may belong to a quota partition.
+pub trait PartitionKey {
+    /// Partition this key is accounted under, or None for no quota.
+    fn partition(&self) -> Option<u64>;
+}
+
+impl PartitionKey for TileCoord {
+    fn partition(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl PartitionKey for SlideTileCoord {
+    /// L2 tiles are partitioned by slide.
+    fn partition(&self) -> Option<u64> {
+        Some(self.slide_id)
+    }
+}
+
+/// L1 key: a tile as rendered by one decode-time transform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct L1Key {
+    coord: TileCoord,
+    /// `TileTransform::key` of the transform the tile was decoded with.
+    transform: u64,
+    /// 0 for the scheduler's active slide; otherwise the slide_id of a slide
+    /// opened alongside it.
+    slide: u64,
+}
+
+impl PartitionKey for L1Key {
+    fn partition(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Thumbnail cache key: one encoded thumbnail per slide, render setting and
+/// transform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ThumbnailKey {
+    pub slide_id: u64,
+    pub max_dim: u32,
+    pub format: ImageFormat,
+    pub quality: u8,
+    /// `TileTransform::key` the thumbnail was rendered with.
+    pub transform: u64,
+}
+
+impl PartitionKey for ThumbnailKey {
+    fn partition(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Overview cache key: one raw RGB thumbnail per slide, size and transform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OverviewKey {
+    pub slide_id: u64,
+    pub max_dim: u32,
+    /// `TileTransform::key` the overview was rendered with.
+    pub transform: u64,
+}
+
+impl PartitionKey for OverviewKey {
+    fn partition(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Bytes held by one partition, with its keys in insertion order.
+struct PartitionUsage<K> {
+    bytes: u64,
+    /// Insertion sequence → key, oldest first.
+    order: BTreeMap<u64, K>,
+    /// Key → (insertion sequence, size in bytes).
+    entries: HashMap<K, (u64, u64)>,
+}
+
+impl<K: Hash + Eq + Clone> Default for PartitionUsage<K> {
+    fn default() -> Self {
+        Self {
+            bytes: 0,
+            order: BTreeMap::new(),
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> PartitionUsage<K> {
+    fn record(&mut self, key: K, seq: u64, size: u64) {
+        self.remove(&key);
+        self.order.insert(seq, key.clone());
+        self.entries.insert(key, (seq, size));
+        self.bytes += size;
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some((seq, size)) = self.entries.remove(key) {
+            self.order.remove(&seq);
+            self.bytes -= size;
+        }
+    }
+
+    /// Forget `key` if it is still recorded as the entry inserted at `seq`;
+    /// a replacement has already been recorded under a newer one.
+    fn remove_seq(&mut self, key: &K, seq: u64) {
+        if self.entries.get(key).is_some_and(|&(s, _)| s == seq) {
+            self.remove(key);
+        }
+    }
+
+    /// Pick this partition's oldest keys to drop so that inserting `key`
+    /// (`size` bytes) stays within `quota`. Victims are removed from the
+    /// accounting here; the caller invalidates them in the cache.
+    fn evict_for(&mut self, key: &K, size: u64, quota: u64) -> Vec<K> {
+        let existing = self.entries.get(key).map_or(0, |&(_, s)| s);
+        let mut total = self.bytes - existing + size;
+        let mut victims = Vec::new();
+        for k in self.order.values() {
+            if total <= quota {
+                break;
+            }
+            if k == key {
+                continue;
+            }
+            total -= self.entries[k].1;
+            victims.push(k.clone());
+        }
+        for k in &victims {
+            self.remove(k);
+        }
+        victims
+    }
+
+    /// Pick this partition's oldest keys totalling at least `bytes` (or all
+    /// of them). Victims are removed from the accounting here.
+    fn evict_oldest(&mut self, bytes: u64) -> (Vec<K>, u64) {
+        let mut freed = 0;
+        let mut victims = Vec::new();
+        for k in self.order.values() {
+            if freed >= bytes {
+                break;
+            }
+            freed += self.entries[k].1;
+            victims.push(k.clone());
+        }
+        for k in &victims {
+            self.remove(k);
+        }
+        (victims, freed)
+    }
+}
+
+/// Per-partition accounting, shared with the eviction listener.
+struct PartitionAccounting<K> {
+    /// Bytes held per partition. Always kept: one atomic add per insert.
+    bytes: RwLock<HashMap<u64, AtomicU64>>,
+    /// Each partition's keys in insertion order, only kept while a quota is
+    /// set (`indexed`): quota admission needs them on every insert.
+    index: Mutex<HashMap<u64, PartitionUsage<K>>>,
+    indexed: AtomicBool,
+}
+
+impl<K: Hash + Eq + Clone> Default for PartitionAccounting<K> {
+    fn default() -> Self {
+        Self {
+            bytes: RwLock::new(HashMap::new()),
+            index: Mutex::new(HashMap::new()),
+            indexed: AtomicBool::new(false),
+        }
+    }
+}
+
+impl<K: PartitionKey + Hash + Eq + Clone> PartitionAccounting<K> {
+    fn add(&self, partition: u64, bytes: u64) {
+        if let Some(counter) = self.bytes.read().get(&partition) {
+            counter.fetch_add(bytes, Ordering::Relaxed);
+            return;
+        }
+        self.bytes
+            .write()
+            .entry(partition)
+            .or_default()
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn bytes(&self, partition: u64) -> u64 {
+        self.bytes
+            .read()
+            .get(&partition)
+            .map_or(0, |counter| counter.load(Ordering::Relaxed))
+    }
+
+    /// Account for the removal of `key`, inserted at `seq` with `weight`.
+    fn forget(&self, key: &K, partition: u64, seq: u64, weight: u64) {
+        if let Some(counter) = self.bytes.read().get(&partition) {
+            // Saturating: a removal may land after `reset`
+            let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |b| {
+                Some(b.saturating_sub(weight))
+            });
+        }
+        if self.indexed.load(Ordering::Acquire) {
+            let mut index = self.index.lock();
+            if let Some(usage) = index.get_mut(&partition) {
+                usage.remove_seq(key, seq);
+                if usage.entries.is_empty() {
+                    index.remove(&partition);
+                }
+            }
+        }
+    }
+
+    /// Record `key` in the quota index and pick the partition's oldest keys
+    /// to drop so it stays within `quota`. The caller invalidates them.
+    fn admit(&self, key: &K, partition: u64, seq: u64, size: u64, quota: u64) -> Vec<K> {
+        let mut index = self.index.lock();
+        let usage = index.entry(partition).or_default();
+        let victims = usage.evict_for(key, size, quota);
+        usage.record(key.clone(), seq, size);
+        victims
+    }
+
+    /// Start keeping the quota index, seeded with the resident `entries`.
+    fn build_index(&self, entries: impl Iterator<Item = (K, u64, u64)>) {
+        let mut index = self.index.lock();
+        if self.indexed.load(Ordering::Acquire) {
+            return;
+        }
+        index.clear();
+        for (key, seq, weight) in entries {
+            if let Some(partition) = key.partition() {
+                index.entry(partition).or_default().record(key, seq, weight);
+            }
+        }
+        self.indexed.store(true, Ordering::Release);
+    }
+
+    fn drop_index(&self) {
+        let mut index = self.index.lock();
+        self.indexed.store(false, Ordering::Release);
+        index.clear();
+    }
+
+    fn reset(&self) {
+        self.bytes.write().clear();
+        self.index.lock().clear();
+    }
+}
+
+/// Callback for entries evicted to make room (see `set_eviction_sink`).
+pub type EvictionSink<K, V> = Arc<dyn Fn(&K, &V) + Send + Sync>;
+type EvictionSinkSlot<K, V> = Arc<RwLock<Option<EvictionSink<K, V>>>>;
+
+/// Removal events buffered before new ones are dropped (see `set_events_enabled`).
+const CACHE_EVENT_CAPACITY: usize = 4096;
+
+/// Default per-entry overhead charged by the L1 weigher (see
+/// `TrackedCache::set_entry_overhead`): the `TileData`/`Bytes` headers plus
+/// moka's key, entry and deque-node bookkeeping, which `data.len()` misses.
+pub const L1_ENTRY_OVERHEAD_BYTES: u64 = 256;
+
+type CacheEventChannel<K> = (Sender<(K, RemovalCause)>, Receiver<(K, RemovalCause)>);
+type CacheEventSlot<K> = Arc<RwLock<Option<CacheEventChannel<K>>>>;
+
+/// Short name for why an entry left a cache, as reported by `drain_events`.
+pub fn removal_reason(cause: RemovalCause) -> &'static str {
+    match cause {
+        RemovalCause::Size => "size",
+        RemovalCause::Expired => "expired",
+        RemovalCause::Explicit => "explicit",
+        RemovalCause::Replaced => "replaced",
+    }
+}
+
 /// Cache statistics.
 #[derive(Debug, Clone, Default)]
 pub struct CacheStats {
@@ -87,6 +394,36 @@ pub struct CacheStats {
     pub num_tiles: usize,
 }
 
+/// Resident tiles and weighted bytes (stored bytes plus per-entry overhead)
+/// for one pyramid level.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LevelUsage {
+    pub num_tiles: usize,
+    pub size_bytes: usize,
+}
+
+/// Resident entries of one partition (slide), for diagnostics.
+#[derive(Debug, Clone, Default)]
+pub struct PartitionDebug {
+    pub entries: u64,
+    pub bytes: u64,
+    /// Oldest and newest resident tiles by insertion order.
+    pub oldest: Option<TileCoord>,
+    pub newest: Option<TileCoord>,
+}
+
+/// Read-only snapshot of a cache's internals for diagnosing eviction patterns.
+#[derive(Debug, Clone, Default)]
+pub struct CacheDebug {
+    pub entry_count: u64,
+    pub weighted_size: u64,
+    pub max_bytes: u64,
+    /// Resident entries per pyramid level.
+    pub level_counts: BTreeMap<u32, u64>,
+    /// Per-partition accounting (empty for unpartitioned caches).
+    pub partitions: BTreeMap<u64, PartitionDebug>,
+}
+
 /// Trait for cache values that report their size in bytes.
 pub trait Weighted: Clone + Send + Sync + 'static {
     fn size_bytes(&self) -> usize;
@@ -104,6 +441,108 @@ impl Weighted for CompressedTileData {
     }
 }
 
+impl Weighted for Bytes {
+    fn size_bytes(&self) -> usize {
+        self.len()
+    }
+}
+
+/// L1 entry: decoded RGB, stored raw or LZ4-compressed.
+#[derive(Debug, Clone)]
+pub enum L1Tile {
+    Raw(TileData),
+    /// LZ4 block with the uncompressed length prepended.
+    Lz4 {
+        block: Bytes,
+        width: u32,
+        height: u32,
+        sample_format: SampleFormat,
+    },
+}
+
+impl L1Tile {
+    /// Wrap a decoded tile, LZ4-compressing it if `lz4` is set.
+    ///
+    /// Falls back to raw storage when compression doesn't shrink the tile
+    /// (e.g. noise-like content), so enabling LZ4 never costs capacity.
+    fn pack(tile: TileData, lz4: bool) -> Self {
+        if !lz4 {
+            return Self::Raw(tile);
+        }
+        let block = lz4_flex::compress_prepend_size(&tile.data);
+        if block.len() >= tile.data.len() {
+            return Self::Raw(tile);
+        }
+        Self::Lz4 {
+            block: Bytes::from(block),
+            width: tile.width,
+            height: tile.height,
+            sample_format: tile.sample_format,
+        }
+    }
+
+    /// Recover the decoded tile. None only if an LZ4 block is corrupt.
+    fn unpack(self) -> Option<TileData> {
+        match self {
+            Self::Raw(tile) => Some(tile),
+            Self::Lz4 {
+                block,
+                width,
+                height,
+                sample_format,
+            } => lz4_flex::decompress_size_prepended(&block)
+                .ok()
+                .map(|data| TileData::with_format(data, width, height, sample_format)),
+        }
+    }
+}
+
+impl Weighted for L1Tile {
+    fn size_bytes(&self) -> usize {
+        match self {
+            Self::Raw(tile) => tile.size_bytes(),
+            Self::Lz4 { block, .. } => block.len(),
+        }
+    }
+}
+
+/// A cached value with the weight it was charged at insert, so usage and
+/// eviction accounting agree even if the entry overhead changes meanwhile.
+#[derive(Clone)]
+struct Charged<V> {
+    value: V,
+    weight: u64,
+    /// Insertion order across the cache, oldest first.
+    seq: u64,
+}
+
+/// One moka cache and the partition accounting its eviction listener keeps.
+///
+/// `rebuild` swaps in a fresh pair, so removals still draining from the old
+/// cache never touch the new cache's accounting.
+struct Store<K, V> {
+    cache: Cache<K, Charged<V>>,
+    partitions: Arc<PartitionAccounting<K>>,
+}
+
+/// A cache read under an epoch guard (see `TrackedCache::pinned`).
+struct Pinned<'a, T> {
+    ptr: *const T,
+    guard: epoch::Guard,
+    _owner: PhantomData<&'a T>,
+}
+
+impl<T> Deref for Pinned<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: `TrackedCache::inner` is never null, and a swapped-out
+        // cache is only destroyed once every guard pinned before the swap,
+        // including this one, is dropped
+        unsafe { &*self.ptr }
+    }
+}
+
 /// Thread-safe cache with TinyLFU eviction and hit/miss tracking.
 ///
 /// Generic over key and value types. Uses moka::sync::Cache for O(1)
@@ -111,10 +550,29 @@ impl Weighted for CompressedTileData {
 /// and internal sharding.
 pub struct TrackedCache<K, V>
 where
-    K: Hash + Eq + Send + Sync + Clone + 'static,
+    K: PartitionKey + Hash + Eq + Send + Sync + Clone + 'static,
     V: Weighted,
 {
-    inner: Cache<K, V>,
+    /// Swappable so `rebuild` can resize the hash table without a new
+    /// `TrackedCache`. Never null; read through `pinned`, which costs an
+    /// epoch pin instead of a shared lock on every get/insert.
+    inner: Atomic<Store<K, V>>,
+    /// Size limit in bytes (weighted capacity).
+    max_bytes: u64,
+    /// Initial entry capacity of the current `inner` (0 = moka default).
+    initial_capacity: AtomicUsize,
+    /// Sequence number for the next insert.
+    next_seq: AtomicU64,
+    /// Largest fraction of `max_bytes` one partition may hold (f64 bits; 1.0 = no quota).
+    partition_max_fraction: AtomicU64,
+    /// Bytes added to every entry's weight on top of `Weighted::size_bytes`.
+    entry_overhead: AtomicU64,
+    /// Evict entries not read or written for this long (None = never).
+    time_to_idle: Mutex<Option<Duration>>,
+    /// Called with entries moka evicts for capacity (e.g. to spill to L3).
+    eviction_sink: EvictionSinkSlot<K, V>,
+    /// Removal telemetry for `drain_events`; None while disabled.
+    events: CacheEventSlot<K>,
     /// Cache hit count.
     hits: AtomicU64,
     /// Cache miss count.
@@ -123,32 +581,167 @@ where
 
 impl<K, V> TrackedCache<K, V>
 where
-    K: Hash + Eq + Send + Sync + Clone + 'static,
+    K: PartitionKey + Hash + Eq + Send + Sync + Clone + 'static,
     V: Weighted,
 {
     /// Create a new cache with the given size limit in megabytes.
     pub fn new(max_size_mb: usize) -> Self {
         let max_bytes = (max_size_mb as u64) * 1024 * 1024;
-        let inner = Cache::builder()
-            .max_capacity(max_bytes)
-            .weigher(|_key: &K, value: &V| -> u32 {
-                Weighted::size_bytes(value).try_into().unwrap_or(u32::MAX)
-            })
-            .build();
+        let eviction_sink: EvictionSinkSlot<K, V> = Arc::default();
+        let events: CacheEventSlot<K> = Arc::default();
+        let inner = Self::build(max_bytes, 0, None, false, &eviction_sink, &events);
         Self {
-            inner,
+            inner: Atomic::new(inner),
+            max_bytes,
+            initial_capacity: AtomicUsize::new(0),
+            next_seq: AtomicU64::new(0),
+            partition_max_fraction: AtomicU64::new(1.0f64.to_bits()),
+            entry_overhead: AtomicU64::new(0),
+            time_to_idle: Mutex::new(None),
+            eviction_sink,
+            events,
             hits: AtomicU64::new(0),
             misses: AtomicU64::new(0),
         }
     }
 
+    /// A new empty store; `indexed` starts the quota index (see
+    /// `set_partition_max_fraction`).
+    fn build(
+        max_bytes: u64,
+        initial_capacity: usize,
+        time_to_idle: Option<Duration>,
+        indexed: bool,
+        eviction_sink: &EvictionSinkSlot<K, V>,
+        events: &CacheEventSlot<K>,
+    ) -> Store<K, V> {
+        let accounting: Arc<PartitionAccounting<K>> = Arc::default();
+        if indexed {
+            accounting.build_index(std::iter::empty());
+        }
+        let partitions = Arc::clone(&accounting);
+        let eviction_sink = Arc::clone(eviction_sink);
+        let events = Arc::clone(events);
+        let mut builder = Cache::builder()
+            .max_capacity(max_bytes)
+            .weigher(|_key: &K, entry: &Charged<V>| -> u32 {
+                entry.weight.try_into().unwrap_or(u32::MAX)
+            })
+            .eviction_listener(move |key: Arc<K>, entry: Charged<V>, cause: RemovalCause| {
+                if let Some(partition) = key.partition() {
+                    partitions.forget(&key, partition, entry.seq, entry.weight);
+                }
+                // A replacement is not a removal as far as the caller is concerned
+                if cause == RemovalCause::Replaced {
+                    return;
+                }
+                if let Some((tx, _)) = events.read().as_ref() {
+                    // Full channel: drop the event rather than block maintenance
+                    let _ = tx.try_send(((*key).clone(), cause));
+                }
+                if cause == RemovalCause::Size {
+                    if let Some(sink) = eviction_sink.read().as_ref() {
+                        sink(&key, &entry.value);
+                    }
+                }
+            });
+        if initial_capacity > 0 {
+            builder = builder.initial_capacity(initial_capacity);
+        }
+        if let Some(idle) = time_to_idle {
+            builder = builder.time_to_idle(idle);
+        }
+        Store {
+            cache: builder.build(),
+            partitions: accounting,
+        }
+    }
+
+    /// Replace the cache with an empty one presized for `initial_capacity` entries.
+    ///
+    /// moka fixes the hash table size at build time, so this is how a caller
+    /// that knows its working set (e.g. on slide open) avoids rehashing during
+    /// the first burst of inserts. Like `clear`, this drops all entries and
+    /// resets hit/miss counters. The new cache comes with its own partition
+    /// accounting, so the old one's removals can't skew it, and an insert
+    /// racing the swap is retried into the new cache (see `insert`).
+    pub fn rebuild(&self, initial_capacity: usize) {
+        let fresh = Self::build(
+            self.max_bytes,
+            initial_capacity,
+            *self.time_to_idle.lock(),
+            self.partition_quota().is_some(),
+            &self.eviction_sink,
+            &self.events,
+        );
+        let guard = epoch::pin();
+        let old = self.inner.swap(Owned::new(fresh), Ordering::SeqCst, &guard);
+        self.initial_capacity.store(initial_capacity, Ordering::Relaxed);
+        self.reset_stats();
+        // SAFETY: `old` was just unlinked, so no new reader can reach it, and
+        // readers that pinned before the swap keep it alive until they unpin
+        unsafe {
+            old.deref().cache.invalidate_all();
+            guard.defer_destroy(old);
+        }
+    }
+
+    /// The current store, kept alive until the handle is dropped even if
+    /// `rebuild` swaps in another meanwhile.
+    fn pinned(&self) -> Pinned<'_, Store<K, V>> {
+        let guard = epoch::pin();
+        let ptr = self.inner.load(Ordering::SeqCst, &guard).as_raw();
+        Pinned {
+            ptr,
+            guard,
+            _owner: PhantomData,
+        }
+    }
+
+    /// Whether `pinned` is still the current store (no `rebuild` since).
+    fn is_current(&self, pinned: &Pinned<'_, Store<K, V>>) -> bool {
+        std::ptr::eq(self.inner.load(Ordering::SeqCst, &pinned.guard).as_raw(), pinned.ptr)
+    }
+
+    /// Charge `bytes` on top of `Weighted::size_bytes` for every entry
+    /// inserted from now on (0 by default).
+    ///
+    /// Without it, many small entries overshoot `max_bytes` in real memory by
+    /// the per-entry struct and bookkeeping cost.
+    pub fn set_entry_overhead(&self, bytes: u64) {
+        self.entry_overhead.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Weight to charge `value` at insert; stored with the entry.
+    fn weight(&self, value: &V) -> u64 {
+        Weighted::size_bytes(value) as u64 + self.entry_overhead.load(Ordering::Relaxed)
+    }
+
+    /// Evict entries that go `idle` without a read or write (None = never,
+    /// the default).
+    ///
+    /// moka fixes expiry at build time, so this rebuilds the cache like
+    /// `rebuild` (same initial capacity), dropping all entries. Idle entries
+    /// are removed with cause `Expired`; they are never passed to the
+    /// eviction sink.
+    pub fn set_time_to_idle(&self, idle: Option<Duration>) {
+        *self.time_to_idle.lock() = idle;
+        self.rebuild(self.initial_capacity.load(Ordering::Relaxed));
+    }
+
+    /// Initial entry capacity the cache was last built with (used in tests).
+    #[allow(dead_code)]
+    pub fn initial_capacity(&self) -> usize {
+        self.initial_capacity.load(Ordering::Relaxed)
+    }
+
     /// Get a value from the cache.
     ///
     /// Returns None if the key is not cached.
     pub fn get(&self, key: &K) -> Option<V> {
-        if let Some(value) = self.inner.get(key) {
+        if let Some(entry) = self.pinned().cache.get(key) {
             self.hits.fetch_add(1, Ordering::Relaxed);
-            Some(value)
+            Some(entry.value)
         } else {
             self.misses.fetch_add(1, Ordering::Relaxed);
             None
@@ -158,13 +751,213 @@ where
     /// Insert a value into the cache.
     ///
     /// Eviction is handled internally by moka when capacity is exceeded.
+    /// If a partition quota is set and the key's partition would exceed it,
+    /// that partition's own oldest entries are evicted first (other partitions
+    /// are never touched). A value larger than the whole quota is rejected.
+    ///
+    /// An insert that races a `rebuild` and lands in the swapped-out cache
+    /// is repeated in the new one, so it isn't dropped with the old cache.
     pub fn insert(&self, key: K, value: V) {
-        self.inner.insert(key, value);
+        loop {
+            let inner = self.pinned();
+            self.insert_into(&inner, key.clone(), value.clone());
+            if self.is_current(&inner) {
+                return;
+            }
+        }
+    }
+
+    /// Insert a group of values, then run moka's maintenance once.
+    ///
+    /// For bulk loads: moka queues housekeeping on every insert, and applying
+    /// it once per group instead of piecemeal cuts per-insert overhead. The
+    /// entries are visible to `contains` on return.
+    pub fn insert_many(&self, entries: Vec<(K, V)>) {
+        loop {
+            let inner = self.pinned();
+            for (key, value) in &entries {
+                self.insert_into(&inner, key.clone(), value.clone());
+            }
+            inner.cache.run_pending_tasks();
+            // Raced a `rebuild`: repeat the group in the new cache, as `insert` does
+            if self.is_current(&inner) {
+                return;
+            }
+        }
+    }
+
+    fn insert_into(&self, inner: &Store<K, V>, key: K, value: V) {
+        let weight = self.weight(&value);
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        if let Some(partition) = key.partition() {
+            if let Some(quota) = self.partition_quota() {
+                if weight > quota {
+                    return;
+                }
+                let victims = inner.partitions.admit(&key, partition, seq, weight, quota);
+                // Invalidate outside the lock: the eviction listener takes it too
+                for victim in &victims {
+                    inner.cache.invalidate(victim);
+                }
+            }
+            // Accounted before the insert, so an eviction racing in right
+            // after it always finds the bytes to subtract
+            inner.partitions.add(partition, weight);
+        }
+        inner.cache.insert(key, Charged { value, weight, seq });
+    }
+
+    /// Hand entries evicted for capacity to `sink` (None to stop).
+    ///
+    /// Explicit removals (`clear`, quota evictions, invalidation) are not
+    /// passed on. The sink runs on whichever thread performs moka's
+    /// maintenance, so it must not block.
+    pub fn set_eviction_sink(&self, sink: Option<EvictionSink<K, V>>) {
+        *self.eviction_sink.write() = sink;
+    }
+
+    /// Record removals (capacity evictions, expiry, explicit invalidation)
+    /// for `drain_events`.
+    ///
+    /// Off by default. Events are buffered in a bounded channel; once it
+    /// holds `CACHE_EVENT_CAPACITY` events, new ones are dropped until the
+    /// next drain. Disabling discards anything buffered.
+    pub fn set_events_enabled(&self, enabled: bool) {
+        let mut events = self.events.write();
+        if enabled {
+            events.get_or_insert_with(|| crossbeam_channel::bounded(CACHE_EVENT_CAPACITY));
+        } else {
+            *events = None;
+        }
+    }
+
+    /// Take the removal events recorded since the last drain, oldest first.
+    ///
+    /// Runs moka's pending maintenance first so recent evictions are included.
+    pub fn drain_events(&self) -> Vec<(K, RemovalCause)> {
+        self.pinned().cache.run_pending_tasks();
+        match self.events.read().as_ref() {
+            Some((_, rx)) => rx.try_iter().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Limit any one partition to `fraction` of the cache size (1.0 = no limit).
+    ///
+    /// Quota admission needs each partition's keys in insertion order, so
+    /// that index is built from the resident entries when a quota is first
+    /// set and dropped again when it is lifted.
+    pub fn set_partition_max_fraction(&self, fraction: f64) {
+        let fraction = fraction.clamp(0.0, 1.0);
+        let inner = self.pinned();
+        if fraction < 1.0 {
+            inner.cache.run_pending_tasks();
+            inner.partitions.build_index(
+                inner
+                    .cache
+                    .iter()
+                    .map(|(key, entry)| ((*key).clone(), entry.seq, entry.weight)),
+            );
+        } else {
+            inner.partitions.drop_index();
+        }
+        self.partition_max_fraction
+            .store(fraction.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Largest fraction of the cache one partition may hold.
+    pub fn partition_max_fraction(&self) -> f64 {
+        f64::from_bits(self.partition_max_fraction.load(Ordering::Relaxed))
+    }
+
+    fn partition_quota(&self) -> Option<u64> {
+        let fraction = self.partition_max_fraction();
+        (fraction < 1.0).then_some((self.max_bytes as f64 * fraction) as u64)
+    }
+
+    /// Evict `partition`'s oldest entries until at least `bytes` are freed.
+    ///
+    /// Returns the bytes actually freed (less if the partition runs out).
+    /// Pending maintenance is run so the freed space is usable on return.
+    /// Without a quota there is no insertion-order index, so this walks the
+    /// cache to find them.
+    pub fn evict_partition_oldest(&self, partition: u64, bytes: u64) -> u64 {
+        let inner = self.pinned();
+        if inner.partitions.bytes(partition) == 0 {
+            return 0;
+        }
+        let (victims, freed) = if inner.partitions.indexed.load(Ordering::Acquire) {
+            match inner.partitions.index.lock().get_mut(&partition) {
+                Some(usage) => usage.evict_oldest(bytes),
+                None => (Vec::new(), 0),
+            }
+        } else {
+            Self::oldest_in_partition(&inner.cache, partition, bytes)
+        };
+        for victim in &victims {
+            inner.cache.invalidate(victim);
+        }
+        inner.cache.run_pending_tasks();
+        freed
+    }
+
+    /// `partition`'s oldest resident keys holding at least `bytes` (fewer if
+    /// it runs out), with the bytes they hold.
+    fn oldest_in_partition(
+        inner: &Cache<K, Charged<V>>,
+        partition: u64,
+        bytes: u64,
+    ) -> (Vec<K>, u64) {
+        let mut resident: Vec<(u64, u64, Arc<K>)> = inner
+            .iter()
+            .filter(|(key, _)| key.partition() == Some(partition))
+            .map(|(key, entry)| (entry.seq, entry.weight, key))
+            .collect();
+        resident.sort_unstable_by_key(|&(seq, ..)| seq);
+
+        let mut victims = Vec::new();
+        let mut freed = 0;
+        for (_, weight, key) in resident {
+            if freed >= bytes {
+                break;
+            }
+            freed += weight;
+            victims.push((*key).clone());
+        }
+        (victims, freed)
+    }
+
+    /// Drop every entry of `partition`; returns the bytes freed.
+    pub fn clear_partition(&self, partition: u64) -> u64 {
+        self.evict_partition_oldest(partition, u64::MAX)
+    }
+
+    /// Apply pending inserts and evictions so `contains` reflects them.
+    pub fn run_pending_tasks(&self) {
+        self.pinned().cache.run_pending_tasks();
+    }
+
+    /// Capacity in bytes not currently in use.
+    pub fn free_bytes(&self) -> u64 {
+        let inner = self.pinned();
+        inner.cache.run_pending_tasks();
+        self.max_bytes.saturating_sub(inner.cache.weighted_size())
+    }
+
+    /// Size limit in bytes (weighted capacity).
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Bytes currently accounted to `partition` (used in tests).
+    #[allow(dead_code)]
+    pub fn partition_bytes(&self, partition: u64) -> u64 {
+        self.pinned().partitions.bytes(partition)
     }
 
     /// Check if a key is in the cache.
     pub fn contains(&self, key: &K) -> bool {
-        self.inner.contains_key(key)
+        self.pinned().cache.contains_key(key)
     }
 
     /// Clear the cache.
@@ -172,8 +965,10 @@ where
     /// Runs pending eviction tasks synchronously so entries are gone before
     /// return, and resets hit/miss counters so each slide starts fresh.
     pub fn clear(&self) {
-        self.inner.invalidate_all();
-        self.inner.run_pending_tasks();
+        let inner = self.pinned();
+        inner.cache.invalidate_all();
+        inner.cache.run_pending_tasks();
+        inner.partitions.reset();
         self.reset_stats();
     }
 
@@ -188,7 +983,8 @@ where
     /// Runs pending moka maintenance first so `entry_count()` and
     /// `weighted_size()` reflect the latest inserts/evictions.
     pub fn stats(&self) -> CacheStats {
-        self.inner.run_pending_tasks();
+        let inner = self.pinned();
+        inner.cache.run_pending_tasks();
         let hits = self.hits.load(Ordering::Relaxed);
         let misses = self.misses.load(Ordering::Relaxed);
         let total = hits + misses;
@@ -197,20 +993,245 @@ where
             hits,
             misses,
             hit_ratio,
-            size_bytes: self.inner.weighted_size() as usize,
-            num_tiles: self.inner.entry_count() as usize,
+            size_bytes: inner.cache.weighted_size() as usize,
+            num_tiles: inner.cache.entry_count() as usize,
         }
     }
 
     /// Check if cache is empty (used in tests).
     #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
-        self.inner.entry_count() == 0
+        self.pinned().cache.entry_count() == 0
+    }
+}
+
+impl<K, V> TrackedCache<K, V>
+where
+    K: PartitionKey + TileKey + Hash + Eq + Send + Sync + Clone + 'static,
+    V: Weighted,
+{
+    /// Snapshot entry counts, per-level and per-partition residency.
+    ///
+    /// Walks every resident entry, so this is for diagnostics only.
+    pub fn debug(&self) -> CacheDebug {
+        let inner = self.pinned();
+        inner.cache.run_pending_tasks();
+
+        let mut level_counts = BTreeMap::new();
+        // Partition → (residency, oldest seq, newest seq)
+        let mut resident: BTreeMap<u64, (PartitionDebug, u64, u64)> = BTreeMap::new();
+        for (key, entry) in inner.cache.iter() {
+            let coord = key.tile_coord();
+            *level_counts.entry(coord.level).or_insert(0) += 1;
+            let Some(partition) = key.partition() else {
+                continue;
+            };
+            let (debug, oldest, newest) = resident
+                .entry(partition)
+                .or_insert((PartitionDebug::default(), u64::MAX, 0));
+            debug.entries += 1;
+            debug.bytes += entry.weight;
+            if entry.seq <= *oldest {
+                *oldest = entry.seq;
+                debug.oldest = Some(coord);
+            }
+            if entry.seq >= *newest {
+                *newest = entry.seq;
+                debug.newest = Some(coord);
+            }
+        }
+        let partitions = resident
+            .into_iter()
+            .map(|(id, (debug, ..))| (id, debug))
+            .collect();
+
+        CacheDebug {
+            entry_count: inner.cache.entry_count(),
+            weighted_size: inner.cache.weighted_size(),
+            max_bytes: self.max_bytes,
+            level_counts,
+            partitions,
+        }
+    }
+
+    /// Resident tiles and weighted bytes per pyramid level.
+    ///
+    /// Walks every resident entry, like `debug`.
+    pub fn level_usage(&self) -> BTreeMap<u32, LevelUsage> {
+        let inner = self.pinned();
+        inner.cache.run_pending_tasks();
+
+        let mut usage = BTreeMap::new();
+        for (key, entry) in inner.cache.iter() {
+            let level: &mut LevelUsage = usage.entry(key.tile_coord().level).or_default();
+            level.num_tiles += 1;
+            level.size_bytes += entry.weight as usize;
+        }
+        usage
+    }
+}
+
+impl<K, V> Drop for TrackedCache<K, V>
+where
+    K: PartitionKey + Hash + Eq + Send + Sync + Clone + 'static,
+    V: Weighted,
+{
+    fn drop(&mut self) {
+        let inner = std::mem::replace(&mut self.inner, Atomic::null());
+        // SAFETY: `&mut self` means no `Pinned` handle is outstanding
+        drop(unsafe { inner.into_owned() });
     }
 }
 
 /// L1 decoded RGB tile cache — cleared on slide switch.
-pub type TileCache = TrackedCache<TileCoord, TileData>;
+///
+/// With LZ4 enabled, tiles are compressed on insert and decompressed on
+/// `get`: a cheap decompress (much faster than a JPEG decode from L2) buys
+/// roughly double the tile residency for the same memory budget. Toggling
+/// LZ4 only affects new inserts; existing entries are served either way.
+///
+/// Entries are keyed by the active decode transform as well as the tile, so
+/// switching transforms never serves tiles rendered by another one.
+pub struct TileCache {
+    tiles: TrackedCache<L1Key, L1Tile>,
+    lz4: AtomicBool,
+    /// `TileTransform::key` of the active transform.
+    transform: AtomicU64,
+}
+
+impl TileCache {
+    /// Create a new L1 cache with the given size limit in megabytes.
+    ///
+    /// Entries are charged `L1_ENTRY_OVERHEAD_BYTES` on top of their pixel
+    /// data, so `max_size_mb` bounds real memory for small tiles too.
+    pub fn new(max_size_mb: usize) -> Self {
+        let tiles = TrackedCache::new(max_size_mb);
+        tiles.set_entry_overhead(L1_ENTRY_OVERHEAD_BYTES);
+        Self {
+            tiles,
+            lz4: AtomicBool::new(false),
+            transform: AtomicU64::new(0),
+        }
+    }
+
+    /// Key subsequent lookups and inserts by `TileTransform::key`.
+    pub fn set_transform_key(&self, key: u64) {
+        self.transform.store(key, Ordering::Release);
+    }
+
+    fn key(&self, coord: TileCoord) -> L1Key {
+        self.key_for(0, coord)
+    }
+
+    fn key_for(&self, slide: u64, coord: TileCoord) -> L1Key {
+        L1Key {
+            coord,
+            transform: self.transform.load(Ordering::Acquire),
+            slide,
+        }
+    }
+
+    /// See [`TrackedCache::set_entry_overhead`].
+    pub fn set_entry_overhead(&self, bytes: u64) {
+        self.tiles.set_entry_overhead(bytes);
+    }
+
+    /// See [`TrackedCache::set_time_to_idle`].
+    pub fn set_time_to_idle(&self, idle: Option<Duration>) {
+        self.tiles.set_time_to_idle(idle);
+    }
+
+    /// Store newly inserted tiles LZ4-compressed.
+    pub fn set_lz4(&self, enabled: bool) {
+        self.lz4.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether new inserts are LZ4-compressed.
+    pub fn lz4(&self) -> bool {
+        self.lz4.load(Ordering::Relaxed)
+    }
+
+    /// See [`TrackedCache::rebuild`].
+    pub fn rebuild(&self, initial_capacity: usize) {
+        self.tiles.rebuild(initial_capacity);
+    }
+
+    /// Initial entry capacity the cache was last built with (used in tests).
+    #[allow(dead_code)]
+    pub fn initial_capacity(&self) -> usize {
+        self.tiles.initial_capacity()
+    }
+
+    /// Get a decoded tile, decompressing it if stored as LZ4.
+    pub fn get(&self, key: &TileCoord) -> Option<TileData> {
+        self.tiles.get(&self.key(*key)).and_then(L1Tile::unpack)
+    }
+
+    /// Insert a decoded tile.
+    pub fn insert(&self, key: TileCoord, tile: TileData) {
+        self.tiles.insert(self.key(key), L1Tile::pack(tile, self.lz4()));
+    }
+
+    /// Check if a tile is in the cache.
+    pub fn contains(&self, key: &TileCoord) -> bool {
+        self.tiles.contains(&self.key(*key))
+    }
+
+    /// `get` for a slide other than the active one (see `L1Key::slide`).
+    pub fn get_for(&self, slide: u64, key: &TileCoord) -> Option<TileData> {
+        self.tiles.get(&self.key_for(slide, *key)).and_then(L1Tile::unpack)
+    }
+
+    /// `insert` for a slide other than the active one.
+    pub fn insert_for(&self, slide: u64, key: TileCoord, tile: TileData) {
+        self.tiles.insert(self.key_for(slide, key), L1Tile::pack(tile, self.lz4()));
+    }
+
+    /// See [`TrackedCache::clear`].
+    pub fn clear(&self) {
+        self.tiles.clear();
+    }
+
+    /// See [`TrackedCache::set_events_enabled`].
+    pub fn set_events_enabled(&self, enabled: bool) {
+        self.tiles.set_events_enabled(enabled);
+    }
+
+    /// Tiles removed since the last drain with their `removal_reason`, oldest first.
+    pub fn drain_events(&self) -> Vec<(TileCoord, &'static str)> {
+        self.tiles
+            .drain_events()
+            .into_iter()
+            .map(|(key, cause)| (key.coord, removal_reason(cause)))
+            .collect()
+    }
+
+    /// Reset hit/miss counters to zero.
+    pub fn reset_stats(&self) {
+        self.tiles.reset_stats();
+    }
+
+    /// Get cache statistics (`size_bytes` counts stored, i.e. compressed,
+    /// bytes plus the per-entry overhead).
+    pub fn stats(&self) -> CacheStats {
+        self.tiles.stats()
+    }
+
+    /// Check if cache is empty (used in tests).
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    pub fn debug(&self) -> CacheDebug {
+        self.tiles.debug()
+    }
+
+    /// See [`TrackedCache::level_usage`] (sizes are weighted bytes, as in `stats`).
+    pub fn level_usage(&self) -> BTreeMap<u32, LevelUsage> {
+        self.tiles.level_usage()
+    }
+}
 
 /// L2 compressed JPEG cache — persists across slide switches.
 ///
@@ -218,14 +1239,22 @@ pub type TileCache = TrackedCache<TileCoord, TileData>;
 /// Tiles from different slides are disambiguated by `SlideTileCoord::slide_id()`.
 pub type CompressedTileCache = TrackedCache<SlideTileCoord, CompressedTileData>;
 
+/// Encoded slide thumbnails — like L2, persists across slide switches.
+pub type ThumbnailCache = TrackedCache<ThumbnailKey, Bytes>;
+
+/// Raw RGB slide overviews (`get_thumbnail_rgb`), held by `SlidePool`.
+pub type OverviewCache = TrackedCache<OverviewKey, TileData>;
+
 /// Compute a slide identifier by hashing its path string.
 ///
-/// Uses `DefaultHasher` (SipHash-2-4). Not stable across Rust versions,
-/// but that's fine — the L2 cache is in-memory only, no persistence.
+/// Uses 64-bit FNV-1a over the UTF-8 bytes, so IDs are reproducible across
+/// processes, builds and Rust versions (unlike `DefaultHasher`) and can be
+/// persisted alongside cached data.
 pub fn compute_slide_id(path: &str) -> u64 {
-    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-    path.hash(&mut hasher);
-    hasher.finish()
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    path.bytes()
+        .fold(FNV_OFFSET, |h, b| (h ^ b as u64).wrapping_mul(FNV_PRIME))
 }
 
 #[cfg(test)]
@@ -323,7 +1352,7 @@ mod tests {
         let coord = TileCoord::new(0, 1, 2);
         cache.insert(coord, make_tile(100));
         // Force moka to process the insert
-        cache.inner.run_pending_tasks();
+        cache.tiles.run_pending_tasks();
 
         // Generate hits and misses
         cache.get(&coord);
@@ -346,6 +1375,131 @@ mod tests {
         assert_eq!(stats.num_tiles, 0);
     }
 
+    #[test]
+    fn test_cache_rebuild() {
+        let cache = TileCache::new(10);
+        let coord = TileCoord::new(0, 1, 2);
+        cache.insert(coord, make_tile(100));
+        cache.get(&coord);
+        assert_eq!(cache.initial_capacity(), 0);
+
+        cache.rebuild(64);
+
+        assert_eq!(cache.initial_capacity(), 64);
+        assert!(!cache.contains(&coord));
+        assert_eq!(cache.stats().hits, 0);
+
+        // Still a working, size-bounded cache
+        cache.insert(coord, make_tile(100));
+        assert!(cache.get(&coord).is_some());
+        assert_eq!(cache.stats().size_bytes as u64, 100 + L1_ENTRY_OVERHEAD_BYTES);
+    }
+
+    #[test]
+    #[ignore = "timing benchmark; run with --ignored --nocapture"]
+    fn bench_open_presized_vs_cleared() {
+        use std::time::Instant;
+
+        // A slide open: drop the old slide's tiles, then the first burst of
+        // inserts and reads for the new one
+        const TILES: u32 = 4096;
+        let open = |presize: bool| {
+            let cache = TileCache::new(64);
+            for i in 0..TILES {
+                cache.insert(TileCoord::new(1, i % 64, i / 64), make_tile(64));
+            }
+            let start = Instant::now();
+            if presize {
+                cache.rebuild(TILES as usize);
+            } else {
+                cache.clear();
+            }
+            for i in 0..TILES {
+                cache.insert(TileCoord::new(0, i % 64, i / 64), make_tile(64));
+            }
+            for i in 0..TILES {
+                cache.get(&TileCoord::new(0, i % 64, i / 64));
+            }
+            cache.tiles.run_pending_tasks();
+            assert_eq!(cache.stats().num_tiles, TILES as usize);
+            start.elapsed().as_secs_f64() * 1000.0
+        };
+
+        let cleared_ms = open(false);
+        let presized_ms = open(true);
+        eprintln!(
+            "[BENCH] open + {TILES} L1 inserts/gets: cleared {cleared_ms:.1}ms, \
+             presized {presized_ms:.1}ms"
+        );
+    }
+
+    #[test]
+    fn test_cache_events_report_evictions() {
+        let cache = TileCache::new(1);
+        // Disabled by default
+        cache.insert(TileCoord::new(0, 0, 0), make_tile(100));
+        cache.clear();
+        assert!(cache.drain_events().is_empty());
+
+        cache.set_events_enabled(true);
+        for col in 0..20 {
+            cache.insert(TileCoord::new(0, col, 0), make_tile(100 * 1024));
+        }
+        let events = cache.drain_events();
+        assert!(!events.is_empty());
+        assert!(events.iter().all(|&(coord, reason)| coord.level == 0 && reason == "size"));
+        assert!(cache.drain_events().is_empty());
+
+        cache.clear();
+        let events = cache.drain_events();
+        assert!(!events.is_empty());
+        assert!(events.iter().all(|&(_, reason)| reason == "explicit"));
+
+        cache.set_events_enabled(false);
+        cache.insert(TileCoord::new(0, 0, 0), make_tile(100));
+        cache.clear();
+        assert!(cache.drain_events().is_empty());
+    }
+
+    #[test]
+    fn test_lz4_tiles_roundtrip() {
+        let cache = TileCache::new(10);
+        cache.set_lz4(true);
+
+        // Smooth gradient: compressible, like tissue background
+        let pixels: Vec<u8> = (0..256 * 256 * 3).map(|i| (i / 768) as u8).collect();
+        let coord = TileCoord::new(0, 1, 2);
+        cache.insert(coord, TileData::new(pixels.clone(), 256, 256));
+
+        let stats = cache.stats();
+        assert!(stats.size_bytes < pixels.len() / 2);
+        let tile = cache.get(&coord).unwrap();
+        assert_eq!((tile.width, tile.height), (256, 256));
+        assert_eq!(tile.data.as_ref(), pixels.as_slice());
+    }
+
+    #[test]
+    fn test_lz4_incompressible_tile_stored_raw() {
+        let cache = TileCache::new(10);
+        cache.set_lz4(true);
+
+        // Pseudo-random bytes don't compress; stored raw at full size
+        let mut state = 0x2545_f491u32;
+        let pixels: Vec<u8> = (0..3000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let coord = TileCoord::new(0, 0, 0);
+        cache.insert(coord, TileData::new(pixels.clone(), 1000, 1));
+
+        assert_eq!(cache.stats().size_bytes as u64, 3000 + L1_ENTRY_OVERHEAD_BYTES);
+        assert_eq!(cache.get(&coord).unwrap().data.as_ref(), pixels.as_slice());
+    }
+
     // --- TileCoord Display ---
 
     #[test]
@@ -377,6 +1531,7 @@ mod tests {
     #[test]
     fn test_slide_tile_coord_hash_differs_by_slide_id() {
         use std::collections::hash_map::DefaultHasher;
+        use std::hash::Hasher;
 
         let hash_of = |c: &SlideTileCoord| -> u64 {
             let mut h = DefaultHasher::new();
@@ -412,6 +1567,13 @@ mod tests {
         assert_ne!(id1, id2);
     }
 
+    #[test]
+    fn test_compute_slide_id_stable() {
+        // Pinned: persisted caches depend on this never changing
+        assert_eq!(compute_slide_id("/slides/test.fastpath"), 0x405e84853c626074);
+        assert_eq!(compute_slide_id(""), 0xcbf2_9ce4_8422_2325);
+    }
+
     #[test]
     fn test_compute_slide_id_empty_string() {
         // Should not panic
@@ -515,7 +1677,7 @@ mod tests {
         assert!(cache.is_empty());
 
         cache.insert(SlideTileCoord::new(1, 0, 0, 0), make_compressed_tile(100));
-        cache.inner.run_pending_tasks();
+        cache.run_pending_tasks();
         assert!(!cache.is_empty());
     }
 
@@ -524,7 +1686,7 @@ mod tests {
         let cache = CompressedTileCache::new(10);
         let coord = SlideTileCoord::new(1, 0, 1, 2);
         cache.insert(coord, make_compressed_tile(100));
-        cache.inner.run_pending_tasks();
+        cache.run_pending_tasks();
 
         // Generate some stats
         cache.get(&coord);
@@ -551,12 +1713,287 @@ mod tests {
         let cache = CompressedTileCache::new(10);
         let coord = SlideTileCoord::new(1, 0, 0, 0);
         cache.insert(coord, make_compressed_tile(2048));
-        cache.inner.run_pending_tasks();
+        cache.run_pending_tasks();
 
         let stats = cache.stats();
         assert_eq!(stats.size_bytes, 2048);
     }
 
+    #[test]
+    fn test_compressed_cache_slide_quota_evicts_own_tiles() {
+        // 1 MB cache, each slide limited to half
+        let cache = CompressedTileCache::new(1);
+        cache.set_partition_max_fraction(0.5);
+        let tile_size = 100 * 1024;
+
+        for col in 0..3 {
+            cache.insert(SlideTileCoord::new(2, 0, col, 0), make_compressed_tile(tile_size));
+        }
+        for col in 0..5 {
+            cache.insert(SlideTileCoord::new(1, 0, col, 0), make_compressed_tile(tile_size));
+        }
+        assert_eq!(cache.partition_bytes(1), 5 * tile_size as u64);
+
+        // Slide 1 is at quota: its oldest tile goes, slide 2 is untouched
+        cache.insert(SlideTileCoord::new(1, 0, 5, 0), make_compressed_tile(tile_size));
+        assert!(!cache.contains(&SlideTileCoord::new(1, 0, 0, 0)));
+        assert!((1..6).all(|col| cache.contains(&SlideTileCoord::new(1, 0, col, 0))));
+        assert!((0..3).all(|col| cache.contains(&SlideTileCoord::new(2, 0, col, 0))));
+        assert_eq!(cache.partition_bytes(1), 5 * tile_size as u64);
+        assert_eq!(cache.partition_bytes(2), 3 * tile_size as u64);
+
+        // Re-inserting an existing key doesn't evict anything
+        cache.insert(SlideTileCoord::new(1, 0, 5, 0), make_compressed_tile(tile_size));
+        assert!(cache.contains(&SlideTileCoord::new(1, 0, 1, 0)));
+
+        // A tile bigger than the whole quota is rejected
+        cache.insert(SlideTileCoord::new(3, 0, 0, 0), make_compressed_tile(600 * 1024));
+        assert!(!cache.contains(&SlideTileCoord::new(3, 0, 0, 0)));
+    }
+
+    #[test]
+    fn test_quota_set_later_indexes_resident_tiles() {
+        let cache = CompressedTileCache::new(1);
+        let tile_size = 100 * 1024;
+        for col in 0..5 {
+            cache.insert(SlideTileCoord::new(1, 0, col, 0), make_compressed_tile(tile_size));
+        }
+        // Replacing a tile re-charges it rather than adding to the partition
+        cache.insert(SlideTileCoord::new(1, 0, 0, 0), make_compressed_tile(tile_size));
+        assert_eq!(cache.partition_bytes(1), 5 * tile_size as u64);
+
+        // Col 1 is now the oldest, since col 0 was just re-inserted
+        cache.set_partition_max_fraction(0.5);
+        cache.insert(SlideTileCoord::new(1, 0, 5, 0), make_compressed_tile(tile_size));
+        assert!(!cache.contains(&SlideTileCoord::new(1, 0, 1, 0)));
+        assert!(cache.contains(&SlideTileCoord::new(1, 0, 0, 0)));
+        assert_eq!(cache.partition_bytes(1), 5 * tile_size as u64);
+
+        // Lifting the quota stops evicting on insert
+        cache.set_partition_max_fraction(1.0);
+        cache.insert(SlideTileCoord::new(1, 0, 6, 0), make_compressed_tile(tile_size));
+        assert!(cache.contains(&SlideTileCoord::new(1, 0, 2, 0)));
+        assert_eq!(cache.partition_bytes(1), 6 * tile_size as u64);
+    }
+
+    #[test]
+    fn test_rebuild_keeps_partition_accounting_apart() {
+        let cache = CompressedTileCache::new(1);
+        cache.set_partition_max_fraction(0.5);
+        let tile_size = 100 * 1024;
+        for col in 0..5 {
+            cache.insert(SlideTileCoord::new(1, 0, col, 0), make_compressed_tile(tile_size));
+        }
+
+        // A reader still holding the old cache across the swap
+        let old = cache.pinned();
+        cache.rebuild(16);
+        assert!(!cache.is_current(&old));
+        for col in 0..2 {
+            cache.insert(SlideTileCoord::new(1, 0, col, 0), make_compressed_tile(tile_size));
+        }
+
+        // The old cache's removals drain late; they must not touch the new totals
+        old.cache.run_pending_tasks();
+        drop(old);
+        assert_eq!(cache.partition_bytes(1), 2 * tile_size as u64);
+
+        // The quota still holds in the rebuilt cache
+        for col in 2..6 {
+            cache.insert(SlideTileCoord::new(1, 0, col, 0), make_compressed_tile(tile_size));
+        }
+        assert_eq!(cache.partition_bytes(1), 5 * tile_size as u64);
+        assert!(!cache.contains(&SlideTileCoord::new(1, 0, 0, 0)));
+    }
+
+    #[test]
+    fn test_evict_partition_oldest_frees_space() {
+        let cache = CompressedTileCache::new(1);
+        let tile_size = 100 * 1024;
+        for col in 0..4 {
+            cache.insert(SlideTileCoord::new(1, 0, col, 0), make_compressed_tile(tile_size));
+        }
+        cache.insert(SlideTileCoord::new(2, 0, 0, 0), make_compressed_tile(tile_size));
+        let free = cache.free_bytes();
+        assert_eq!(free, 1024 * 1024 - 5 * tile_size as u64);
+
+        // Oldest first, rounded up to whole tiles; other slides untouched
+        let freed = cache.evict_partition_oldest(1, tile_size as u64 + 1);
+        assert_eq!(freed, 2 * tile_size as u64);
+        assert_eq!(cache.free_bytes(), free + freed);
+        assert!(!cache.contains(&SlideTileCoord::new(1, 0, 0, 0)));
+        assert!(!cache.contains(&SlideTileCoord::new(1, 0, 1, 0)));
+        assert!(cache.contains(&SlideTileCoord::new(1, 0, 2, 0)));
+        assert!(cache.contains(&SlideTileCoord::new(2, 0, 0, 0)));
+
+        // Asking for more than the partition holds frees what's there
+        assert_eq!(cache.evict_partition_oldest(1, u64::MAX), 2 * tile_size as u64);
+        assert_eq!(cache.partition_bytes(1), 0);
+        assert_eq!(cache.evict_partition_oldest(7, 1), 0);
+    }
+
+    #[test]
+    fn test_debug_counts_match_inserts() {
+        let cache = CompressedTileCache::new(10);
+        for col in 0..3 {
+            cache.insert(SlideTileCoord::new(1, 0, col, 0), make_compressed_tile(100));
+        }
+        cache.insert(SlideTileCoord::new(1, 2, 0, 0), make_compressed_tile(100));
+        cache.insert(SlideTileCoord::new(2, 2, 5, 6), make_compressed_tile(50));
+
+        let debug = cache.debug();
+        assert_eq!(debug.entry_count, 5);
+        assert_eq!(debug.weighted_size, 450);
+        assert_eq!(debug.max_bytes, 10 * 1024 * 1024);
+        assert_eq!(debug.level_counts, BTreeMap::from([(0, 3), (2, 2)]));
+
+        assert_eq!(debug.partitions.len(), 2);
+        let slide1 = &debug.partitions[&1];
+        assert_eq!((slide1.entries, slide1.bytes), (4, 400));
+        assert_eq!(slide1.oldest, Some(TileCoord::new(0, 0, 0)));
+        assert_eq!(slide1.newest, Some(TileCoord::new(2, 0, 0)));
+        let slide2 = &debug.partitions[&2];
+        assert_eq!((slide2.entries, slide2.bytes), (1, 50));
+        assert_eq!(slide2.oldest, slide2.newest);
+
+        // L1 has no partitions, only level residency
+        let l1 = TileCache::new(10);
+        l1.insert(TileCoord::new(1, 0, 0), make_tile(64));
+        let debug = l1.debug();
+        assert_eq!(debug.level_counts, BTreeMap::from([(1, 1)]));
+        assert!(debug.partitions.is_empty());
+    }
+
+    #[test]
+    fn test_time_to_idle_evicts_unread_tiles() {
+        // moka's clock can't be mocked from here, so the margins are wide
+        // instead: reads come 20x more often than the idle limit, and the
+        // idle tile is checked at 2.5x the limit
+        let idle_limit = Duration::from_secs(1);
+        let cache = TileCache::new(10);
+        cache.set_time_to_idle(Some(idle_limit));
+        let (idle, read) = (TileCoord::new(0, 0, 0), TileCoord::new(0, 1, 0));
+        cache.insert(idle, make_tile(100));
+        cache.insert(read, make_tile(100));
+        // L2 and other caches keep entries indefinitely by default
+        let l2 = CompressedTileCache::new(10);
+        let coord = SlideTileCoord::new(1, 0, 0, 0);
+        l2.insert(coord, make_compressed_tile(100));
+
+        // Reads reset the idle clock; untouched tiles expire
+        for _ in 0..50 {
+            std::thread::sleep(idle_limit / 20);
+            assert!(cache.get(&read).is_some());
+        }
+        assert!(!cache.contains(&idle));
+        assert!(cache.contains(&read));
+        assert_eq!(cache.stats().num_tiles, 1);
+        assert!(l2.contains(&coord));
+    }
+
+    #[test]
+    fn test_weighted_size_includes_entry_overhead() {
+        let l1 = TileCache::new(10);
+        l1.insert(TileCoord::new(0, 0, 0), make_tile(100));
+        assert_eq!(l1.debug().weighted_size, 100 + L1_ENTRY_OVERHEAD_BYTES);
+
+        // Applies to later inserts; unpartitioned caches default to none
+        l1.set_entry_overhead(1000);
+        l1.insert(TileCoord::new(0, 1, 0), make_tile(100));
+        assert_eq!(l1.debug().weighted_size, 1200 + L1_ENTRY_OVERHEAD_BYTES);
+        // Each entry keeps the weight it was charged at insert
+        let charged = l1.level_usage()[&0].size_bytes as u64;
+        assert_eq!(charged, l1.debug().weighted_size);
+        let l2 = CompressedTileCache::new(10);
+        l2.insert(SlideTileCoord::new(1, 0, 0, 0), make_compressed_tile(100));
+        assert_eq!(l2.debug().weighted_size, 100);
+
+        // 4096 16-byte tiles are 64 KiB of pixels, but with the overhead
+        // they no longer fit a 1 MiB budget
+        let small = TileCache::new(1);
+        for i in 0..4096 {
+            small.insert(TileCoord::new(0, i % 64, i / 64), make_tile(16));
+        }
+        let debug = small.debug();
+        assert!(debug.weighted_size <= debug.max_bytes);
+        assert!(debug.entry_count < 4096);
+    }
+
+    #[test]
+    fn test_level_usage_sums_tiles_and_bytes_per_level() {
+        let cache = TileCache::new(10);
+        cache.set_entry_overhead(0);
+        assert!(cache.level_usage().is_empty());
+
+        cache.insert(TileCoord::new(0, 0, 0), make_tile(64));
+        cache.insert(TileCoord::new(0, 1, 0), make_tile(64));
+        cache.insert(TileCoord::new(3, 0, 0), make_tile(32));
+
+        let usage = cache.level_usage();
+        assert_eq!(
+            usage,
+            BTreeMap::from([
+                (0, LevelUsage { num_tiles: 2, size_bytes: 128 }),
+                (3, LevelUsage { num_tiles: 1, size_bytes: 32 }),
+            ])
+        );
+        let total: usize = usage.values().map(|l| l.size_bytes).sum();
+        assert_eq!(total, cache.stats().size_bytes);
+    }
+
+    #[test]
+    fn test_compressed_cache_insert_many_all_present() {
+        let cache = CompressedTileCache::new(10);
+        let coords: Vec<_> = (0..64).map(|i| SlideTileCoord::new(1, 0, i % 8, i / 8)).collect();
+        cache.insert_many(coords.iter().map(|&c| (c, make_compressed_tile(100))).collect());
+
+        assert!(coords.iter().all(|c| cache.contains(c)));
+        assert_eq!(cache.partition_bytes(1), 64 * 100);
+    }
+
+    #[test]
+    #[ignore = "timing benchmark; run with --ignored --nocapture"]
+    fn bench_insert_many_vs_individual() {
+        use std::time::Instant;
+
+        const TILES: u32 = 20_000;
+        let entries = |slide_id| -> Vec<_> {
+            (0..TILES)
+                .map(|i| (SlideTileCoord::new(slide_id, 0, i % 256, i / 256), make_compressed_tile(64)))
+                .collect()
+        };
+
+        let cache = CompressedTileCache::new(64);
+        let start = Instant::now();
+        for (coord, tile) in entries(1) {
+            cache.insert(coord, tile);
+        }
+        cache.run_pending_tasks();
+        let individual_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        let cache = CompressedTileCache::new(64);
+        let start = Instant::now();
+        for batch in entries(1).chunks(256) {
+            cache.insert_many(batch.to_vec());
+        }
+        let batched_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+        eprintln!(
+            "[BENCH] {TILES} L2 inserts: individual {individual_ms:.1}ms, insert_many {batched_ms:.1}ms"
+        );
+        assert_eq!(cache.stats().num_tiles, TILES as usize);
+    }
+
+    #[test]
+    fn test_compressed_cache_no_quota_by_default() {
+        let cache = CompressedTileCache::new(1);
+        assert_eq!(cache.partition_max_fraction(), 1.0);
+        for col in 0..8 {
+            cache.insert(SlideTileCoord::new(1, 0, col, 0), make_compressed_tile(100 * 1024));
+        }
+        assert_eq!(cache.partition_bytes(1), 8 * 100 * 1024);
+    }
+
     #[test]
     fn test_compressed_cache_no_clear_method() {
         // CompressedTileCache (L2) should not be cleared on slide switch.
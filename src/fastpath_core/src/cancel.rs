@@ -0,0 +1,57 @@
+//! Cancellation handle for long reads started from Python.
+//!
+//! Python creates a `CancelToken`, passes it to a read (`decode_region`,
+//! `get_tiles`) and calls `cancel()` from another thread, e.g. when the user
+//! scrolls away. The read checks the flag between tiles.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use pyo3::prelude::*;
+
+use crate::error::{TileError, TileResult};
+
+/// Thread-safe cancel flag shared between Python and a running read.
+#[pyclass(frozen)]
+#[derive(Default)]
+pub struct CancelToken {
+    cancelled: AtomicBool,
+}
+
+impl CancelToken {
+    /// The flag reads poll between tiles.
+    pub fn flag(&self) -> &AtomicBool {
+        &self.cancelled
+    }
+
+    /// `Err(TileError::Cancelled)` once cancelled.
+    pub fn check(&self) -> TileResult<()> {
+        if self.cancelled.load(Ordering::Acquire) {
+            return Err(TileError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl CancelToken {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask reads using this token to stop at the next tile.
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Clear the flag so the token can be reused for another read.
+    fn reset(&self) {
+        self.cancelled.store(false, Ordering::Release);
+    }
+
+    /// Whether cancel() has been called (and not reset since).
+    #[getter]
+    fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+}
@@ -0,0 +1,133 @@
+//! Runtime CPU feature detection for SIMD decode paths.
+//!
+//! zune-jpeg chooses its AVX2 (x86) / NEON (aarch64) IDCT and color
+//! conversion at runtime via `is_x86_feature_detected!` and falls back to
+//! scalar code, so a baseline build runs on any CPU of its architecture.
+//! The hazard is a build compiled with wider target features (e.g.
+//! `-C target-cpu=native`): the compiler may then emit those instructions
+//! anywhere, and an older CPU dies with SIGILL mid-decode. Module import
+//! checks `missing_compiled_features` and refuses to load instead.
+
+/// SIMD features of the running CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuFeatures {
+    pub arch: &'static str,
+    pub sse2: bool,
+    pub sse41: bool,
+    pub avx: bool,
+    pub avx2: bool,
+    pub neon: bool,
+}
+
+impl CpuFeatures {
+    /// Detect features of the CPU we're running on.
+    pub fn detect() -> Self {
+        #[allow(unused_mut)]
+        let mut features = Self {
+            arch: std::env::consts::ARCH,
+            sse2: false,
+            sse41: false,
+            avx: false,
+            avx2: false,
+            neon: false,
+        };
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            features.sse2 = is_x86_feature_detected!("sse2");
+            features.sse41 = is_x86_feature_detected!("sse4.1");
+            features.avx = is_x86_feature_detected!("avx");
+            features.avx2 = is_x86_feature_detected!("avx2");
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            features.neon = std::arch::is_aarch64_feature_detected!("neon");
+        }
+        features
+    }
+
+    /// SIMD path zune-jpeg's runtime dispatch selects for IDCT and color
+    /// conversion on this CPU.
+    pub fn jpeg_simd(&self) -> &'static str {
+        if self.avx2 {
+            "avx2"
+        } else if self.neon {
+            "neon"
+        } else {
+            "scalar"
+        }
+    }
+}
+
+/// Target features this binary was compiled to assume unconditionally.
+pub fn compiled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(target_feature = "sse2") {
+        features.push("sse2");
+    }
+    if cfg!(target_feature = "sse4.1") {
+        features.push("sse4.1");
+    }
+    if cfg!(target_feature = "avx") {
+        features.push("avx");
+    }
+    if cfg!(target_feature = "avx2") {
+        features.push("avx2");
+    }
+    if cfg!(target_feature = "neon") {
+        features.push("neon");
+    }
+    features
+}
+
+/// Compiled-in target features the running CPU lacks (empty = safe to run).
+pub fn missing_compiled_features(cpu: &CpuFeatures) -> Vec<&'static str> {
+    compiled_features()
+        .into_iter()
+        .filter(|&feature| match feature {
+            "sse2" => !cpu.sse2,
+            "sse4.1" => !cpu.sse41,
+            "avx" => !cpu.avx,
+            "avx2" => !cpu.avx2,
+            "neon" => !cpu.neon,
+            _ => false,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_is_consistent_on_build_machine() {
+        let cpu = CpuFeatures::detect();
+        assert_eq!(cpu.arch, std::env::consts::ARCH);
+
+        // Each level implies the ones below it
+        assert!(!cpu.avx2 || cpu.avx);
+        assert!(!cpu.avx || cpu.sse41);
+        assert!(!cpu.sse41 || cpu.sse2);
+        if cfg!(target_arch = "x86_64") {
+            assert!(cpu.sse2, "SSE2 is baseline on x86-64");
+            assert!(!cpu.neon);
+        }
+
+        assert_eq!(cpu.jpeg_simd() == "avx2", cpu.avx2);
+        // Tests run, so this binary's compiled features are all present
+        assert!(missing_compiled_features(&cpu).is_empty());
+    }
+
+    #[test]
+    fn test_missing_compiled_features_on_older_cpu() {
+        let bare = CpuFeatures {
+            arch: std::env::consts::ARCH,
+            sse2: false,
+            sse41: false,
+            avx: false,
+            avx2: false,
+            neon: false,
+        };
+        assert_eq!(bare.jpeg_simd(), "scalar");
+        assert_eq!(missing_compiled_features(&bare), compiled_features());
+    }
+}
@@ -1,85 +1,546 @@
-//! Tile decoding for JPEG format.
+//! Tile decoding for JPEG, PNG, and WebP formats.
 //!
-//! Uses zune-jpeg for fast SIMD-accelerated decoding (~2-3x faster than image crate).
+//! The codec is sniffed per tile from its magic bytes, so a single slide can
+//! mix codecs (e.g. JPEG tiles with lossless PNG re-scans) regardless of file
+//! extension. JPEG uses zune-jpeg for fast SIMD-accelerated decoding
+//! (~2-3x faster than image crate).
 
 use std::fs::File;
-use std::io::Read;
+use std::io::{Cursor, Read};
 use std::path::Path;
+use std::time::Instant;
 
 use bytes::Bytes;
+use zune_jpeg::zune_core::colorspace::ColorSpace;
+use zune_jpeg::zune_core::options::DecoderOptions;
 use zune_jpeg::JpegDecoder;
 
 use crate::error::{TileError, TileResult};
+use crate::imaging::{encode_rgb_with_icc, ImageFormat};
+
+const PNG_MAGIC: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// Compressed tile codec, identified from a tile's leading magic bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileCodec {
+    Jpeg,
+    Png,
+    WebP,
+}
+
+impl TileCodec {
+    /// Sniff the codec from magic bytes (`FFD8`, `\x89PNG`, `RIFF....WEBP`).
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0xFF, 0xD8]) {
+            Some(Self::Jpeg)
+        } else if bytes.starts_with(PNG_MAGIC) {
+            Some(Self::Png)
+        } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(Self::WebP)
+        } else {
+            None
+        }
+    }
+
+    /// Sniff the codec, failing with a decode error for unrecognized bytes.
+    fn detect(bytes: &[u8]) -> TileResult<Self> {
+        Self::sniff(bytes)
+            .ok_or_else(|| TileError::Decode("Unrecognized tile codec (not JPEG/PNG/WebP)".into()))
+    }
+}
+
+/// Sample layout of a decoded tile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// 8-bit R G B.
+    #[default]
+    Rgb8,
+    /// 8-bit B G R A with opaque alpha (`PixelFormat::Bgra`).
+    Bgra8,
+    /// 8-bit R G B A with opaque alpha (`PixelFormat::Rgba`).
+    Rgba8,
+    /// One 8-bit channel.
+    Gray8,
+    /// One 16-bit channel, native-endian (`QImage.Format_Grayscale16`).
+    Gray16,
+}
+
+impl SampleFormat {
+    /// Bytes per channel sample.
+    pub fn bytes_per_sample(&self) -> usize {
+        match self {
+            Self::Rgb8 | Self::Bgra8 | Self::Rgba8 | Self::Gray8 => 1,
+            Self::Gray16 => 2,
+        }
+    }
+
+    /// Bytes per pixel, all channels included.
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            Self::Rgb8 => 3,
+            Self::Bgra8 | Self::Rgba8 => 4,
+            Self::Gray8 | Self::Gray16 => self.bytes_per_sample(),
+        }
+    }
+
+    /// Whether this is a 3- or 4-channel color layout.
+    pub fn is_color(&self) -> bool {
+        matches!(self, Self::Rgb8 | Self::Bgra8 | Self::Rgba8)
+    }
+
+    /// NumPy dtype of one sample.
+    pub fn dtype(&self) -> &'static str {
+        match self {
+            Self::Rgb8 | Self::Bgra8 | Self::Rgba8 | Self::Gray8 => "uint8",
+            Self::Gray16 => "uint16",
+        }
+    }
+
+    /// PEP 3118 buffer format character of one sample.
+    pub fn buffer_format(&self) -> &'static str {
+        match self {
+            Self::Rgb8 | Self::Bgra8 | Self::Rgba8 | Self::Gray8 => "B",
+            Self::Gray16 => "H",
+        }
+    }
+
+    /// Name reported to Python ("rgb8", "bgra8", "rgba8", "gray8", "gray16").
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rgb8 => "rgb8",
+            Self::Bgra8 => "bgra8",
+            Self::Rgba8 => "rgba8",
+            Self::Gray8 => "gray8",
+            Self::Gray16 => "gray16",
+        }
+    }
+}
 
 /// Decoded tile data.
 #[derive(Debug, Clone)]
 pub struct TileData {
-    /// Raw RGB pixel data.
+    /// Raw pixel data, laid out as `sample_format` says.
     pub data: Bytes,
     /// Tile width in pixels.
     pub width: u32,
     /// Tile height in pixels.
     pub height: u32,
+    pub sample_format: SampleFormat,
 }
 
 impl TileData {
-    /// Create new tile data.
+    /// Create new 8-bit color tile data.
     pub fn new(data: Vec<u8>, width: u32, height: u32) -> Self {
+        Self::with_format(data, width, height, SampleFormat::Rgb8)
+    }
+
+    /// Create tile data in an explicit sample format.
+    pub fn with_format(
+        data: Vec<u8>,
+        width: u32,
+        height: u32,
+        sample_format: SampleFormat,
+    ) -> Self {
         Self {
             data: Bytes::from(data),
             width,
             height,
+            sample_format,
+        }
+    }
+}
+
+/// Pixel layout handed to Python for a decoded tile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 3 bytes per pixel, R G B (`QImage.Format_RGB888`).
+    #[default]
+    Rgb,
+    /// 4 bytes per pixel, B G R A with opaque alpha
+    /// (`QImage.Format_ARGB32` on little-endian).
+    Bgra,
+    /// 4 bytes per pixel, R G B A with opaque alpha
+    /// (`QImage.Format_RGBA8888`, any endianness).
+    Rgba,
+}
+
+impl PixelFormat {
+    /// Parse a format name ("rgb", "bgra" or "rgba", case-insensitive).
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "rgb" => Some(Self::Rgb),
+            "bgra" => Some(Self::Bgra),
+            "rgba" => Some(Self::Rgba),
+            _ => None,
+        }
+    }
+
+    /// Sample layout of color tiles in this format.
+    pub fn sample_format(&self) -> SampleFormat {
+        match self {
+            Self::Rgb => SampleFormat::Rgb8,
+            Self::Bgra => SampleFormat::Bgra8,
+            Self::Rgba => SampleFormat::Rgba8,
+        }
+    }
+
+    /// zune-jpeg output colorspace producing this layout.
+    fn jpeg_colorspace(&self) -> ColorSpace {
+        match self {
+            Self::Rgb => ColorSpace::RGB,
+            Self::Bgra => ColorSpace::BGRA,
+            Self::Rgba => ColorSpace::RGBA,
+        }
+    }
+}
+
+/// Per-pixel transform applied to decoded tiles before they enter L1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TileTransform {
+    #[default]
+    None,
+    /// Display gamma: `255 * (v / 255)^(1 / gamma)` per channel.
+    Gamma(f32),
+    /// `255 - v` per channel (e.g. fluorescence shown on a light background).
+    Invert,
+}
+
+impl TileTransform {
+    /// Parse a transform name ("none", "gamma", "invert") and its parameters.
+    ///
+    /// `gamma` takes exactly one positive parameter; the others take none.
+    pub fn parse(kind: &str, params: &[f64]) -> Option<Self> {
+        match (kind.to_ascii_lowercase().as_str(), params) {
+            ("none", []) => Some(Self::None),
+            ("invert", []) => Some(Self::Invert),
+            ("gamma", &[gamma]) if gamma.is_finite() && gamma > 0.0 => {
+                Some(Self::Gamma(gamma as f32))
+            }
+            _ => None,
+        }
+    }
+
+    /// Identity for cache keys: equal keys produce identical pixels.
+    pub fn key(&self) -> u64 {
+        match *self {
+            Self::None => 0,
+            Self::Invert => 1,
+            Self::Gamma(gamma) => (2 << 32) | gamma.to_bits() as u64,
+        }
+    }
+
+    /// Per-channel lookup table, or None for the identity transform.
+    fn lut(&self) -> Option<[u8; 256]> {
+        let mut lut = [0u8; 256];
+        match *self {
+            Self::None => return None,
+            Self::Invert => {
+                for (v, out) in lut.iter_mut().enumerate() {
+                    *out = 255 - v as u8;
+                }
+            }
+            Self::Gamma(gamma) => {
+                let inv = 1.0 / gamma;
+                for (v, out) in lut.iter_mut().enumerate() {
+                    *out = ((v as f32 / 255.0).powf(inv) * 255.0).round() as u8;
+                }
+            }
+        }
+        Some(lut)
+    }
+
+    /// Apply this transform to every 16-bit sample.
+    fn apply_u16(&self, v: u16) -> u16 {
+        match *self {
+            Self::None => v,
+            Self::Invert => u16::MAX - v,
+            Self::Gamma(gamma) => {
+                ((v as f32 / 65535.0).powf(1.0 / gamma) * 65535.0).round() as u16
+            }
+        }
+    }
+
+    /// Apply this transform to every channel of `tile`.
+    pub fn apply(&self, tile: TileData) -> TileData {
+        let Some(lut) = self.lut() else {
+            return tile;
+        };
+        let data = match tile.sample_format {
+            SampleFormat::Gray16 => tile
+                .data
+                .chunks_exact(2)
+                .flat_map(|s| self.apply_u16(u16::from_ne_bytes([s[0], s[1]])).to_ne_bytes())
+                .collect(),
+            SampleFormat::Rgb8 | SampleFormat::Gray8 => {
+                tile.data.iter().map(|&v| lut[v as usize]).collect()
+            }
+            // Alpha stays opaque
+            SampleFormat::Bgra8 | SampleFormat::Rgba8 => tile
+                .data
+                .chunks_exact(4)
+                .flat_map(|p| [lut[p[0] as usize], lut[p[1] as usize], lut[p[2] as usize], p[3]])
+                .collect(),
+        };
+        TileData::with_format(data, tile.width, tile.height, tile.sample_format)
+    }
+}
+
+impl TileData {
+    /// NumPy dtype of one sample.
+    pub fn dtype(&self) -> &'static str {
+        self.sample_format.dtype()
+    }
+
+    /// Interleaved channels per pixel, derived from the buffer length
+    /// (3 for RGB, 4 for BGRA/RGBA, 1 for grayscale).
+    pub fn channels(&self) -> u32 {
+        let pixels = self.width as usize * self.height as usize;
+        let pixel_bytes = pixels.max(1) * self.sample_format.bytes_per_sample();
+        (self.data.len() / pixel_bytes) as u32
+    }
+
+    /// C-contiguous array shape `(height, width, channels)` of `data`.
+    pub fn array_shape(&self) -> (u32, u32, u32) {
+        (self.height, self.width, self.channels())
+    }
+
+    /// Remove `border` pixels from all four sides of this (decoded) tile.
+    ///
+    /// A border at least half the tile size leaves an empty 0x0 tile.
+    pub fn trim_border(self, border: u32) -> TileData {
+        if border == 0 {
+            return self;
+        }
+        let width = self.width.saturating_sub(2 * border);
+        let height = self.height.saturating_sub(2 * border);
+        self.crop(border, border, width, height)
+    }
+
+    /// The `width` x `height` pixels at (`x`, `y`), clipped to the tile.
+    /// Empty (0x0) when nothing is left after clipping.
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> TileData {
+        let width = width.min(self.width.saturating_sub(x));
+        let height = height.min(self.height.saturating_sub(y));
+        if width == 0 || height == 0 {
+            return TileData::with_format(Vec::new(), 0, 0, self.sample_format);
+        }
+
+        let pixel_bytes = self.sample_format.bytes_per_pixel();
+        let stride = self.width as usize * pixel_bytes;
+        let row_len = width as usize * pixel_bytes;
+        let left = x as usize * pixel_bytes;
+        let mut out = Vec::with_capacity(row_len * height as usize);
+        for row in self.data.chunks_exact(stride).skip(y as usize).take(height as usize) {
+            out.extend_from_slice(&row[left..left + row_len]);
         }
+        TileData::with_format(out, width, height, self.sample_format)
+    }
+
+    /// This tile as 8-bit RGB, for consumers that only handle RGB (region
+    /// assembly, thumbnails). RGB is returned as-is (no copy); 16-bit
+    /// samples keep their high byte and alpha is dropped.
+    pub fn to_rgb8(&self) -> TileData {
+        let data = match self.sample_format {
+            SampleFormat::Rgb8 => return self.clone(),
+            SampleFormat::Bgra8 => {
+                self.data.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0]]).collect()
+            }
+            SampleFormat::Rgba8 => {
+                self.data.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect()
+            }
+            SampleFormat::Gray8 => self.data.iter().flat_map(|&g| [g, g, g]).collect(),
+            SampleFormat::Gray16 => self
+                .data
+                .chunks_exact(2)
+                .flat_map(|s| {
+                    let g = (u16::from_ne_bytes([s[0], s[1]]) >> 8) as u8;
+                    [g, g, g]
+                })
+                .collect(),
+        };
+        TileData::new(data, self.width, self.height)
+    }
+
+    /// Convert this color tile to `format`. A tile already in `format` is
+    /// returned as-is (no copy).
+    ///
+    /// Grayscale tiles are returned as-is: their layout is `sample_format`.
+    pub fn to_format(&self, format: PixelFormat) -> TileData {
+        let target = format.sample_format();
+        if !self.sample_format.is_color() || self.sample_format == target {
+            return self.clone();
+        }
+        let rgb = self.to_rgb8();
+        let data = match format {
+            PixelFormat::Rgb => return rgb,
+            PixelFormat::Bgra => {
+                rgb.data.chunks_exact(3).flat_map(|p| [p[2], p[1], p[0], 255]).collect()
+            }
+            PixelFormat::Rgba => {
+                rgb.data.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect()
+            }
+        };
+        TileData::with_format(data, self.width, self.height, target)
     }
 }
 
-/// Compressed JPEG tile data (not yet decoded to RGB).
+/// Compressed tile data (not yet decoded to RGB).
 #[derive(Debug, Clone)]
 pub struct CompressedTileData {
-    /// Raw JPEG file bytes.
+    /// Raw compressed tile bytes (JPEG, PNG, or WebP).
     pub jpeg_bytes: Bytes,
-    /// Tile width in pixels (parsed from JPEG header).
+    /// Tile width in pixels (parsed from the codec header).
     /// Used by L2 cache reads (Part 4).
     #[allow(dead_code)]
     pub width: u32,
-    /// Tile height in pixels (parsed from JPEG header).
+    /// Tile height in pixels (parsed from the codec header).
     /// Used by L2 cache reads (Part 4).
     #[allow(dead_code)]
     pub height: u32,
 }
 
+/// Parse tile dimensions from the codec header without decoding pixels.
+pub fn read_tile_header(bytes: &[u8]) -> TileResult<(u32, u32)> {
+    match TileCodec::detect(bytes)? {
+        TileCodec::Jpeg => {
+            let mut decoder = JpegDecoder::new(bytes);
+            decoder
+                .decode_headers()
+                .map_err(|e| TileError::Decode(format!("Failed to parse JPEG header: {:?}", e)))?;
+            let info = decoder
+                .info()
+                .ok_or_else(|| TileError::Decode("Failed to get image info from header".into()))?;
+            Ok((info.width as u32, info.height as u32))
+        }
+        TileCodec::Png => {
+            let reader = png::Decoder::new(bytes)
+                .read_info()
+                .map_err(|e| TileError::Decode(format!("Failed to parse PNG header: {e}")))?;
+            let info = reader.info();
+            Ok((info.width, info.height))
+        }
+        TileCodec::WebP => {
+            let decoder = image_webp::WebPDecoder::new(Cursor::new(bytes))
+                .map_err(|e| TileError::Decode(format!("Failed to parse WebP header: {e}")))?;
+            Ok(decoder.dimensions())
+        }
+    }
+}
 
-/// Read a JPEG tile file and parse its header for dimensions.
+/// Extract the embedded ICC color profile without decoding pixels.
 ///
-/// Returns compressed JPEG bytes with width/height metadata.
-/// Does NOT decode pixels — use `decode_jpeg_bytes()` for that.
-pub fn read_jpeg_bytes(path: &Path) -> TileResult<CompressedTileData> {
-    let mut file = File::open(path)?;
-    let mut jpeg_data = Vec::new();
-    file.read_to_end(&mut jpeg_data)?;
+/// Reads JPEG APP2 `ICC_PROFILE` segments (reassembling multi-segment
+/// profiles), the PNG `iCCP` chunk, or the WebP `ICCP` chunk. Returns None
+/// if the tile has no profile or its profile segments are malformed.
+pub fn read_icc_profile(bytes: &[u8]) -> TileResult<Option<Vec<u8>>> {
+    match TileCodec::detect(bytes)? {
+        TileCodec::Jpeg => {
+            let mut decoder = JpegDecoder::new(bytes);
+            decoder
+                .decode_headers()
+                .map_err(|e| TileError::Decode(format!("Failed to parse JPEG header: {:?}", e)))?;
+            Ok(decoder.icc_profile())
+        }
+        TileCodec::Png => {
+            let reader = png::Decoder::new(bytes)
+                .read_info()
+                .map_err(|e| TileError::Decode(format!("Failed to parse PNG header: {e}")))?;
+            Ok(reader.info().icc_profile.as_ref().map(|icc| icc.to_vec()))
+        }
+        TileCodec::WebP => {
+            let mut decoder = image_webp::WebPDecoder::new(Cursor::new(bytes))
+                .map_err(|e| TileError::Decode(format!("Failed to parse WebP header: {e}")))?;
+            decoder
+                .icc_profile()
+                .map_err(|e| TileError::Decode(format!("Failed to read WebP ICC profile: {e}")))
+        }
+    }
+}
 
-    // Parse JPEG header for dimensions without decoding pixels
-    let mut decoder = JpegDecoder::new(&jpeg_data);
-    decoder
-        .decode_headers()
-        .map_err(|e| TileError::Decode(format!("Failed to parse JPEG header: {:?}", e)))?;
+/// Read a tile file and parse its header for dimensions.
+///
+/// Returns compressed bytes with width/height metadata for whichever codec
+/// the file's magic bytes identify (the extension is ignored).
+/// Does NOT decode pixels — use `decode_tile_bytes()` for that.
+pub fn read_compressed_tile(path: &Path) -> TileResult<CompressedTileData> {
+    let mut file = File::open(path)?;
+    let mut tile_data = Vec::new();
+    file.read_to_end(&mut tile_data)?;
 
-    let info = decoder
-        .info()
-        .ok_or_else(|| TileError::Decode("Failed to get image info from header".into()))?;
+    let (width, height) = read_tile_header(&tile_data)?;
 
     Ok(CompressedTileData {
-        jpeg_bytes: Bytes::from(jpeg_data),
-        width: info.width as u32,
-        height: info.height as u32,
+        jpeg_bytes: Bytes::from(tile_data),
+        width,
+        height,
     })
 }
 
-/// Decode compressed JPEG bytes to RGB pixel data.
+/// Decode compressed tile bytes to RGB pixel data.
+///
+/// Dispatches on the sniffed codec. Grayscale and alpha channels are
+/// converted to RGB so every codec yields the same `TileData` layout.
+pub fn decode_tile_bytes(compressed: &CompressedTileData) -> TileResult<TileData> {
+    decode_tile_bytes_with(compressed, false)
+}
+
+/// Decode compressed tile bytes, optionally keeping grayscale sources
+/// single-channel.
 ///
-/// Handles grayscale-to-RGB conversion automatically.
-pub fn decode_jpeg_bytes(compressed: &CompressedTileData) -> TileResult<TileData> {
-    let mut decoder = JpegDecoder::new(compressed.jpeg_bytes.as_ref());
+/// With `preserve_gray`, grayscale JPEG/PNG tiles decode to `Gray8`, and
+/// 16-bit grayscale PNG tiles to `Gray16` instead of being narrowed
+/// (zune-jpeg only decodes 8-bit JPEG). Color tiles are always RGB.
+pub fn decode_tile_bytes_with(
+    compressed: &CompressedTileData,
+    preserve_gray: bool,
+) -> TileResult<TileData> {
+    decode_tile_bytes_as(compressed, preserve_gray, PixelFormat::Rgb)
+}
+
+/// `decode_tile_bytes_with`, with color tiles decoded into `format`.
+///
+/// YCbCr JPEGs (nearly every tile) are color-converted by the decoder
+/// straight into `format`; other sources are decoded to RGB and repacked
+/// once here.
+pub fn decode_tile_bytes_as(
+    compressed: &CompressedTileData,
+    preserve_gray: bool,
+    format: PixelFormat,
+) -> TileResult<TileData> {
+    let bytes = compressed.jpeg_bytes.as_ref();
+    let tile = match TileCodec::detect(bytes)? {
+        TileCodec::Jpeg => decode_jpeg(bytes, preserve_gray, format)?,
+        TileCodec::Png => decode_png(bytes, preserve_gray)?,
+        TileCodec::WebP => decode_webp(bytes)?,
+    };
+    Ok(tile.to_format(format))
+}
+
+/// Decode a 1/8-scale RGB preview of a tile from its JPEG DC coefficients.
+///
+/// Several times faster than a full decode as it skips the IDCT. Errors for
+/// PNG/WebP tiles and for JPEG variants the DC decoder doesn't handle
+/// (progressive, arithmetic, CMYK).
+pub fn decode_tile_preview(compressed: &CompressedTileData) -> TileResult<TileData> {
+    let bytes = compressed.jpeg_bytes.as_ref();
+    match TileCodec::detect(bytes)? {
+        TileCodec::Jpeg => crate::jpeg_dc::decode_dc_preview(bytes),
+        codec => Err(TileError::Decode(format!("No DC preview for {codec:?} tiles"))),
+    }
+}
+
+fn decode_jpeg(bytes: &[u8], preserve_gray: bool, format: PixelFormat) -> TileResult<TileData> {
+    let options = DecoderOptions::default().jpeg_set_out_colorspace(format.jpeg_colorspace());
+    let mut decoder = JpegDecoder::new_with_options(bytes, options);
+    decoder
+        .decode_headers()
+        .map_err(|e| TileError::Decode(format!("Failed to decode JPEG: {:?}", e)))?;
+    // zune-jpeg converts only YCbCr (and grayscale, which stays one
+    // channel) to the 4-byte layouts; anything else decodes to RGB
+    let (mut decoder, sample_format) = match decoder.get_input_colorspace() {
+        Some(ColorSpace::YCbCr | ColorSpace::Luma) => (decoder, format.sample_format()),
+        _ => (JpegDecoder::new(bytes), SampleFormat::Rgb8),
+    };
 
     let pixels = decoder
         .decode()
@@ -92,8 +553,125 @@ pub fn decode_jpeg_bytes(compressed: &CompressedTileData) -> TileResult<TileData
     let width = info.width as u32;
     let height = info.height as u32;
 
-    let rgb_data = if info.components == 1 {
-        pixels.iter().flat_map(|&gray| [gray, gray, gray]).collect()
+    if info.components == 1 && preserve_gray {
+        return Ok(TileData::with_format(pixels, width, height, SampleFormat::Gray8));
+    }
+    if info.components == 1 {
+        let rgb = pixels.iter().flat_map(|&gray| [gray, gray, gray]).collect();
+        return Ok(TileData::new(rgb, width, height));
+    }
+
+    Ok(TileData::with_format(pixels, width, height, sample_format))
+}
+
+fn decode_png(bytes: &[u8], preserve_gray: bool) -> TileResult<TileData> {
+    let mut decoder = png::Decoder::new(bytes);
+    // Expand palettes/low bit depths and strip 16-bit samples to 8-bit.
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| TileError::Decode(format!("Failed to parse PNG header: {e}")))?;
+
+    let info = reader.info();
+    let gray = matches!(
+        info.color_type,
+        png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha
+    );
+    if preserve_gray && gray && info.bit_depth == png::BitDepth::Sixteen {
+        return decode_png_gray16(bytes);
+    }
+
+    let mut pixels = vec![0u8; reader.output_buffer_size()];
+    let frame = reader
+        .next_frame(&mut pixels)
+        .map_err(|e| TileError::Decode(format!("Failed to decode PNG: {e}")))?;
+    pixels.truncate(frame.buffer_size());
+
+    let rgb_data = match frame.color_type {
+        png::ColorType::Rgb => pixels,
+        png::ColorType::Rgba => pixels
+            .chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2]])
+            .collect(),
+        png::ColorType::Grayscale if preserve_gray => {
+            return Ok(TileData::with_format(
+                pixels,
+                frame.width,
+                frame.height,
+                SampleFormat::Gray8,
+            ));
+        }
+        png::ColorType::GrayscaleAlpha if preserve_gray => {
+            let gray = pixels.chunks_exact(2).map(|p| p[0]).collect();
+            return Ok(TileData::with_format(
+                gray,
+                frame.width,
+                frame.height,
+                SampleFormat::Gray8,
+            ));
+        }
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&g| [g, g, g]).collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0]])
+            .collect(),
+        png::ColorType::Indexed => {
+            return Err(TileError::Decode("PNG palette was not expanded".into()));
+        }
+    };
+
+    Ok(TileData::new(rgb_data, frame.width, frame.height))
+}
+
+/// Decode a 16-bit grayscale PNG to native-endian `Gray16` (alpha dropped).
+fn decode_png_gray16(bytes: &[u8]) -> TileResult<TileData> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::IDENTITY);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| TileError::Decode(format!("Failed to parse PNG header: {e}")))?;
+
+    let mut pixels = vec![0u8; reader.output_buffer_size()];
+    let frame = reader
+        .next_frame(&mut pixels)
+        .map_err(|e| TileError::Decode(format!("Failed to decode PNG: {e}")))?;
+    pixels.truncate(frame.buffer_size());
+
+    // PNG samples are big-endian; keep the gray sample of each pixel
+    let pixel_bytes = match frame.color_type {
+        png::ColorType::Grayscale => 2,
+        png::ColorType::GrayscaleAlpha => 4,
+        other => {
+            return Err(TileError::Decode(format!(
+                "Expected a 16-bit grayscale PNG, got {other:?}"
+            )));
+        }
+    };
+    let data = pixels
+        .chunks_exact(pixel_bytes)
+        .flat_map(|p| u16::from_be_bytes([p[0], p[1]]).to_ne_bytes())
+        .collect();
+    Ok(TileData::with_format(data, frame.width, frame.height, SampleFormat::Gray16))
+}
+
+fn decode_webp(bytes: &[u8]) -> TileResult<TileData> {
+    let mut decoder = image_webp::WebPDecoder::new(Cursor::new(bytes))
+        .map_err(|e| TileError::Decode(format!("Failed to parse WebP header: {e}")))?;
+    let (width, height) = decoder.dimensions();
+    let buf_size = decoder
+        .output_buffer_size()
+        .ok_or_else(|| TileError::Decode("WebP image too large".into()))?;
+
+    let mut pixels = vec![0u8; buf_size];
+    decoder
+        .read_image(&mut pixels)
+        .map_err(|e| TileError::Decode(format!("Failed to decode WebP: {e}")))?;
+
+    let rgb_data = if decoder.has_alpha() {
+        pixels
+            .chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2]])
+            .collect()
     } else {
         pixels
     };
@@ -101,23 +679,148 @@ pub fn decode_jpeg_bytes(compressed: &CompressedTileData) -> TileResult<TileData
     Ok(TileData::new(rgb_data, width, height))
 }
 
+/// Throughput measured by [`bench_decode`].
+#[derive(Debug, Clone, Copy)]
+pub struct DecodeBench {
+    pub total_ms: f64,
+    pub per_tile_ms: f64,
+    pub megapixels_per_sec: f64,
+    /// Time to LZ4-decompress the decoded tile (an L1 hit with LZ4 storage).
+    pub lz4_per_tile_ms: f64,
+    /// Decoded size / LZ4-compressed size.
+    pub lz4_ratio: f64,
+}
+
+/// Decode a tile and trim its scanner border (see `SlideMetadata::tile_border`).
+pub fn decode_tile_trimmed(compressed: &CompressedTileData, border: u32) -> TileResult<TileData> {
+    decode_tile_bytes(compressed).map(|tile| tile.trim_border(border))
+}
+
+/// Decode a tile and re-encode it as RGB in `format` (`quality` applies to
+/// JPEG only, see `encode_rgb`). An embedded ICC profile is carried over.
+///
+/// Grayscale and 16-bit tiles come back as 8-bit RGB (see `to_rgb8`).
+pub fn transcode_tile(
+    compressed: &CompressedTileData,
+    format: ImageFormat,
+    quality: u8,
+) -> TileResult<CompressedTileData> {
+    let icc = read_icc_profile(&compressed.jpeg_bytes)?;
+    let tile = decode_tile_bytes(compressed)?.to_rgb8();
+    let (w, h) = (tile.width, tile.height);
+    let bytes = encode_rgb_with_icc(&tile.data, w, h, format, quality, icc.as_deref())?;
+    Ok(CompressedTileData {
+        jpeg_bytes: Bytes::from(bytes),
+        width: tile.width,
+        height: tile.height,
+    })
+}
+
+/// Benchmark: decode the same tile `iterations` times.
+///
+/// Used to compare tile sizes and encoder settings on target hardware.
+pub fn bench_decode(bytes: Bytes, iterations: u32) -> TileResult<DecodeBench> {
+    if iterations == 0 {
+        return Err(TileError::Validation("iterations must be positive".into()));
+    }
+    let compressed = CompressedTileData {
+        jpeg_bytes: bytes,
+        width: 0,
+        height: 0,
+    };
+
+    let start = Instant::now();
+    let mut pixels = 0u64;
+    let mut decoded = None;
+    for _ in 0..iterations {
+        let tile = decode_tile_bytes(&compressed)?;
+        pixels += tile.width as u64 * tile.height as u64;
+        decoded = Some(tile);
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    // Same tile through the L1 LZ4 path, for comparison against the decode
+    let rgb = decoded.map(|t| t.data).unwrap_or_default();
+    let block = lz4_flex::compress_prepend_size(&rgb);
+    let lz4_start = Instant::now();
+    for _ in 0..iterations {
+        let restored = lz4_flex::decompress_size_prepended(&block)
+            .map_err(|e| TileError::Decode(format!("LZ4 round-trip failed: {e}")))?;
+        std::hint::black_box(restored);
+    }
+    let lz4_ms = lz4_start.elapsed().as_secs_f64() * 1000.0;
+
+    let total_ms = elapsed * 1000.0;
+    Ok(DecodeBench {
+        total_ms,
+        per_tile_ms: total_ms / iterations as f64,
+        megapixels_per_sec: pixels as f64 / 1_000_000.0 / elapsed.max(f64::MIN_POSITIVE),
+        lz4_per_tile_ms: lz4_ms / iterations as f64,
+        lz4_ratio: rgb.len() as f64 / block.len().max(1) as f64,
+    })
+}
+
 /// Decode a tile from a file path.
 ///
-/// Supports JPEG (.jpg, .jpeg) format.
-/// Uses zune-jpeg for fast SIMD-accelerated decoding.
+/// Supports JPEG, PNG, and WebP (sniffed from content, not extension).
 /// Convenience wrapper used by tests; scheduler uses split read/decode path.
 #[allow(dead_code)]
 pub fn decode_tile(path: &Path) -> TileResult<TileData> {
-    let compressed = read_jpeg_bytes(path)?;
-    decode_jpeg_bytes(&compressed)
+    let compressed = read_compressed_tile(path)?;
+    decode_tile_bytes(&compressed)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::imaging::encode_rgb;
+    use crate::pack::{pack_dzsave_tiles, TilePack};
+    use crate::test_utils::{test_gray_png_bytes, test_jpeg_bytes, test_png_bytes, test_webp_bytes};
     use std::fs;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_tile_transform_apply() {
+        let tile = TileData::new(vec![0, 64, 255], 1, 1);
+        assert_eq!(TileTransform::None.apply(tile.clone()).data.as_ref(), &[0, 64, 255]);
+        assert_eq!(TileTransform::Invert.apply(tile.clone()).data.as_ref(), &[255, 191, 0]);
+
+        // gamma 2 brightens midtones, fixes the endpoints; gamma 1 is identity
+        let bright = TileTransform::Gamma(2.0).apply(tile.clone());
+        assert_eq!(bright.data.as_ref(), &[0, 128, 255]);
+        assert_eq!(TileTransform::Gamma(1.0).apply(tile).data.as_ref(), &[0, 64, 255]);
+    }
+
+    #[test]
+    fn test_transcode_tile_reencodes_as_rgb_jpeg() {
+        let png = test_gray_png_bytes(4, 2, png::BitDepth::Eight, &[128; 8]);
+        let compressed = CompressedTileData {
+            jpeg_bytes: Bytes::from(png),
+            width: 4,
+            height: 2,
+        };
+        let jpeg = transcode_tile(&compressed, ImageFormat::Jpeg, 80).unwrap();
+        assert!(jpeg.jpeg_bytes.starts_with(&[0xFF, 0xD8]));
+        assert_eq!((jpeg.width, jpeg.height), (4, 2));
+        let tile = decode_tile_bytes(&jpeg).unwrap();
+        assert_eq!(tile.sample_format, SampleFormat::Rgb8);
+        assert!(tile.data.iter().all(|&v| v.abs_diff(128) <= 2));
+    }
+
+    #[test]
+    fn test_tile_transform_parse_and_key() {
+        assert_eq!(TileTransform::parse("INVERT", &[]), Some(TileTransform::Invert));
+        assert_eq!(TileTransform::parse("gamma", &[2.2]), Some(TileTransform::Gamma(2.2)));
+        assert_eq!(TileTransform::parse("gamma", &[]), None);
+        assert_eq!(TileTransform::parse("gamma", &[0.0]), None);
+        assert_eq!(TileTransform::parse("invert", &[1.0]), None);
+        assert_eq!(TileTransform::parse("sepia", &[]), None);
+
+        assert_eq!(TileTransform::None.key(), 0);
+        assert_ne!(TileTransform::Gamma(1.8).key(), TileTransform::Gamma(2.2).key());
+        assert_ne!(TileTransform::Invert.key(), TileTransform::Gamma(1.0).key());
+    }
+
     #[test]
     fn test_decode_invalid_path() {
         let result = decode_tile(Path::new("/nonexistent/path.jpg"));
@@ -135,17 +838,17 @@ mod tests {
     }
 
     #[test]
-    fn test_read_jpeg_bytes_invalid_path() {
-        let result = read_jpeg_bytes(Path::new("/nonexistent/path.jpg"));
+    fn test_read_compressed_tile_invalid_path() {
+        let result = read_compressed_tile(Path::new("/nonexistent/path.jpg"));
         assert!(result.is_err());
     }
 
     #[test]
-    fn test_read_jpeg_bytes_invalid_data() {
+    fn test_read_compressed_tile_invalid_data() {
         let temp = TempDir::new().unwrap();
         let path = temp.path().join("fake.jpg");
         fs::write(&path, b"not a jpeg").unwrap();
-        let result = read_jpeg_bytes(&path);
+        let result = read_compressed_tile(&path);
         assert!(result.is_err());
     }
 
@@ -160,13 +863,284 @@ mod tests {
     }
 
     #[test]
-    fn test_decode_jpeg_bytes_invalid_data() {
+    fn test_bench_decode_reports_positive_numbers() {
+        let bench = bench_decode(Bytes::from(test_jpeg_bytes()), 50).unwrap();
+        assert!(bench.total_ms > 0.0);
+        assert!(bench.per_tile_ms > 0.0);
+        assert!(bench.per_tile_ms <= bench.total_ms);
+        assert!(bench.megapixels_per_sec > 0.0);
+        assert!(bench.lz4_per_tile_ms >= 0.0);
+        assert!(bench.lz4_ratio > 0.0);
+    }
+
+    #[test]
+    fn test_bench_decode_rejects_bad_input() {
+        assert!(bench_decode(Bytes::from(test_jpeg_bytes()), 0).is_err());
+        assert!(bench_decode(Bytes::from_static(b"not a tile"), 1).is_err());
+    }
+
+    #[test]
+    fn test_trim_border() {
+        // 4x4 tile, pixel value = row * 4 + col
+        let data: Vec<u8> = (0..16u8).flat_map(|v| [v, v, v]).collect();
+        let tile = TileData::new(data, 4, 4);
+
+        let trimmed = tile.clone().trim_border(1);
+        assert_eq!((trimmed.width, trimmed.height), (2, 2));
+        let values: Vec<u8> = trimmed.data.chunks_exact(3).map(|p| p[0]).collect();
+        assert_eq!(values, vec![5, 6, 9, 10]);
+
+        assert_eq!(tile.clone().trim_border(0).data, tile.data);
+
+        // Crops past the edge are clipped
+        let corner = tile.crop(3, 2, 2, 2);
+        assert_eq!((corner.width, corner.height), (1, 2));
+        let values: Vec<u8> = corner.data.chunks_exact(3).map(|p| p[0]).collect();
+        assert_eq!(values, vec![11, 15]);
+        assert_eq!(tile.crop(4, 0, 1, 1).data.len(), 0);
+
+        let empty = tile.trim_border(2);
+        assert_eq!((empty.width, empty.height, empty.data.len()), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_array_shape_matches_data() {
+        let tile = TileData::new(vec![0; 4 * 2 * 3], 4, 2);
+        assert_eq!(tile.array_shape(), (2, 4, 3));
+        assert_eq!(tile.dtype(), "uint8");
+
+        let bgra = tile.to_format(PixelFormat::Bgra);
+        assert_eq!(bgra.array_shape(), (2, 4, 4));
+        let (h, w, c) = bgra.array_shape();
+        assert_eq!((h * w * c) as usize, bgra.data.len());
+    }
+
+    #[test]
+    fn test_decode_gray16_png_preserved() {
+        let values = [0u16, 1000, 40000, 65535];
+        let samples: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        let compressed = CompressedTileData {
+            jpeg_bytes: Bytes::from(test_gray_png_bytes(2, 2, png::BitDepth::Sixteen, &samples)),
+            width: 0,
+            height: 0,
+        };
+
+        let tile = decode_tile_bytes_with(&compressed, true).unwrap();
+        assert_eq!(tile.sample_format, SampleFormat::Gray16);
+        assert_eq!(tile.array_shape(), (2, 2, 1));
+        assert_eq!(tile.dtype(), "uint16");
+        let decoded: Vec<u16> = tile
+            .data
+            .chunks_exact(2)
+            .map(|s| u16::from_ne_bytes([s[0], s[1]]))
+            .collect();
+        assert_eq!(decoded, values);
+
+        // Transforms and RGB consumers handle 16-bit samples
+        let inverted = TileTransform::Invert.apply(tile.clone());
+        assert_eq!(inverted.sample_format, SampleFormat::Gray16);
+        assert_eq!(&inverted.data[..2], &u16::MAX.to_ne_bytes());
+        let rgb = tile.to_rgb8();
+        assert_eq!(rgb.data.as_ref(), &[0, 0, 0, 3, 3, 3, 156, 156, 156, 255, 255, 255]);
+        assert_eq!(tile.to_format(PixelFormat::Bgra).data, tile.data);
+
+        // The default decode stays 8-bit RGB
+        let default = decode_tile_bytes(&compressed).unwrap();
+        assert_eq!(default.sample_format, SampleFormat::Rgb8);
+        assert_eq!(default.array_shape(), (2, 2, 3));
+    }
+
+    #[test]
+    fn test_decode_gray8_preserved() {
+        let compressed = CompressedTileData {
+            jpeg_bytes: Bytes::from(test_gray_png_bytes(3, 1, png::BitDepth::Eight, &[1, 2, 3])),
+            width: 0,
+            height: 0,
+        };
+        let tile = decode_tile_bytes_with(&compressed, true).unwrap();
+        assert_eq!(tile.sample_format, SampleFormat::Gray8);
+        assert_eq!(tile.data.as_ref(), &[1, 2, 3]);
+        let gray16 = TileData::with_format(vec![0; 9 * 2], 3, 3, SampleFormat::Gray16);
+        let trimmed = gray16.trim_border(1);
+        assert_eq!((trimmed.width, trimmed.data.len()), (1, 2));
+
+        // Color tiles are unaffected
+        let rgb = decode_tile_bytes_with(
+            &CompressedTileData {
+                jpeg_bytes: Bytes::from(test_png_bytes(1, 1, &[1, 2, 3])),
+                width: 0,
+                height: 0,
+            },
+            true,
+        )
+        .unwrap();
+        assert_eq!(rgb.sample_format, SampleFormat::Rgb8);
+    }
+
+    #[test]
+    fn test_decode_into_pixel_format_matches_rgb_decode() {
+        let pixels: Vec<u8> = (0..16 * 16 * 3).map(|i| (i * 7 % 256) as u8).collect();
+        let jpeg = encode_rgb(&pixels, 16, 16, ImageFormat::Jpeg, 90).unwrap();
+        let gray_png = test_gray_png_bytes(2, 1, png::BitDepth::Eight, &[10, 20]);
+        for bytes in [jpeg, test_png_bytes(1, 1, &[1, 2, 3]), gray_png] {
+            let compressed = CompressedTileData {
+                jpeg_bytes: Bytes::from(bytes),
+                width: 0,
+                height: 0,
+            };
+            let rgb = decode_tile_bytes(&compressed).unwrap();
+            for (format, sample_format) in [
+                (PixelFormat::Bgra, SampleFormat::Bgra8),
+                (PixelFormat::Rgba, SampleFormat::Rgba8),
+            ] {
+                let tile = decode_tile_bytes_as(&compressed, false, format).unwrap();
+                assert_eq!(tile.sample_format, sample_format);
+                assert_eq!(tile.array_shape(), (rgb.height, rgb.width, 4));
+                assert_eq!(tile.data, rgb.to_format(format).data);
+                assert_eq!(tile.to_rgb8().data, rgb.data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pixel_format_conversion() {
+        let tile = TileData::new(vec![10, 20, 30, 40, 50, 60], 2, 1);
+        assert_eq!(tile.to_format(PixelFormat::Rgb).data, tile.data);
+
+        let bgra = tile.to_format(PixelFormat::Bgra);
+        assert_eq!((bgra.width, bgra.height), (2, 1));
+        assert_eq!(bgra.data.as_ref(), &[30, 20, 10, 255, 60, 50, 40, 255]);
+        let rgba = tile.to_format(PixelFormat::Rgba);
+        assert_eq!(rgba.data.as_ref(), &[10, 20, 30, 255, 40, 50, 60, 255]);
+        assert_eq!(rgba.array_shape(), (1, 2, 4));
+
+        // Converting back from a 4-byte layout recovers the RGB pixels
+        assert_eq!(bgra.to_format(PixelFormat::Rgba).data, rgba.data);
+        assert_eq!(rgba.to_format(PixelFormat::Rgb).data, tile.data);
+        // Transforms leave alpha opaque
+        let inverted = TileTransform::Invert.apply(bgra);
+        assert_eq!(inverted.data.as_ref(), &[225, 235, 245, 255, 195, 205, 215, 255]);
+
+        assert_eq!(PixelFormat::parse("BGRA"), Some(PixelFormat::Bgra));
+        assert_eq!(PixelFormat::parse("rgba"), Some(PixelFormat::Rgba));
+        assert_eq!(PixelFormat::parse("rgb"), Some(PixelFormat::Rgb));
+        assert_eq!(PixelFormat::parse("argb"), None);
+    }
+
+    #[test]
+    fn test_sniff_codec() {
+        assert_eq!(TileCodec::sniff(&test_jpeg_bytes()), Some(TileCodec::Jpeg));
+        assert_eq!(
+            TileCodec::sniff(&test_png_bytes(1, 1, &[0, 0, 0])),
+            Some(TileCodec::Png)
+        );
+        assert_eq!(
+            TileCodec::sniff(b"RIFF\x00\x00\x00\x00WEBPVP8 "),
+            Some(TileCodec::WebP)
+        );
+        assert_eq!(TileCodec::sniff(b"not a tile"), None);
+    }
+
+    #[test]
+    fn test_mixed_codec_level_decodes() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let level_dir = dir.join("tiles_files").join("0");
+        fs::create_dir_all(&level_dir).unwrap();
+
+        // PNG and WebP bytes behind a .jpg extension: the codec is sniffed, not assumed.
+        let png_pixels = [255u8, 0, 0, 0, 0, 255];
+        let webp_pixels = [0u8, 255, 0, 10, 20, 30, 40, 50, 60, 70, 80, 90];
+        fs::write(level_dir.join("0_0.jpg"), test_jpeg_bytes()).unwrap();
+        fs::write(level_dir.join("1_0.jpg"), test_png_bytes(2, 1, &png_pixels)).unwrap();
+        fs::write(level_dir.join("2_0.jpg"), test_webp_bytes(2, 2, &webp_pixels)).unwrap();
+        pack_dzsave_tiles(dir, &[(0, 3, 1)], None, None).unwrap();
+
+        let pack = TilePack::open(dir).unwrap();
+        let decode_at = |col: u32| {
+            let bytes = pack.read_tile_bytes(pack.tile_ref(0, col, 0).unwrap()).unwrap();
+            let (w, h) = read_tile_header(&bytes).unwrap();
+            let tile = decode_tile_bytes(&CompressedTileData {
+                jpeg_bytes: bytes,
+                width: w,
+                height: h,
+            })
+            .unwrap();
+            assert_eq!((tile.width, tile.height), (w, h));
+            tile
+        };
+
+        let jpeg_tile = decode_at(0);
+        assert_eq!((jpeg_tile.width, jpeg_tile.height), (1, 1));
+        assert_eq!(jpeg_tile.data.len(), 3);
+
+        let png_tile = decode_at(1);
+        assert_eq!((png_tile.width, png_tile.height), (2, 1));
+        assert_eq!(png_tile.data.as_ref(), &png_pixels);
+
+        // Lossless WebP decodes back to the exact pixels
+        let webp_tile = decode_at(2);
+        assert_eq!((webp_tile.width, webp_tile.height), (2, 2));
+        assert_eq!(webp_tile.data.as_ref(), &webp_pixels);
+    }
+
+    #[test]
+    fn test_decode_tile_bytes_invalid_data() {
         let bad = CompressedTileData {
             jpeg_bytes: Bytes::from(b"not a jpeg".to_vec()),
             width: 0,
             height: 0,
         };
-        let result = decode_jpeg_bytes(&bad);
-        assert!(result.is_err());
+        let err = decode_tile_bytes(&bad).unwrap_err().to_string();
+        assert!(err.starts_with("Failed to decode tile:"), "{err}");
+
+        // A corrupt PNG reports the codec without calling it a JPEG
+        let png = CompressedTileData {
+            jpeg_bytes: Bytes::from(b"\x89PNG\r\n\x1a\ngarbage".to_vec()),
+            width: 0,
+            height: 0,
+        };
+        let err = decode_tile_bytes(&png).unwrap_err().to_string();
+        assert!(err.starts_with("Failed to decode tile:"), "{err}");
+        assert!(!err.contains("JPEG"), "{err}");
+    }
+
+    /// Insert APP2 `ICC_PROFILE` segments (one per chunk) after the JPEG SOI.
+    fn with_icc_segments(jpeg: &[u8], chunks: &[&[u8]]) -> Vec<u8> {
+        let mut out = jpeg[..2].to_vec();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let len = (2 + 12 + 2 + chunk.len()) as u16;
+            out.extend_from_slice(&[0xFF, 0xE2]);
+            out.extend_from_slice(&len.to_be_bytes());
+            out.extend_from_slice(b"ICC_PROFILE\0");
+            out.extend_from_slice(&[i as u8 + 1, chunks.len() as u8]);
+            out.extend_from_slice(chunk);
+        }
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    #[test]
+    fn test_read_icc_profile() {
+        let jpeg = test_jpeg_bytes();
+        assert_eq!(read_icc_profile(&jpeg).unwrap(), None);
+        assert_eq!(read_icc_profile(&test_png_bytes(1, 1, &[0, 0, 0])).unwrap(), None);
+        assert_eq!(read_icc_profile(&test_webp_bytes(1, 1, &[0, 0, 0])).unwrap(), None);
+
+        let tagged = with_icc_segments(&jpeg, &[b"first half ", b"second half"]);
+        assert_eq!(
+            read_icc_profile(&tagged).unwrap().as_deref(),
+            Some(b"first half second half".as_slice())
+        );
+        // The profile segments don't disturb decoding
+        let tile = decode_tile_bytes(&CompressedTileData {
+            jpeg_bytes: Bytes::from(tagged),
+            width: 0,
+            height: 0,
+        })
+        .unwrap();
+        assert_eq!((tile.width, tile.height), (1, 1));
+
+        assert!(read_icc_profile(b"not an image").is_err());
     }
 }
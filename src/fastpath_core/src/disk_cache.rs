@@ -0,0 +1,353 @@
+//! Disk-backed L3 cache for compressed tiles.
+//!
+//! Tiles the L2 evicts for space are spilled to a scratch directory, one file
+//! per tile under `<dir>/<slide_id>/`, and read back on an L2 miss before the
+//! scheduler goes to the original pack. Spills are queued to a single writer
+//! thread, so the eviction (which moka may run on a foreground thread) never
+//! waits on disk I/O. The queue is bounded: when the writer falls behind,
+//! further spills are dropped (those tiles are simply re-read from the pack).
+//!
+//! The directory is bounded by `max_bytes`: once spills exceed it, the writer
+//! deletes the oldest ones. Spills left by earlier sessions count toward the
+//! limit, and their interrupted `.tmp` writes are removed on open.
+//!
+//! A slide re-converted at the same path keeps its slide_id, so hits are
+//! checked against the pack index (length, and CRC32 for `FPLIDX2` packs)
+//! before they are trusted. Zstd-wrapped pack entries can't be checked that
+//! way (spills hold the decompressed bytes), so they always miss.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::SystemTime;
+
+use bytes::Bytes;
+use log::warn;
+use parking_lot::Mutex;
+
+use crate::cache::{CacheStats, SlideTileCoord};
+use crate::error::TileResult;
+use crate::pack::PackTileRef;
+
+/// Spills waiting for the writer; more are dropped rather than queued.
+const SPILL_QUEUE_CAPACITY: usize = 1024;
+
+enum Job {
+    Write(SlideTileCoord, Bytes),
+    /// Acknowledge once every earlier write is on disk.
+    Flush(Sender<()>),
+}
+
+/// Spilled tile files in the order they were written, oldest first.
+///
+/// Owned by the writer thread; the counters are shared for `stats`.
+struct Residency {
+    files: VecDeque<(PathBuf, u64)>,
+    max_bytes: u64,
+    tiles: Arc<AtomicU64>,
+    bytes: Arc<AtomicU64>,
+}
+
+impl Residency {
+    /// Take stock of the spills already in `dir`, removing stray temp files.
+    fn scan(&mut self, dir: &Path) {
+        let mut found: Vec<(SystemTime, PathBuf, u64)> = Vec::new();
+        let slides = std::fs::read_dir(dir).into_iter().flatten().flatten();
+        for slide in slides {
+            let Ok(entries) = std::fs::read_dir(slide.path()) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let Ok(meta) = entry.metadata() else {
+                    continue;
+                };
+                if !meta.is_file() {
+                    continue;
+                }
+                if path.extension().is_some_and(|ext| ext == "tmp") {
+                    let _ = std::fs::remove_file(&path);
+                    continue;
+                }
+                let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                found.push((modified, path, meta.len()));
+            }
+        }
+        found.sort_unstable_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+        for (_, path, len) in found {
+            self.add(path, len);
+        }
+        self.trim();
+    }
+
+    fn add(&mut self, path: PathBuf, len: u64) {
+        self.files.push_back((path, len));
+        self.tiles.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(len, Ordering::Relaxed);
+    }
+
+    /// Delete the oldest spills until the directory fits `max_bytes`.
+    fn trim(&mut self) {
+        while self.bytes.load(Ordering::Relaxed) > self.max_bytes {
+            let Some((path, len)) = self.files.pop_front() else {
+                break;
+            };
+            if let Err(e) = std::fs::remove_file(&path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("failed to remove spill {}: {e}", path.display());
+                }
+            }
+            self.tiles.fetch_sub(1, Ordering::Relaxed);
+            self.bytes.fetch_sub(len, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Spill directory for compressed tiles evicted from L2.
+pub struct DiskTileCache {
+    dir: PathBuf,
+    jobs: Mutex<Option<SyncSender<Job>>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    /// Tiles and bytes currently spilled to the directory.
+    resident_tiles: Arc<AtomicU64>,
+    resident_bytes: Arc<AtomicU64>,
+}
+
+impl DiskTileCache {
+    /// Use `dir` (created if missing) as the spill directory, holding at
+    /// most `max_bytes` of spilled tiles.
+    pub fn open(dir: &Path, max_bytes: u64) -> TileResult<Self> {
+        std::fs::create_dir_all(dir)?;
+        let (tx, rx) = mpsc::sync_channel(SPILL_QUEUE_CAPACITY);
+        let resident_tiles = Arc::new(AtomicU64::new(0));
+        let resident_bytes = Arc::new(AtomicU64::new(0));
+
+        let writer = {
+            let dir = dir.to_path_buf();
+            let residency = Residency {
+                files: VecDeque::new(),
+                max_bytes,
+                tiles: Arc::clone(&resident_tiles),
+                bytes: Arc::clone(&resident_bytes),
+            };
+            std::thread::Builder::new()
+                .name("l3-writer".into())
+                .spawn(move || Self::run_writer(&dir, rx, residency))?
+        };
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            jobs: Mutex::new(Some(tx)),
+            writer: Mutex::new(Some(writer)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            resident_tiles,
+            resident_bytes,
+        })
+    }
+
+    fn run_writer(dir: &Path, jobs: Receiver<Job>, mut residency: Residency) {
+        // On the writer rather than in `open`, so a large leftover directory
+        // doesn't hold up scheduler construction
+        residency.scan(dir);
+        for job in jobs {
+            match job {
+                Job::Write(key, bytes) => {
+                    let path = tile_path(dir, &key);
+                    if path.exists() {
+                        continue;
+                    }
+                    match write_atomic(&path, &bytes) {
+                        Ok(()) => {
+                            residency.add(path, bytes.len() as u64);
+                            residency.trim();
+                        }
+                        Err(e) => warn!("spill failed for {}: {e}", path.display()),
+                    }
+                }
+                Job::Flush(done) => {
+                    let _ = done.send(());
+                }
+            }
+        }
+    }
+
+    /// Queue `bytes` to be written for `key` (never blocks).
+    ///
+    /// Dropped if `SPILL_QUEUE_CAPACITY` spills are already waiting.
+    pub fn spill(&self, key: SlideTileCoord, bytes: Bytes) {
+        if let Some(jobs) = self.jobs.lock().as_ref() {
+            let _ = jobs.try_send(Job::Write(key, bytes));
+        }
+    }
+
+    /// Read a spilled tile, if present and consistent with `tile_ref`.
+    pub fn get(&self, key: &SlideTileCoord, tile_ref: PackTileRef) -> Option<Bytes> {
+        let hit = (!tile_ref.zstd).then(|| std::fs::read(tile_path(&self.dir, key)).ok());
+        let hit = hit.flatten().filter(|data| {
+            data.len() == tile_ref.length as usize
+                && tile_ref.crc32.is_none_or(|crc| crc32fast::hash(data) == crc)
+        });
+        match hit {
+            Some(data) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(Bytes::from(data))
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Block until every spill queued so far is on disk (used in tests).
+    #[allow(dead_code)]
+    pub fn flush(&self) {
+        let (tx, rx) = mpsc::channel();
+        if let Some(jobs) = self.jobs.lock().as_ref() {
+            if jobs.send(Job::Flush(tx)).is_ok() {
+                let _ = rx.recv();
+            }
+        }
+    }
+
+    /// Hit/miss counters; size and tile count cover the tiles currently
+    /// spilled, including earlier sessions' once the writer has scanned them.
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let total = hits + misses;
+        CacheStats {
+            hits,
+            misses,
+            hit_ratio: if total > 0 { hits as f64 / total as f64 } else { 0.0 },
+            size_bytes: self.resident_bytes.load(Ordering::Relaxed) as usize,
+            num_tiles: self.resident_tiles.load(Ordering::Relaxed) as usize,
+        }
+    }
+
+    /// Reset hit/miss counters to zero.
+    pub fn reset_stats(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Drop for DiskTileCache {
+    fn drop(&mut self) {
+        // Closing the channel lets the writer finish queued spills and exit
+        self.jobs.lock().take();
+        if let Some(handle) = self.writer.lock().take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn tile_path(dir: &Path, key: &SlideTileCoord) -> PathBuf {
+    dir.join(format!("{:016x}", key.slide_id()))
+        .join(format!("{}_{}_{}.tile", key.level(), key.col(), key.row()))
+}
+
+/// Write via a temp file + rename so readers never see a partial tile.
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn tile_ref(data: &[u8]) -> PackTileRef {
+        PackTileRef {
+            level: 0,
+            offset: 0,
+            length: data.len() as u32,
+            crc32: Some(crc32fast::hash(data)),
+            zstd: false,
+        }
+    }
+
+    #[test]
+    fn test_spill_then_get_round_trips() {
+        let temp = TempDir::new().unwrap();
+        let l3 = DiskTileCache::open(&temp.path().join("l3"), u64::MAX).unwrap();
+        let key = SlideTileCoord::new(7, 1, 2, 3);
+        let data = b"compressed tile".to_vec();
+
+        assert!(l3.get(&key, tile_ref(&data)).is_none());
+        l3.spill(key, Bytes::from(data.clone()));
+        l3.flush();
+
+        assert_eq!(l3.get(&key, tile_ref(&data)).unwrap().as_ref(), data.as_slice());
+        let stats = l3.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!((stats.num_tiles, stats.size_bytes), (1, data.len()));
+    }
+
+    #[test]
+    fn test_stale_spill_is_a_miss() {
+        let temp = TempDir::new().unwrap();
+        let l3 = DiskTileCache::open(temp.path(), u64::MAX).unwrap();
+        let key = SlideTileCoord::new(7, 0, 0, 0);
+        l3.spill(key, Bytes::from_static(b"old tile bytes"));
+        l3.flush();
+
+        // Slide re-converted: same length, different content
+        assert!(l3.get(&key, tile_ref(b"new tile bytes")).is_none());
+        assert_eq!(l3.stats().misses, 1);
+    }
+
+    #[test]
+    fn test_zstd_entry_is_never_served_from_spill() {
+        let temp = TempDir::new().unwrap();
+        let l3 = DiskTileCache::open(temp.path(), u64::MAX).unwrap();
+        let key = SlideTileCoord::new(7, 0, 0, 0);
+        let data = b"decompressed tile".to_vec();
+        l3.spill(key, Bytes::from(data.clone()));
+        l3.flush();
+
+        let zstd_ref = PackTileRef { zstd: true, ..tile_ref(&data) };
+        assert!(l3.get(&key, zstd_ref).is_none());
+        assert_eq!(l3.stats().misses, 1);
+    }
+
+    #[test]
+    fn test_oldest_spills_deleted_past_size_limit() {
+        let temp = TempDir::new().unwrap();
+        let data = b"ten bytes!".to_vec();
+        let key = |col| SlideTileCoord::new(7, 0, col, 0);
+        {
+            let l3 = DiskTileCache::open(temp.path(), 2 * data.len() as u64).unwrap();
+            for col in 0..3 {
+                l3.spill(key(col), Bytes::from(data.clone()));
+                l3.flush();
+            }
+            assert!(l3.get(&key(0), tile_ref(&data)).is_none());
+            assert!((1..3).all(|col| l3.get(&key(col), tile_ref(&data)).is_some()));
+            let stats = l3.stats();
+            assert_eq!((stats.num_tiles, stats.size_bytes), (2, 2 * data.len()));
+        }
+
+        // Reopened with a smaller limit: earlier spills count toward it and
+        // an interrupted write is cleaned up
+        let tmp = tile_path(temp.path(), &key(9)).with_extension("tmp");
+        std::fs::write(&tmp, b"partial").unwrap();
+        let l3 = DiskTileCache::open(temp.path(), data.len() as u64).unwrap();
+        l3.flush();
+        assert!(!tmp.exists());
+        let stats = l3.stats();
+        assert_eq!((stats.num_tiles, stats.size_bytes), (1, data.len()));
+    }
+}
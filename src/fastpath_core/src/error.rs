@@ -1,15 +1,18 @@
 //! Error types for fastpath_core.
 
-use pyo3::exceptions::PyRuntimeError;
+use pyo3::exceptions::{PyIndexError, PyRuntimeError};
 use pyo3::PyErr;
 use thiserror::Error;
 
 /// Error types for tile operations.
 #[derive(Error, Debug)]
 pub enum TileError {
-    #[error("Failed to decode JPEG: {0}")]
+    #[error("Failed to decode tile: {0}")]
     Decode(String),
 
+    #[error("Failed to encode image: {0}")]
+    Encode(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -18,11 +21,40 @@ pub enum TileError {
 
     #[error("Invalid metadata: {0}")]
     Validation(String),
+
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    #[error("Tile ({level}, {col}, {row}) is out of range: {reason}")]
+    InvalidCoord {
+        level: u32,
+        col: u32,
+        row: u32,
+        reason: String,
+    },
 }
 
 impl From<TileError> for PyErr {
     fn from(err: TileError) -> PyErr {
-        PyRuntimeError::new_err(err.to_string())
+        match err {
+            TileError::InvalidCoord { .. } => PyIndexError::new_err(err.to_string()),
+            _ => PyRuntimeError::new_err(err.to_string()),
+        }
+    }
+}
+
+impl TileError {
+    /// I/O errors worth retrying: the read may succeed if repeated shortly
+    /// (e.g. a network mount timing out). NotFound and the rest are final.
+    pub fn is_transient(&self) -> bool {
+        use std::io::ErrorKind;
+        matches!(
+            self,
+            TileError::Io(e) if matches!(
+                e.kind(),
+                ErrorKind::Interrupted | ErrorKind::TimedOut | ErrorKind::WouldBlock
+            )
+        )
     }
 }
 
@@ -1,10 +1,14 @@
 //! Slide metadata for .fastpath directories.
 
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
 
+use log::warn;
+use rayon::prelude::*;
 use serde::Deserialize;
 
 use crate::error::{TileError, TileResult};
+use crate::pack::read_level_grid;
 
 /// Information about a pyramid level.
 #[derive(Debug, Clone, Deserialize)]
@@ -15,6 +19,91 @@ pub struct LevelInfo {
     pub rows: u32,
 }
 
+impl LevelInfo {
+    /// Tiles in the level's grid, missing ones included.
+    pub fn tile_count(&self) -> u64 {
+        self.cols as u64 * self.rows as u64
+    }
+
+    /// Whether (`col`, `row`) is inside the level's grid.
+    pub fn contains(&self, col: u32, row: u32) -> bool {
+        col < self.cols && row < self.rows
+    }
+
+    /// Level size in pixels for a slide of `slide_dims` (level-0 pixels):
+    /// `ceil(dim / downsample)` per axis.
+    pub fn dimensions(&self, slide_dims: (u32, u32)) -> (u32, u32) {
+        let ds = self.downsample.max(1);
+        (slide_dims.0.div_ceil(ds), slide_dims.1.div_ceil(ds))
+    }
+
+    /// Pixel size of tile (`col`, `row`): `tile_size` square, except edge
+    /// tiles cut off by the level's right or bottom edge. None outside the
+    /// grid. A slide without dimensions (0x0) gets full-size tiles.
+    pub fn tile_dimensions(
+        &self,
+        col: u32,
+        row: u32,
+        tile_size: u32,
+        slide_dims: (u32, u32),
+    ) -> Option<(u32, u32)> {
+        if !self.contains(col, row) {
+            return None;
+        }
+        if slide_dims.0 == 0 || slide_dims.1 == 0 {
+            return Some((tile_size, tile_size));
+        }
+        let (level_w, level_h) = self.dimensions(slide_dims);
+        let edge = |pos: u32, extent: u32| {
+            extent.saturating_sub(pos.saturating_mul(tile_size)).min(tile_size)
+        };
+        Some((edge(col, level_w), edge(row, level_h)))
+    }
+}
+
+/// Opening view (region of interest) in level-0 pixel coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct DefaultView {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl DefaultView {
+    fn is_valid(&self) -> bool {
+        self.x.is_finite()
+            && self.y.is_finite()
+            && self.width.is_finite()
+            && self.height.is_finite()
+            && self.width > 0.0
+            && self.height > 0.0
+    }
+}
+
+/// Named sub-region of a slide (e.g. one core of a tissue microarray) in
+/// level-0 pixel coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct SubslideRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl SubslideRect {
+    /// The rect in the pixels of a level with `downsample`, as
+    /// (x, y, width, height); partial pixels at the edges are included.
+    pub fn level_rect(&self, downsample: u32) -> (i64, i64, u32, u32) {
+        let ds = downsample.max(1) as u64;
+        let (x, y) = (self.x as u64, self.y as u64);
+        let right = (x + self.width as u64).div_ceil(ds);
+        let bottom = (y + self.height as u64).div_ceil(ds);
+        let (left, top) = (x / ds, y / ds);
+        (left as i64, top as i64, (right - left) as u32, (bottom - top) as u32)
+    }
+}
+
 /// Metadata from metadata.json.
 #[derive(Debug, Clone, Deserialize)]
 pub struct SlideMetadata {
@@ -23,6 +112,27 @@ pub struct SlideMetadata {
     pub levels: Vec<LevelInfo>,
     pub target_mpp: f64,
     pub target_magnification: f64,
+    /// Padding (pixels) the scanner added on each side of every stored tile.
+    ///
+    /// Stored tiles are `tile_size + 2 * tile_border` wide; decoding trims the
+    /// border so the tile grid (`tile_size`) and all coordinate math are
+    /// unaffected. Unlike dzsave overlap, the border holds no image data.
+    #[serde(default)]
+    pub tile_border: u32,
+    /// Region the viewer should open on, if the scanner/annotator set one.
+    #[serde(default)]
+    pub default_view: Option<DefaultView>,
+    /// Directory name under `tiles_files/` for levels a converter didn't name
+    /// by level number (e.g. `{"0": "ds1"}`). Unlisted levels use the number.
+    #[serde(default)]
+    pub level_dir_names: HashMap<u32, String>,
+    /// Non-pyramid images (label, macro, overview) by name, as paths
+    /// relative to the .fastpath directory.
+    #[serde(default)]
+    pub associated_images: HashMap<String, String>,
+    /// Named sub-regions addressed as slides of their own (e.g. TMA cores).
+    #[serde(default)]
+    pub subslides: HashMap<String, SubslideRect>,
 }
 
 impl SlideMetadata {
@@ -30,52 +140,184 @@ impl SlideMetadata {
     pub fn load(fastpath_dir: &Path) -> TileResult<Self> {
         let metadata_path = fastpath_dir.join("metadata.json");
         let content = std::fs::read_to_string(&metadata_path)?;
-        let mut metadata: SlideMetadata = serde_json::from_str(&content)?;
+        Self::from_json(&content)
+    }
+
+    /// Parse and validate metadata from a JSON string in the
+    /// metadata.json schema.
+    pub fn from_json(json: &str) -> TileResult<Self> {
+        let mut metadata: SlideMetadata = serde_json::from_str(json)?;
         metadata.validate()?;
         Ok(metadata)
     }
 
     /// Validate metadata fields and sort levels by level number.
+    ///
+    /// Fails with the first violation `validate_all` reports.
     fn validate(&mut self) -> TileResult<()> {
+        if let Some(first) = self.validate_all().into_iter().next() {
+            return Err(TileError::Validation(first));
+        }
+        self.levels.sort_by_key(|l| l.level);
+        Ok(())
+    }
+
+    /// Read metadata.json and report every validation problem at once.
+    ///
+    /// Unlike `load`, which stops at the first error, this is for QC tooling.
+    /// I/O and JSON errors are still returned as `Err`.
+    pub fn validate_file(fastpath_dir: &Path) -> TileResult<Vec<String>> {
+        let metadata_path = fastpath_dir.join("metadata.json");
+        let content = std::fs::read_to_string(&metadata_path)?;
+        let metadata: SlideMetadata = serde_json::from_str(&content)?;
+        Ok(metadata.validate_all())
+    }
+
+    /// Collect every validation violation (empty if valid).
+    ///
+    /// The single rule list behind the fail-fast `validate`; doesn't sort
+    /// levels in place.
+    pub fn validate_all(&self) -> Vec<String> {
+        let mut errors = Vec::new();
         if self.dimensions.0 == 0 || self.dimensions.1 == 0 {
-            return Err(TileError::Validation(
-                "dimensions must be positive".into(),
-            ));
+            errors.push("dimensions must be positive".to_string());
         }
         if self.tile_size == 0 {
-            return Err(TileError::Validation(
-                "tile_size must be positive".into(),
-            ));
+            errors.push("tile_size must be positive".to_string());
         }
         if self.levels.is_empty() {
-            return Err(TileError::Validation(
-                "levels must not be empty".into(),
-            ));
+            errors.push("levels must not be empty".to_string());
         }
-        self.levels.sort_by_key(|l| l.level);
-        for (i, li) in self.levels.iter().enumerate() {
+
+        let mut levels: Vec<&LevelInfo> = self.levels.iter().collect();
+        levels.sort_by_key(|l| l.level);
+        for (i, li) in levels.iter().enumerate() {
             if li.downsample == 0 {
-                return Err(TileError::Validation(
-                    format!("level {}: downsample must be positive", li.level),
-                ));
+                errors.push(format!("level {}: downsample must be positive", li.level));
             }
             if li.cols == 0 {
-                return Err(TileError::Validation(
-                    format!("level {}: cols must be positive", li.level),
-                ));
+                errors.push(format!("level {}: cols must be positive", li.level));
             }
             if li.rows == 0 {
-                return Err(TileError::Validation(
-                    format!("level {}: rows must be positive", li.level),
-                ));
+                errors.push(format!("level {}: rows must be positive", li.level));
             }
-            if i > 0 && li.level == self.levels[i - 1].level {
-                return Err(TileError::Validation(
-                    format!("duplicate level number: {}", li.level),
-                ));
+            if i > 0 && li.level == levels[i - 1].level {
+                errors.push(format!("duplicate level number: {}", li.level));
             }
         }
-        Ok(())
+        if self.default_view.is_some_and(|v| !v.is_valid()) {
+            errors.push("default_view must be finite with positive width and height".to_string());
+        }
+        if let Some((level, name)) = self.invalid_level_dir_name() {
+            errors.push(format!(
+                "level {level}: level_dir_names entry {name:?} is not a plain directory name"
+            ));
+        }
+        if let Some((name, path)) = self.invalid_associated_image() {
+            errors.push(format!(
+                "associated image {name:?}: path {path:?} must be relative to the slide directory"
+            ));
+        }
+        if let Some(name) = self.invalid_subslide() {
+            errors.push(format!(
+                "subslide {name:?} must be non-empty and within the slide dimensions"
+            ));
+        }
+        errors
+    }
+
+    /// Extend each level's grid to the one its packed index was written with.
+    ///
+    /// Reads the cols/rows header of `tiles/level_<n>.idx` and, where it
+    /// exceeds the declared `cols`/`rows`, grows the grid so edge tiles lost
+    /// to converter rounding stay reachable. `pack_dzsave_tiles` grows the
+    /// index to cover every tile dzsave wrote. Grids are never shrunk. Levels
+    /// without an index are left alone. Returns the level numbers that were
+    /// extended.
+    pub fn reconcile_grid(&mut self, fastpath_dir: &Path) -> TileResult<Vec<u32>> {
+        let tiles_dir = fastpath_dir.join("tiles");
+        let mut extended = Vec::new();
+        for li in &mut self.levels {
+            let idx_path = tiles_dir.join(format!("level_{}.idx", li.level));
+            if !idx_path.is_file() {
+                continue;
+            }
+
+            let (idx_cols, idx_rows) = read_level_grid(&idx_path)?;
+            let (cols, rows) = (li.cols.max(idx_cols), li.rows.max(idx_rows));
+            if (cols, rows) != (li.cols, li.rows) {
+                warn!(
+                    "{}: level {} grid {}x{} under-reports its packed index; using {}x{}",
+                    fastpath_dir.display(),
+                    li.level,
+                    li.cols,
+                    li.rows,
+                    cols,
+                    rows
+                );
+                li.cols = cols;
+                li.rows = rows;
+                extended.push(li.level);
+            }
+        }
+        Ok(extended)
+    }
+
+    /// First subslide, by name, that is empty or extends past the slide's
+    /// dimensions. Names are checked in sorted order so the error is stable.
+    fn invalid_subslide(&self) -> Option<&str> {
+        let (width, height) = (self.dimensions.0 as u64, self.dimensions.1 as u64);
+        let mut names: Vec<&String> = self.subslides.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .find(|name| {
+                let r = &self.subslides[name.as_str()];
+                r.width == 0
+                    || r.height == 0
+                    || r.x as u64 + r.width as u64 > width
+                    || r.y as u64 + r.height as u64 > height
+            })
+            .map(String::as_str)
+    }
+
+    /// First `level_dir_names` entry (in level order) that isn't a single
+    /// normal path component.
+    fn invalid_level_dir_name(&self) -> Option<(u32, &str)> {
+        let mut entries: Vec<(&u32, &String)> = self.level_dir_names.iter().collect();
+        entries.sort();
+        entries
+            .into_iter()
+            .find(|(_, name)| {
+                let mut components = Path::new(name.as_str()).components();
+                !matches!(
+                    (components.next(), components.next()),
+                    (Some(Component::Normal(_)), None)
+                )
+            })
+            .map(|(&level, name)| (level, name.as_str()))
+    }
+
+    /// First `associated_images` entry (in name order) whose path could leave
+    /// the slide directory.
+    fn invalid_associated_image(&self) -> Option<(&str, &str)> {
+        let mut entries: Vec<(&String, &String)> = self.associated_images.iter().collect();
+        entries.sort();
+        entries
+            .into_iter()
+            .find(|(_, path)| {
+                let path = Path::new(path.as_str());
+                path.as_os_str().is_empty()
+                    || !path.components().all(|c| matches!(c, Component::Normal(_)))
+            })
+            .map(|(name, path)| (name.as_str(), path.as_str()))
+    }
+
+    /// Path of the associated image `name` under `fastpath_dir`, if listed.
+    pub fn associated_image_path(&self, fastpath_dir: &Path, name: &str) -> Option<PathBuf> {
+        self.associated_images
+            .get(name)
+            .map(|path| fastpath_dir.join(path))
     }
 
     /// Get level info by level number.
@@ -87,6 +329,106 @@ impl SlideMetadata {
     pub fn num_levels(&self) -> usize {
         self.levels.len()
     }
+
+    /// Level whose resolution (`target_mpp * downsample`) is closest to
+    /// `mpp` microns per pixel.
+    ///
+    /// Closeness is by ratio, so 1.0 mpp is as far from 0.5 as from 2.0; ties
+    /// go to the finer level. None if `mpp` isn't a positive finite number or
+    /// there are no levels.
+    pub fn level_for_mpp(&self, mpp: f64) -> Option<u32> {
+        if !(mpp.is_finite() && mpp > 0.0) {
+            return None;
+        }
+        let distance = |l: &LevelInfo| (self.target_mpp * l.downsample as f64 / mpp).ln().abs();
+        self.levels
+            .iter()
+            .min_by(|a, b| {
+                distance(a)
+                    .total_cmp(&distance(b))
+                    .then(a.downsample.cmp(&b.downsample))
+            })
+            .map(|l| l.level)
+    }
+}
+
+/// Directory of `level` under `tiles_files/`: its `names` entry, else the number.
+pub fn level_dir_name(names: &HashMap<u32, String>, level: u32) -> String {
+    names
+        .get(&level)
+        .cloned()
+        .unwrap_or_else(|| level.to_string())
+}
+
+/// `level_dir_names` from `fastpath_dir/metadata.json`, if the file exists.
+///
+/// Only that field is read, so this works before the rest of the metadata is
+/// final (e.g. when packing a converter's output).
+pub fn read_level_dir_names(fastpath_dir: &Path) -> TileResult<HashMap<u32, String>> {
+    #[derive(Deserialize)]
+    struct LevelDirNames {
+        #[serde(default)]
+        level_dir_names: HashMap<u32, String>,
+    }
+
+    let metadata_path = fastpath_dir.join("metadata.json");
+    if !metadata_path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = std::fs::read_to_string(&metadata_path)?;
+    let names: LevelDirNames = serde_json::from_str(&content)?;
+    Ok(names.level_dir_names)
+}
+
+/// Directory suffixes the preprocessor gives slide outputs.
+const SLIDE_DIR_EXTENSIONS: [&str; 2] = ["fastpath", "fastpath_native"];
+
+/// One slide directory found by [`catalog_dir`].
+#[derive(Debug)]
+pub struct CatalogEntry {
+    pub path: PathBuf,
+    /// Parsed metadata, or the load error message for an invalid slide.
+    pub metadata: Result<SlideMetadata, String>,
+}
+
+/// Find every slide directory under `root` and load its metadata in parallel.
+///
+/// Slide directories are not descended into. Entries are sorted by path;
+/// invalid slides are reported as error entries rather than failing the scan.
+pub fn catalog_dir(root: &Path) -> TileResult<Vec<CatalogEntry>> {
+    let mut slide_dirs = Vec::new();
+    collect_slide_dirs(root, &mut slide_dirs)?;
+    slide_dirs.sort();
+
+    Ok(slide_dirs
+        .into_par_iter()
+        .map(|path| {
+            let metadata = SlideMetadata::load(&path).map_err(|e| e.to_string());
+            CatalogEntry { path, metadata }
+        })
+        .collect())
+}
+
+fn is_slide_dir(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SLIDE_DIR_EXTENSIONS.contains(&ext))
+}
+
+fn collect_slide_dirs(dir: &Path, out: &mut Vec<PathBuf>) -> TileResult<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if is_slide_dir(&path) {
+            out.push(path);
+        } else if let Err(e) = collect_slide_dirs(&path, out) {
+            // An unreadable subdirectory shouldn't abort the whole scan
+            warn!("Skipping {}: {e}", path.display());
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -107,9 +449,35 @@ mod tests {
             ],
             target_mpp: 0.5,
             target_magnification: 20.0,
+            tile_border: 0,
+            default_view: None,
+            level_dir_names: HashMap::new(),
+            associated_images: HashMap::new(),
+            subslides: HashMap::new(),
         }
     }
 
+    #[test]
+    fn test_level_info_tile_helpers() {
+        let metadata = valid_metadata();
+        // Level 1: 1000x2000 / 4 = 250x500 pixels in one 512px tile column
+        let level = &metadata.levels[1];
+        assert_eq!(level.tile_count(), 8);
+        assert_eq!(level.dimensions(metadata.dimensions), (250, 500));
+        assert!(level.contains(1, 3));
+        assert!(!level.contains(2, 0) && !level.contains(0, 4));
+
+        let dims = metadata.dimensions;
+        // Level 2 is 1000x2000: the last column is 1000 - 512 = 488 wide
+        let full = &metadata.levels[2];
+        assert_eq!(full.tile_dimensions(0, 0, 512, dims), Some((512, 512)));
+        assert_eq!(full.tile_dimensions(1, 3, 512, dims), Some((488, 464)));
+        assert_eq!(full.tile_dimensions(4, 0, 512, dims), None);
+        // Grid cells past the pixel extent are empty; no size means full tiles
+        assert_eq!(level.tile_dimensions(1, 0, 512, dims), Some((0, 500)));
+        assert_eq!(level.tile_dimensions(1, 0, 512, (0, 0)), Some((512, 512)));
+    }
+
     /// Write metadata JSON to a temp dir and load it via SlideMetadata::load().
     fn write_and_load(dir: &Path, json: &str) -> TileResult<SlideMetadata> {
         fs::write(dir.join("metadata.json"), json).unwrap();
@@ -135,6 +503,188 @@ mod tests {
         assert_eq!(metadata.num_levels(), 3);
     }
 
+    #[test]
+    fn test_subslides_parse_and_validate() {
+        let json = |subslides: &str| {
+            format!(
+                r#"{{
+                    "dimensions": [1000, 2000],
+                    "tile_size": 512,
+                    "levels": [{{"level": 0, "downsample": 1, "cols": 2, "rows": 4}}],
+                    "target_mpp": 0.5,
+                    "target_magnification": 20.0
+                    {subslides}
+                }}"#
+            )
+        };
+        assert!(SlideMetadata::from_json(&json("")).unwrap().subslides.is_empty());
+
+        let core = r#", "subslides": {"A1": {"x": 101, "y": 0, "width": 899, "height": 50}}"#;
+        let metadata = SlideMetadata::from_json(&json(core)).unwrap();
+        let rect = metadata.subslides["A1"];
+        assert_eq!(rect.level_rect(1), (101, 0, 899, 50));
+        // 101..1000 covers level pixels 50..500 at downsample 2
+        assert_eq!(rect.level_rect(2), (50, 0, 450, 25));
+
+        let outside = r#", "subslides": {"A1": {"x": 500, "y": 0, "width": 501, "height": 1}}"#;
+        assert!(SlideMetadata::from_json(&json(outside)).is_err());
+        let empty = r#", "subslides": {"A1": {"x": 0, "y": 0, "width": 0, "height": 1}}"#;
+        let errors = SlideMetadata::from_json(&json(empty)).unwrap_err().to_string();
+        assert!(errors.contains("subslide \"A1\""), "{errors}");
+
+        // With several bad subslides the error names the first in sorted order
+        let several = r#", "subslides": {
+            "C3": {"x": 0, "y": 0, "width": 0, "height": 1},
+            "B2": {"x": 999, "y": 0, "width": 2, "height": 1},
+            "A1": {"x": 0, "y": 0, "width": 1, "height": 1},
+            "D4": {"x": 0, "y": 1999, "width": 1, "height": 2}
+        }"#;
+        for _ in 0..8 {
+            let errors = SlideMetadata::from_json(&json(several)).unwrap_err().to_string();
+            assert!(errors.contains("subslide \"B2\""), "{errors}");
+        }
+    }
+
+    #[test]
+    fn test_from_json_validates() {
+        let json = r#"{
+            "dimensions": [1000, 2000],
+            "tile_size": 512,
+            "levels": [
+                {"level": 1, "downsample": 1, "cols": 2, "rows": 4},
+                {"level": 0, "downsample": 2, "cols": 1, "rows": 2}
+            ],
+            "target_mpp": 0.5,
+            "target_magnification": 20.0
+        }"#;
+        let metadata = SlideMetadata::from_json(json).unwrap();
+        assert_eq!(metadata.levels[0].level, 0);
+
+        let invalid = json.replace("\"tile_size\": 512", "\"tile_size\": 0");
+        assert!(matches!(
+            SlideMetadata::from_json(&invalid),
+            Err(TileError::Validation(_))
+        ));
+        assert!(SlideMetadata::from_json("{").is_err());
+    }
+
+    #[test]
+    fn test_default_view_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let json = |view: &str| {
+            format!(
+                r#"{{
+                    "dimensions": [1000, 2000],
+                    "tile_size": 512,
+                    "levels": [{{"level": 0, "downsample": 1, "cols": 2, "rows": 4}}],
+                    "target_mpp": 0.5,
+                    "target_magnification": 20.0
+                    {view}
+                }}"#
+            )
+        };
+
+        let metadata = write_and_load(temp.path(), &json("")).unwrap();
+        assert_eq!(metadata.default_view, None);
+
+        let view = r#", "default_view": {"x": 100, "y": 200.5, "width": 300, "height": 400}"#;
+        let metadata = write_and_load(temp.path(), &json(view)).unwrap();
+        assert_eq!(
+            metadata.default_view,
+            Some(DefaultView {
+                x: 100.0,
+                y: 200.5,
+                width: 300.0,
+                height: 400.0
+            })
+        );
+
+        let empty = r#", "default_view": {"x": 0, "y": 0, "width": 0, "height": 10}"#;
+        assert!(write_and_load(temp.path(), &json(empty)).is_err());
+    }
+
+    #[test]
+    fn test_level_dir_names() {
+        let temp = TempDir::new().unwrap();
+        let json = |names: &str| {
+            format!(
+                r#"{{
+                    "dimensions": [1024, 512],
+                    "tile_size": 512,
+                    "levels": [
+                        {{"level": 0, "downsample": 2, "cols": 1, "rows": 1}},
+                        {{"level": 1, "downsample": 1, "cols": 1, "rows": 1}}
+                    ],
+                    "target_mpp": 0.5,
+                    "target_magnification": 20.0,
+                    "level_dir_names": {names}
+                }}"#
+            )
+        };
+
+        let metadata = write_and_load(temp.path(), &json(r#"{"1": "ds1"}"#)).unwrap();
+        assert_eq!(level_dir_name(&metadata.level_dir_names, 0), "0");
+        assert_eq!(level_dir_name(&metadata.level_dir_names, 1), "ds1");
+
+        for bad in [r#"{"0": "../ds1"}"#, r#"{"0": ""}"#, r#"{"0": "a/b"}"#] {
+            let err = write_and_load(temp.path(), &json(bad)).unwrap_err();
+            assert!(err.to_string().contains("not a plain directory name"), "{bad}");
+        }
+
+        // With several bad entries the error names the lowest level
+        let several = r#"{"1": "a/b", "0": "../ds0"}"#;
+        for _ in 0..8 {
+            let err = write_and_load(temp.path(), &json(several)).unwrap_err();
+            assert!(err.to_string().contains("level 0:"), "{err}");
+            assert!(!err.to_string().contains("level 1:"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_associated_images() {
+        let temp = TempDir::new().unwrap();
+        let json = |images: &str| {
+            format!(
+                r#"{{
+                    "dimensions": [1000, 2000],
+                    "tile_size": 512,
+                    "levels": [{{"level": 0, "downsample": 1, "cols": 2, "rows": 4}}],
+                    "target_mpp": 0.5,
+                    "target_magnification": 20.0
+                    {images}
+                }}"#
+            )
+        };
+
+        let metadata = write_and_load(temp.path(), &json("")).unwrap();
+        assert!(metadata.associated_images.is_empty());
+
+        let images = r#", "associated_images": {"label": "associated/label.png"}"#;
+        let metadata = write_and_load(temp.path(), &json(images)).unwrap();
+        assert_eq!(
+            metadata.associated_image_path(temp.path(), "label"),
+            Some(temp.path().join("associated/label.png"))
+        );
+        assert_eq!(metadata.associated_image_path(temp.path(), "macro"), None);
+
+        for bad in ["../label.png", "/tmp/label.png", ""] {
+            let images = format!(r#", "associated_images": {{"label": "{bad}"}}"#);
+            let err = write_and_load(temp.path(), &json(&images)).unwrap_err();
+            assert!(err.to_string().contains("must be relative"), "{bad}");
+        }
+
+        // With several bad paths the error names the first image in sorted order
+        let several = r#", "associated_images": {
+            "thumbnail": "/tmp/thumb.png",
+            "macro": "associated/macro.png",
+            "label": "../label.png"
+        }"#;
+        for _ in 0..8 {
+            let err = write_and_load(temp.path(), &json(several)).unwrap_err();
+            assert!(err.to_string().contains("associated image \"label\""), "{err}");
+        }
+    }
+
     #[test]
     fn test_validate_empty_levels() {
         let mut m = valid_metadata();
@@ -208,9 +758,123 @@ mod tests {
             ],
             target_mpp: 0.5,
             target_magnification: 20.0,
+            tile_border: 0,
+            default_view: None,
+            level_dir_names: HashMap::new(),
+            associated_images: HashMap::new(),
+            subslides: HashMap::new(),
         };
         m.validate().unwrap();
         let level_nums: Vec<u32> = m.levels.iter().map(|l| l.level).collect();
         assert_eq!(level_nums, vec![0, 1, 2]);
     }
+
+    #[test]
+    fn test_validate_all_valid() {
+        assert!(valid_metadata().validate_all().is_empty());
+    }
+
+    #[test]
+    fn test_validate_all_reports_every_violation() {
+        let mut m = valid_metadata();
+        m.dimensions = (0, 2000);
+        m.tile_size = 0;
+        m.levels[0].downsample = 0;
+        m.levels[1].cols = 0;
+        m.levels[2].level = 1; // duplicate of levels[1]
+
+        let errors = m.validate_all();
+        assert_eq!(
+            errors,
+            vec![
+                "dimensions must be positive",
+                "tile_size must be positive",
+                "level 0: downsample must be positive",
+                "level 1: cols must be positive",
+                "duplicate level number: 1",
+            ]
+        );
+        // Fail-fast validate still stops at the first one
+        assert!(m.validate().unwrap_err().to_string().contains("dimensions"));
+    }
+
+    #[test]
+    fn test_validate_file() {
+        let temp = TempDir::new().unwrap();
+        assert!(SlideMetadata::validate_file(temp.path()).is_err());
+
+        let json = r#"{
+            "dimensions": [0, 0],
+            "tile_size": 512,
+            "levels": [],
+            "target_mpp": 0.5,
+            "target_magnification": 20.0
+        }"#;
+        fs::write(temp.path().join("metadata.json"), json).unwrap();
+        let errors = SlideMetadata::validate_file(temp.path()).unwrap();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_catalog_dir_mixed_tree() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        let json = r#"{
+            "dimensions": [1000, 2000],
+            "tile_size": 512,
+            "levels": [{"level": 0, "downsample": 1, "cols": 2, "rows": 4}],
+            "target_mpp": 0.5,
+            "target_magnification": 20.0
+        }"#;
+
+        let valid = root.join("a.fastpath");
+        fs::create_dir(&valid).unwrap();
+        fs::write(valid.join("metadata.json"), json).unwrap();
+
+        let nested = root.join("cases/b.fastpath_native");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("metadata.json"), json).unwrap();
+
+        let missing = root.join("c.fastpath");
+        fs::create_dir(&missing).unwrap();
+
+        let corrupt = root.join("d.fastpath");
+        fs::create_dir(&corrupt).unwrap();
+        fs::write(corrupt.join("metadata.json"), "{not json").unwrap();
+
+        // Non-slide dirs and files are ignored
+        fs::create_dir(root.join("other")).unwrap();
+        fs::write(root.join("e.fastpath"), "file, not dir").unwrap();
+
+        let entries = catalog_dir(root).unwrap();
+        let paths: Vec<&Path> = entries.iter().map(|e| e.path.as_path()).collect();
+        let mut expected = vec![valid.as_path(), nested.as_path(), missing.as_path(), corrupt.as_path()];
+        expected.sort();
+        assert_eq!(paths, expected);
+
+        let by_path = |p: &Path| entries.iter().find(|e| e.path == p).unwrap();
+        assert_eq!(by_path(&valid).metadata.as_ref().unwrap().dimensions, (1000, 2000));
+        assert_eq!(by_path(&nested).metadata.as_ref().unwrap().num_levels(), 1);
+        assert!(by_path(&missing).metadata.is_err());
+        assert!(by_path(&corrupt).metadata.is_err());
+    }
+
+    #[test]
+    fn test_catalog_dir_missing_root() {
+        assert!(catalog_dir(Path::new("/nonexistent/catalog/root")).is_err());
+    }
+
+    #[test]
+    fn test_level_for_mpp_picks_closest_resolution() {
+        // Levels at 4.0, 2.0 and 0.5 mpp
+        let meta = valid_metadata();
+        assert_eq!(meta.level_for_mpp(0.5), Some(2));
+        assert_eq!(meta.level_for_mpp(0.25), Some(2));
+        assert_eq!(meta.level_for_mpp(1.5), Some(1));
+        assert_eq!(meta.level_for_mpp(100.0), Some(0));
+        // 1.0 mpp is 2x from both 2.0 and 0.5: the finer level wins
+        assert_eq!(meta.level_for_mpp(1.0), Some(2));
+        assert_eq!(meta.level_for_mpp(0.0), None);
+        assert_eq!(meta.level_for_mpp(f64::NAN), None);
+    }
 }
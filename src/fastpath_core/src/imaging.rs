@@ -0,0 +1,190 @@
+//! RGB image resizing and encoding helpers.
+//!
+//! Used to produce finished images (viewport snapshots) in Rust so the
+//! pixel work and encoding stay off the GIL.
+
+use crate::error::{TileError, TileResult};
+
+/// Output encoding for a finished image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    /// Lossless (VP8L): the pure-Rust encoder has no lossy mode.
+    WebP,
+}
+
+impl ImageFormat {
+    /// Parse a format name ("png", "jpeg"/"jpg", "webp", case-insensitive).
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "jpeg" | "jpg" => Some(Self::Jpeg),
+            "webp" => Some(Self::WebP),
+            _ => None,
+        }
+    }
+}
+
+/// Resize an RGB image with a box filter.
+///
+/// Each output pixel averages the source pixels its footprint covers
+/// (at least one), so downscaling doesn't alias and upscaling degrades to
+/// nearest-neighbor.
+pub fn resize_rgb(src: &[u8], src_w: u32, src_h: u32, dst_w: u32, dst_h: u32) -> Vec<u8> {
+    debug_assert_eq!(src.len(), src_w as usize * src_h as usize * 3);
+    if (src_w, src_h) == (dst_w, dst_h) {
+        return src.to_vec();
+    }
+
+    // Source span [start, end) covered by destination index `i`
+    let span = |i: u32, src_len: u32, dst_len: u32| -> (usize, usize) {
+        let start = (i as u64 * src_len as u64 / dst_len as u64) as usize;
+        let end = ((i as u64 + 1) * src_len as u64).div_ceil(dst_len as u64) as usize;
+        (start, end.max(start + 1).min(src_len as usize))
+    };
+
+    let src_stride = src_w as usize * 3;
+    let mut out = Vec::with_capacity(dst_w as usize * dst_h as usize * 3);
+    for dy in 0..dst_h {
+        let (y0, y1) = span(dy, src_h, dst_h);
+        for dx in 0..dst_w {
+            let (x0, x1) = span(dx, src_w, dst_w);
+            let mut sum = [0u32; 3];
+            for y in y0..y1 {
+                let row = &src[y * src_stride + x0 * 3..y * src_stride + x1 * 3];
+                for px in row.chunks_exact(3) {
+                    sum[0] += px[0] as u32;
+                    sum[1] += px[1] as u32;
+                    sum[2] += px[2] as u32;
+                }
+            }
+            let count = ((y1 - y0) * (x1 - x0)) as u32;
+            out.extend(sum.iter().map(|&s| ((s + count / 2) / count) as u8));
+        }
+    }
+    out
+}
+
+/// Encode an RGB image as PNG, JPEG or lossless WebP (`quality` 1-100,
+/// JPEG only).
+pub fn encode_rgb(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    format: ImageFormat,
+    quality: u8,
+) -> TileResult<Vec<u8>> {
+    encode_rgb_with_icc(data, width, height, format, quality, None)
+}
+
+/// `encode_rgb` embedding `icc_profile` (JPEG APP2, PNG iCCP, WebP ICCP),
+/// so a re-encoded tile keeps the color space of its source.
+pub fn encode_rgb_with_icc(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    format: ImageFormat,
+    quality: u8,
+    icc_profile: Option<&[u8]>,
+) -> TileResult<Vec<u8>> {
+    let mut out = Vec::new();
+    match format {
+        ImageFormat::Png => {
+            let mut info = png::Info::with_size(width, height);
+            info.color_type = png::ColorType::Rgb;
+            info.bit_depth = png::BitDepth::Eight;
+            info.icc_profile = icc_profile.map(std::borrow::Cow::Borrowed);
+            let encoder = png::Encoder::with_info(&mut out, info)
+                .map_err(|e| TileError::Encode(e.to_string()))?;
+            let mut writer = encoder
+                .write_header()
+                .map_err(|e| TileError::Encode(e.to_string()))?;
+            writer
+                .write_image_data(data)
+                .map_err(|e| TileError::Encode(e.to_string()))?;
+        }
+        ImageFormat::Jpeg => {
+            let (w, h) = match (u16::try_from(width), u16::try_from(height)) {
+                (Ok(w), Ok(h)) => (w, h),
+                _ => {
+                    return Err(TileError::Validation(format!(
+                        "JPEG dimensions exceed 65535: {width}x{height}"
+                    )))
+                }
+            };
+            let mut encoder = jpeg_encoder::Encoder::new(&mut out, quality.clamp(1, 100));
+            if let Some(icc) = icc_profile {
+                encoder
+                    .add_icc_profile(icc)
+                    .map_err(|e| TileError::Encode(e.to_string()))?;
+            }
+            encoder
+                .encode(data, w, h, jpeg_encoder::ColorType::Rgb)
+                .map_err(|e| TileError::Encode(e.to_string()))?;
+        }
+        ImageFormat::WebP => {
+            let mut encoder = image_webp::WebPEncoder::new(&mut out);
+            if let Some(icc) = icc_profile {
+                encoder.set_icc_profile(icc.to_vec());
+            }
+            encoder
+                .encode(data, width, height, image_webp::ColorType::Rgb8)
+                .map_err(|e| TileError::Encode(e.to_string()))?;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::{decode_tile_bytes, CompressedTileData};
+
+    fn decode(bytes: Vec<u8>) -> (u32, u32, Vec<u8>) {
+        let tile = decode_tile_bytes(&CompressedTileData {
+            jpeg_bytes: bytes.into(),
+            width: 0,
+            height: 0,
+        })
+        .unwrap();
+        (tile.width, tile.height, tile.data.to_vec())
+    }
+
+    #[test]
+    fn test_resize_rgb_box_average() {
+        // 2x2 → 1x1 averages all four pixels
+        let src = [0, 0, 0, 100, 100, 100, 200, 200, 200, 100, 100, 100];
+        assert_eq!(resize_rgb(&src, 2, 2, 1, 1), vec![100, 100, 100]);
+
+        // 1x1 → 2x2 replicates
+        assert_eq!(resize_rgb(&[9, 8, 7], 1, 1, 2, 2), [9, 8, 7].repeat(4));
+
+        // 3x1 → 2x1 covers every source pixel; the middle one feeds both halves
+        let out = resize_rgb(&[0, 0, 0, 90, 90, 90, 180, 180, 180], 3, 1, 2, 1);
+        assert_eq!(out, vec![45, 45, 45, 135, 135, 135]);
+    }
+
+    #[test]
+    fn test_encode_png_roundtrip() {
+        let pixels: Vec<u8> = (0..4 * 3 * 3).map(|i| i as u8).collect();
+        let png = encode_rgb(&pixels, 4, 3, ImageFormat::Png, 90).unwrap();
+        assert_eq!(decode(png), (4, 3, pixels));
+    }
+
+    #[test]
+    fn test_encode_jpeg_decodable() {
+        let pixels = vec![128u8; 16 * 8 * 3];
+        let jpeg = encode_rgb(&pixels, 16, 8, ImageFormat::Jpeg, 85).unwrap();
+        let (w, h, data) = decode(jpeg);
+        assert_eq!((w, h), (16, 8));
+        assert!(data.iter().all(|&v| v.abs_diff(128) <= 2));
+    }
+
+    #[test]
+    fn test_image_format_parse() {
+        assert_eq!(ImageFormat::parse("PNG"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::parse("jpg"), Some(ImageFormat::Jpeg));
+        assert_eq!(ImageFormat::parse("gif"), None);
+    }
+}
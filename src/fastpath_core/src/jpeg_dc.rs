@@ -0,0 +1,600 @@
+//! DC-only JPEG decode for instant 1/8-scale previews.
+//!
+//! The DC coefficient of each 8x8 block is the block's mean sample value, so
+//! decoding only the DC terms yields the image at 1/8 scale with no IDCT; AC
+//! terms are Huffman-decoded just far enough to skip them. zune-jpeg has no
+//! reduced-resolution mode, hence this small decoder. It handles baseline
+//! (and extended sequential, 8-bit) Huffman JPEGs with one interleaved scan,
+//! which is what slide converters write; progressive, arithmetic-coded and
+//! CMYK files are rejected so the caller can fall back.
+
+use crate::decoder::TileData;
+use crate::error::{TileError, TileResult};
+
+/// Code lengths resolved by a single table lookup; longer codes take the
+/// canonical slow path.
+const LOOKUP_BITS: u32 = 9;
+
+fn preview_error(reason: &str) -> TileError {
+    TileError::Decode(format!("DC preview: {reason}"))
+}
+
+/// Canonical Huffman table (JPEG Annex C).
+struct Huffman {
+    /// (code length, symbol) per `LOOKUP_BITS`-bit prefix; length 0 means
+    /// the code is longer than the lookup.
+    lookup: Vec<(u8, u8)>,
+    /// Largest code of each length (index 1..=16), -1 if there is none.
+    max_code: [i32; 17],
+    /// Added to a code of each length to get its index in `values`.
+    value_offset: [i32; 17],
+    values: Vec<u8>,
+}
+
+impl Huffman {
+    fn new(counts: &[u8], values: Vec<u8>) -> TileResult<Self> {
+        let mut lookup = vec![(0u8, 0u8); 1 << LOOKUP_BITS];
+        let mut max_code = [-1i32; 17];
+        let mut value_offset = [0i32; 17];
+        let mut code = 0i32;
+        let mut k = 0usize;
+        for len in 1..=16u32 {
+            let count = counts[len as usize - 1] as usize;
+            value_offset[len as usize] = k as i32 - code;
+            for _ in 0..count {
+                if code >= 1 << len || k >= values.len() {
+                    return Err(preview_error("invalid Huffman table"));
+                }
+                if len <= LOOKUP_BITS {
+                    let shift = LOOKUP_BITS - len;
+                    let start = (code as usize) << shift;
+                    lookup[start..start + (1 << shift)].fill((len as u8, values[k]));
+                }
+                code += 1;
+                k += 1;
+            }
+            if count > 0 {
+                max_code[len as usize] = code - 1;
+            }
+            code <<= 1;
+        }
+        Ok(Self {
+            lookup,
+            max_code,
+            value_offset,
+            values,
+        })
+    }
+}
+
+/// MSB-first reader over entropy-coded data, undoing 0xFF00 byte stuffing.
+///
+/// Stops at the first marker and feeds zero bits from there on, as the
+/// decoder only needs to reach the marker on well-formed input.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u64,
+    count: u32,
+    at_marker: bool,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            buf: 0,
+            count: 0,
+            at_marker: false,
+        }
+    }
+
+    fn fill(&mut self) {
+        while self.count <= 56 {
+            let mut byte = 0;
+            if !self.at_marker && self.pos < self.data.len() {
+                byte = self.data[self.pos];
+                if byte != 0xFF {
+                    self.pos += 1;
+                } else if self.data.get(self.pos + 1) == Some(&0x00) {
+                    self.pos += 2;
+                } else {
+                    self.at_marker = true;
+                    byte = 0;
+                }
+            }
+            self.buf |= (byte as u64) << (56 - self.count);
+            self.count += 8;
+        }
+    }
+
+    fn peek(&mut self, n: u32) -> u32 {
+        self.fill();
+        (self.buf >> (64 - n)) as u32
+    }
+
+    fn consume(&mut self, n: u32) {
+        self.buf <<= n;
+        self.count -= n;
+    }
+
+    fn bits(&mut self, n: u32) -> u32 {
+        if n == 0 {
+            return 0;
+        }
+        let value = self.peek(n);
+        self.consume(n);
+        value
+    }
+
+    fn decode(&mut self, table: &Huffman) -> TileResult<u8> {
+        let (len, symbol) = table.lookup[self.peek(LOOKUP_BITS) as usize];
+        if len > 0 {
+            self.consume(len as u32);
+            return Ok(symbol);
+        }
+        let bits = self.peek(16) as i32;
+        for len in LOOKUP_BITS + 1..=16 {
+            let code = bits >> (16 - len);
+            if code <= table.max_code[len as usize] {
+                self.consume(len);
+                let index = (code + table.value_offset[len as usize]) as usize;
+                return table
+                    .values
+                    .get(index)
+                    .copied()
+                    .ok_or_else(|| preview_error("corrupt Huffman code"));
+            }
+        }
+        Err(preview_error("corrupt Huffman code"))
+    }
+
+    /// Read an `s`-bit magnitude category value (JPEG `RECEIVE` + `EXTEND`).
+    fn receive_extend(&mut self, s: u32) -> i32 {
+        if s == 0 {
+            return 0;
+        }
+        let value = self.bits(s) as i32;
+        if value < 1 << (s - 1) {
+            value - (1 << s) + 1
+        } else {
+            value
+        }
+    }
+
+    /// Drop buffered bits and continue after the next RSTn marker.
+    fn restart(&mut self) {
+        self.buf = 0;
+        self.count = 0;
+        self.at_marker = false;
+        while self.pos + 1 < self.data.len() {
+            let (byte, next) = (self.data[self.pos], self.data[self.pos + 1]);
+            self.pos += 1;
+            if byte == 0xFF && (0xD0..=0xD7).contains(&next) {
+                self.pos += 1;
+                return;
+            }
+        }
+    }
+}
+
+struct Component {
+    id: u8,
+    h: u32,
+    v: u32,
+    quant: usize,
+    dc_table: usize,
+    ac_table: usize,
+}
+
+struct Frame {
+    width: u32,
+    height: u32,
+    components: Vec<Component>,
+}
+
+#[derive(Default)]
+struct Tables {
+    /// First (DC) entry of each quantization table.
+    dc_quant: [u16; 4],
+    dc: [Option<Huffman>; 4],
+    ac: [Option<Huffman>; 4],
+    restart_interval: u32,
+    /// Color transform from an Adobe APP14 segment (0 = none, i.e. RGB).
+    adobe_transform: Option<u8>,
+}
+
+/// Decode `bytes` to RGB at 1/8 scale (each dimension rounded up) from the
+/// DC coefficients alone.
+pub fn decode_dc_preview(bytes: &[u8]) -> TileResult<TileData> {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return Err(preview_error("not a JPEG"));
+    }
+    let truncated = || preview_error("truncated JPEG");
+    let mut tables = Tables::default();
+    let mut frame = None;
+    let mut pos = 2;
+    loop {
+        while bytes.get(pos).is_some_and(|&b| b != 0xFF) {
+            pos += 1;
+        }
+        while bytes.get(pos) == Some(&0xFF) {
+            pos += 1;
+        }
+        let marker = *bytes.get(pos).ok_or_else(truncated)?;
+        pos += 1;
+        match marker {
+            0xD9 => return Err(preview_error("no image data")),
+            0x01 | 0xD0..=0xD7 => continue,
+            _ => {}
+        }
+        let len = bytes
+            .get(pos..pos + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+            .filter(|&len| len >= 2)
+            .ok_or_else(truncated)?;
+        let segment = bytes.get(pos + 2..pos + len).ok_or_else(truncated)?;
+        pos += len;
+        match marker {
+            0xC0 | 0xC1 => frame = Some(parse_frame(segment)?),
+            0xC2 | 0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => {
+                return Err(preview_error("only sequential Huffman JPEGs are supported"));
+            }
+            0xC4 => parse_huffman(segment, &mut tables)?,
+            0xDB => parse_quant(segment, &mut tables)?,
+            0xDD => {
+                let interval = segment.get(0..2).ok_or_else(truncated)?;
+                tables.restart_interval = u16::from_be_bytes([interval[0], interval[1]]) as u32;
+            }
+            0xEE if segment.starts_with(b"Adobe") && segment.len() >= 12 => {
+                tables.adobe_transform = Some(segment[11]);
+            }
+            0xDA => {
+                let frame = frame.ok_or_else(|| preview_error("scan before frame header"))?;
+                return decode_scan(segment, &bytes[pos..], frame, &tables);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_frame(segment: &[u8]) -> TileResult<Frame> {
+    let header = segment
+        .get(0..6)
+        .ok_or_else(|| preview_error("short frame header"))?;
+    if header[0] != 8 {
+        return Err(preview_error("only 8-bit JPEGs are supported"));
+    }
+    let height = u16::from_be_bytes([header[1], header[2]]) as u32;
+    let width = u16::from_be_bytes([header[3], header[4]]) as u32;
+    let count = header[5] as usize;
+    if width == 0 || height == 0 {
+        return Err(preview_error("image size not in frame header"));
+    }
+    if count != 1 && count != 3 {
+        return Err(preview_error(
+            "only grayscale and 3-component JPEGs are supported",
+        ));
+    }
+    let specs = segment
+        .get(6..6 + 3 * count)
+        .ok_or_else(|| preview_error("short frame header"))?;
+    let components = specs
+        .chunks_exact(3)
+        .map(|spec| {
+            let (h, v) = ((spec[1] >> 4) as u32, (spec[1] & 0x0F) as u32);
+            if !(1..=4).contains(&h) || !(1..=4).contains(&v) || spec[2] > 3 {
+                return Err(preview_error("invalid component"));
+            }
+            Ok(Component {
+                id: spec[0],
+                h,
+                v,
+                quant: spec[2] as usize,
+                dc_table: 0,
+                ac_table: 0,
+            })
+        })
+        .collect::<TileResult<_>>()?;
+    Ok(Frame {
+        width,
+        height,
+        components,
+    })
+}
+
+fn parse_huffman(mut segment: &[u8], tables: &mut Tables) -> TileResult<()> {
+    while !segment.is_empty() {
+        let counts = segment
+            .get(1..17)
+            .ok_or_else(|| preview_error("short Huffman table"))?;
+        let total: usize = counts.iter().map(|&c| c as usize).sum();
+        let values = segment
+            .get(17..17 + total)
+            .ok_or_else(|| preview_error("short Huffman table"))?;
+        let table = Huffman::new(counts, values.to_vec())?;
+        let (class, id) = (segment[0] >> 4, (segment[0] & 0x03) as usize);
+        match class {
+            0 => tables.dc[id] = Some(table),
+            1 => tables.ac[id] = Some(table),
+            _ => return Err(preview_error("invalid Huffman table class")),
+        }
+        segment = &segment[17 + total..];
+    }
+    Ok(())
+}
+
+fn parse_quant(mut segment: &[u8], tables: &mut Tables) -> TileResult<()> {
+    while !segment.is_empty() {
+        let (precision, id) = (segment[0] >> 4, (segment[0] & 0x03) as usize);
+        let len = if precision == 0 { 65 } else { 129 };
+        let table = segment
+            .get(1..len)
+            .ok_or_else(|| preview_error("short quantization table"))?;
+        tables.dc_quant[id] = match precision {
+            0 => table[0] as u16,
+            _ => u16::from_be_bytes([table[0], table[1]]),
+        };
+        segment = &segment[len..];
+    }
+    Ok(())
+}
+
+fn decode_scan(
+    header: &[u8],
+    data: &[u8],
+    mut frame: Frame,
+    tables: &Tables,
+) -> TileResult<TileData> {
+    let count = *header
+        .first()
+        .ok_or_else(|| preview_error("short scan header"))? as usize;
+    if count != frame.components.len() {
+        return Err(preview_error("only single-scan JPEGs are supported"));
+    }
+    let specs = header
+        .get(1..1 + 2 * count + 3)
+        .ok_or_else(|| preview_error("short scan header"))?;
+    for spec in specs[..2 * count].chunks_exact(2) {
+        let component = frame
+            .components
+            .iter_mut()
+            .find(|c| c.id == spec[0])
+            .ok_or_else(|| preview_error("scan names an unknown component"))?;
+        component.dc_table = (spec[1] >> 4) as usize & 0x03;
+        component.ac_table = (spec[1] & 0x03) as usize;
+    }
+    if specs[2 * count..] != [0, 63, 0] {
+        return Err(preview_error("only sequential Huffman JPEGs are supported"));
+    }
+
+    // A single-component scan is not interleaved: one block per MCU,
+    // whatever the sampling factors say
+    if count == 1 {
+        frame.components[0].h = 1;
+        frame.components[0].v = 1;
+    }
+    let h_max = frame.components.iter().map(|c| c.h).max().unwrap_or(1);
+    let v_max = frame.components.iter().map(|c| c.v).max().unwrap_or(1);
+    let mcus_x = frame.width.div_ceil(8 * h_max);
+    let mcus_y = frame.height.div_ceil(8 * v_max);
+
+    let mut huffman = Vec::with_capacity(count);
+    for c in &frame.components {
+        let dc = tables.dc[c.dc_table].as_ref();
+        let ac = tables.ac[c.ac_table].as_ref();
+        let (Some(dc), Some(ac)) = (dc, ac) else {
+            return Err(preview_error("scan uses an undefined Huffman table"));
+        };
+        huffman.push((dc, ac));
+    }
+    // One sample per block, per component
+    let mut planes: Vec<Vec<u8>> = frame
+        .components
+        .iter()
+        .map(|c| vec![0; (mcus_x * c.h * mcus_y * c.v) as usize])
+        .collect();
+    let mut predictors = vec![0i32; count];
+    let mut bits = BitReader::new(data);
+
+    for mcu in 0..mcus_x * mcus_y {
+        if tables.restart_interval > 0 && mcu > 0 && mcu % tables.restart_interval == 0 {
+            bits.restart();
+            predictors.fill(0);
+        }
+        let (mcu_x, mcu_y) = (mcu % mcus_x, mcu / mcus_x);
+        for (i, c) in frame.components.iter().enumerate() {
+            let (dc_table, ac_table) = huffman[i];
+            let quant = tables.dc_quant[c.quant] as i32;
+            for block_y in 0..c.v {
+                for block_x in 0..c.h {
+                    let size = bits.decode(dc_table)? as u32;
+                    if size > 11 {
+                        return Err(preview_error("corrupt DC coefficient"));
+                    }
+                    predictors[i] += bits.receive_extend(size);
+                    skip_ac(&mut bits, ac_table)?;
+
+                    // DC = 8x the block mean, before the level shift
+                    let mean = (predictors[i] * quant) as f32 / 8.0 + 128.0;
+                    let x = mcu_x * c.h + block_x;
+                    let y = mcu_y * c.v + block_y;
+                    planes[i][(y * mcus_x * c.h + x) as usize] =
+                        mean.round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+    }
+
+    let (width, height) = (frame.width.div_ceil(8), frame.height.div_ceil(8));
+    let ids: Vec<u8> = frame.components.iter().map(|c| c.id).collect();
+    let is_rgb = tables.adobe_transform == Some(0) || ids == b"RGB";
+    let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let sample = |i: usize| {
+                let c = &frame.components[i];
+                let (sx, sy) = (x * c.h / h_max, y * c.v / v_max);
+                planes[i][(sy * mcus_x * c.h + sx) as usize]
+            };
+            if count == 1 {
+                let gray = sample(0);
+                rgb.extend_from_slice(&[gray, gray, gray]);
+            } else if is_rgb {
+                rgb.extend_from_slice(&[sample(0), sample(1), sample(2)]);
+            } else {
+                rgb.extend_from_slice(&ycbcr_to_rgb(sample(0), sample(1), sample(2)));
+            }
+        }
+    }
+    Ok(TileData::new(rgb, width, height))
+}
+
+/// Huffman-decode a block's AC coefficients just to move past them.
+fn skip_ac(bits: &mut BitReader, table: &Huffman) -> TileResult<()> {
+    let mut k = 1;
+    while k < 64 {
+        let rs = bits.decode(table)?;
+        let (run, size) = ((rs >> 4) as u32, (rs & 0x0F) as u32);
+        if size == 0 {
+            if run != 15 {
+                break; // end of block
+            }
+            k += 16;
+            continue;
+        }
+        k += run;
+        bits.bits(size);
+        k += 1;
+    }
+    Ok(())
+}
+
+/// JFIF YCbCr to RGB.
+fn ycbcr_to_rgb(y: u8, cb: u8, cr: u8) -> [u8; 3] {
+    let (y, cb, cr) = (y as f32, cb as f32 - 128.0, cr as f32 - 128.0);
+    let clamp = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+    [
+        clamp(y + 1.402 * cr),
+        clamp(y - 0.344_136 * cb - 0.714_136 * cr),
+        clamp(y + 1.772 * cb),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+
+    use super::*;
+    use crate::decoder::{decode_tile_bytes, CompressedTileData};
+
+    /// A smooth 8-bit RGB test image of constant hue, so subsampled chroma
+    /// (averaged over more than one 8x8 block) is exact too.
+    fn gradient(width: u32, height: u32) -> Vec<u8> {
+        (0..height)
+            .flat_map(|y| {
+                (0..width).flat_map(move |x| {
+                    let v = (x + 2 * y).min(200) as u8;
+                    [v + 40, v, v + 20]
+                })
+            })
+            .collect()
+    }
+
+    fn encode(
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        color: ColorType,
+        setup: impl FnOnce(&mut Encoder<&mut Vec<u8>>),
+    ) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = Encoder::new(&mut out, 95);
+        setup(&mut encoder);
+        encoder
+            .encode(pixels, width as u16, height as u16, color)
+            .unwrap();
+        out
+    }
+
+    /// Per-channel mean of each 8x8 block of the full decode.
+    fn block_means(bytes: &[u8]) -> TileData {
+        let full = decode_tile_bytes(&CompressedTileData {
+            jpeg_bytes: bytes.to_vec().into(),
+            width: 0,
+            height: 0,
+        })
+        .unwrap();
+        let (width, height) = (full.width.div_ceil(8), full.height.div_ceil(8));
+        let mut means = Vec::new();
+        for by in 0..height {
+            for bx in 0..width {
+                let block = full.crop(bx * 8, by * 8, 8, 8);
+                let n = (block.width * block.height) as f32;
+                for channel in 0..3 {
+                    let sum: f32 = block
+                        .data
+                        .iter()
+                        .skip(channel)
+                        .step_by(3)
+                        .map(|&v| v as f32)
+                        .sum();
+                    means.push((sum / n).round() as u8);
+                }
+            }
+        }
+        TileData::new(means, width, height)
+    }
+
+    fn assert_close(preview: &TileData, expected: &TileData, tolerance: u8) {
+        assert_eq!(
+            (preview.width, preview.height),
+            (expected.width, expected.height)
+        );
+        for (i, (&a, &b)) in preview.data.iter().zip(expected.data.iter()).enumerate() {
+            assert!(a.abs_diff(b) <= tolerance, "sample {i}: {a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn test_dc_preview_matches_block_means() {
+        let (width, height) = (60, 44); // partial edge blocks
+        let pixels = gradient(width, height);
+        for sampling in [SamplingFactor::F_1_1, SamplingFactor::F_2_2] {
+            let jpeg = encode(&pixels, width, height, ColorType::Rgb, |e| {
+                e.set_sampling_factor(sampling)
+            });
+            let preview = decode_dc_preview(&jpeg).unwrap();
+            assert_eq!((preview.width, preview.height), (8, 6));
+            assert_close(&preview, &block_means(&jpeg), 4);
+        }
+    }
+
+    #[test]
+    fn test_dc_preview_grayscale_and_restart_markers() {
+        let (width, height) = (64, 64);
+        let gray: Vec<u8> = (0..width * height).map(|i| (i % 251) as u8).collect();
+        let jpeg = encode(&gray, width, height, ColorType::Luma, |e| {
+            e.set_restart_interval(3)
+        });
+        let preview = decode_dc_preview(&jpeg).unwrap();
+        assert!(preview
+            .data
+            .chunks_exact(3)
+            .all(|p| p[0] == p[1] && p[1] == p[2]));
+        assert_close(&preview, &block_means(&jpeg), 3);
+    }
+
+    #[test]
+    fn test_dc_preview_rejects_unsupported_input() {
+        let pixels = gradient(16, 16);
+        let progressive = encode(&pixels, 16, 16, ColorType::Rgb, |e| e.set_progressive(true));
+        assert!(decode_dc_preview(&progressive).is_err());
+        assert!(decode_dc_preview(b"\x89PNG\r\n\x1a\n").is_err());
+        let jpeg = encode(&pixels, 16, 16, ColorType::Rgb, |_| {});
+        // A missing EOI is tolerated, truncated headers are not
+        assert!(decode_dc_preview(&jpeg[..jpeg.len() - 2]).is_ok_and(|p| p.width == 2));
+        assert!(decode_dc_preview(&jpeg[..20]).is_err());
+    }
+}
@@ -4,30 +4,60 @@
 //! - Concurrent tile cache using moka (TinyLFU eviction)
 //! - Parallel I/O with rayon thread pool
 //! - Viewport-based prefetching with velocity prediction
-//! - Fast JPEG decoding
+//! - Fast tile decoding (JPEG, PNG, WebP sniffed per tile)
 
 mod bulk_preload;
 mod cache;
+mod cancel;
+mod cpu;
 mod decoder;
+mod disk_cache;
 mod error;
 mod format;
+mod imaging;
+mod jpeg_dc;
+mod logging;
+mod memory;
+mod overflow_drain;
 mod pack;
 mod prefetch;
 mod scheduler;
 mod slide_pool;
 mod tile_buffer;
 mod tile_reader;
+mod tile_source;
+mod timing;
 #[cfg(test)]
 pub(crate) mod test_utils;
 
+use std::collections::BTreeMap;
+use std::ffi::CString;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
+use pyo3::exceptions::{PyImportError, PyUserWarning, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::{PyBytes, PyDict};
 
+use cache::{CacheDebug, CompressedTileCache, TileCoord};
+use cancel::CancelToken;
+use cpu::CpuFeatures;
+use decoder::{PixelFormat, TileTransform};
+use imaging::ImageFormat;
+use prefetch::{TileOrder, Viewport};
 use scheduler::TileScheduler;
 use tile_buffer::TileBuffer;
-use tile_reader::FastpathTileReader;
+use tile_reader::{FastpathTileReader, LevelTileIter};
+
+/// `get_tile_array` result: buffer, (height, width, channels), NumPy dtype.
+type TileArray<'py> = (Bound<'py, TileBuffer>, (u32, u32, u32), &'static str);
+
+/// `get_tiles` entry: buffer, width, height.
+type TileBufferEntry<'py> = (Bound<'py, TileBuffer>, u32, u32);
+
+/// One `region_tiles` entry: (buffer, width, height, dst_x, dst_y).
+type RegionTileEntry<'py> = (Bound<'py, TileBuffer>, u32, u32, i64, i64);
 
 /// Python-exposed tile scheduler with two-level caching.
 ///
@@ -57,7 +87,45 @@ use tile_reader::FastpathTileReader;
 /// ```
 #[pyclass]
 pub struct RustTileScheduler {
-    inner: TileScheduler,
+    inner: Arc<TileScheduler>,
+}
+
+impl Drop for RustTileScheduler {
+    fn drop(&mut self) {
+        // Background threads may still hold `inner`; tell them to stop now
+        self.inner.shutdown();
+    }
+}
+
+/// L2 compressed tile cache shared by several schedulers.
+///
+/// Pass the same instance as `shared_l2` to each RustTileScheduler (e.g. one
+/// per viewer tab) so they fill and read one cache instead of each reserving
+/// its own. Tiles are keyed by slide, so schedulers showing the same slide
+/// reuse each other's reads. Settings and stat resets made through any
+/// scheduler (e.g. l2_per_slide_max_fraction) apply to the shared cache.
+///
+/// Usage:
+/// ```python
+/// l2 = SharedL2(size_mb=32768)
+/// left = RustTileScheduler(shared_l2=l2)
+/// right = RustTileScheduler(shared_l2=l2)
+/// ```
+#[pyclass(frozen)]
+pub struct SharedL2 {
+    cache: Arc<CompressedTileCache>,
+}
+
+#[pymethods]
+impl SharedL2 {
+    /// Create a shared L2 cache of `size_mb` megabytes (default: 32768 = 32GB).
+    #[new]
+    #[pyo3(signature = (size_mb=32768))]
+    fn new(size_mb: usize) -> Self {
+        Self {
+            cache: Arc::new(CompressedTileCache::new(size_mb)),
+        }
+    }
 }
 
 #[pymethods]
@@ -65,32 +133,182 @@ impl RustTileScheduler {
     /// Create a new tile scheduler.
     ///
     /// Args:
-    ///     cache_size_mb: Maximum L1 cache size in megabytes (default: 4096 = 4GB).
-    ///         Holds decoded RGB tile data.
-    ///     l2_cache_size_mb: Maximum L2 cache size in megabytes (default: 32768 = 32GB).
-    ///         Holds compressed JPEG bytes; persists across slide switches.
+    ///     cache_size_mb: Maximum L1 cache size in megabytes (default: None =
+    ///         4096 = 4GB). Holds decoded RGB tile data.
+    ///     l2_cache_size_mb: Maximum L2 cache size in megabytes (default: None =
+    ///         32768 = 32GB). Holds compressed JPEG bytes; persists across slide
+    ///         switches. If either size is given (or shared_l2 is), a UserWarning
+    ///         is raised when the two together exceed 75% of system RAM; the
+    ///         defaults alone never warn.
     ///     prefetch_distance: Number of tiles to prefetch ahead (default: 3)
+    ///     l1_lz4: Store L1 tiles LZ4-compressed (default: False). Roughly doubles
+    ///         L1 residency at the cost of a fast decompress on every L1 hit.
+    ///     shared_l2: SharedL2 to use instead of a private L2 cache
+    ///         (l2_cache_size_mb is then ignored).
+    ///     l3_cache_dir: Scratch directory to spill tiles evicted from L2 to
+    ///         (default: None = no L3). Checked on L2 misses before the pack.
+    ///     resolution_bias: Upscaling tolerated before switching to a finer
+    ///         level (default: 1.0 = never upscale). 2.0 serves scale 0.6 from
+    ///         the 2x-downsampled level, loading fewer tiles.
+    ///     cache_events: Record L1 removals for drain_cache_events
+    ///         (default: False; adds a little work per eviction).
+    ///     io_threads: Threads for parallel tile loads (prefetch, get_tiles,
+    ///         warm_region). Caps concurrent disk reads, e.g. on network storage.
+    ///         0 (default) uses the global pool, one thread per core.
+    ///     mmap_packs: Memory-map .pack files instead of one read syscall per
+    ///         tile (default: False).
+    ///     prefetch_lookahead: Seconds of pan motion to prefetch ahead
+    ///         (default: 0 = off). Never less than prefetch_distance tiles,
+    ///         nor more than 8 tiles per axis.
+    ///     l1_entry_overhead: Bytes charged per L1 tile on top of its pixels,
+    ///         for struct and cache bookkeeping (default: None = 256). Keeps
+    ///         cache_size_mb a real memory bound when tiles are small.
+    ///     l1_idle_secs: Evict L1 tiles not read for this many seconds
+    ///         (default: 0 = only on capacity). L2 has no idle eviction.
+    ///     max_l2_fill_ratio: Fraction of L2 capacity past which bulk preload
+    ///         stops filling neighbouring slides (default: 1.0 = no limit).
+    ///         The current slide is always preloaded in full.
+    ///     read_retries: Extra attempts for a tile read failing with a
+    ///         transient I/O error (interrupted, timed out, would block), e.g.
+    ///         on network storage (default: 0). Missing files are not retried.
+    ///     read_retry_delay_ms: Wait before the first retry, doubled before
+    ///         each further one up to 1 second (default: 10).
+    ///     access_counts: Count get_tile hits per tile for access_heatmap
+    ///         (default: False).
+    ///     l3_cache_size_mb: Most spilled tile data l3_cache_dir may hold
+    ///         (default: 16384 = 16GB); the oldest spills are deleted past it.
+    ///
+    /// Warns:
+    ///     UserWarning: If cache_size_mb plus the L2 size exceeds 75% of
+    ///         system RAM, where the caches filling up risks an OOM kill
+    ///
+    /// Raises:
+    ///     ValueError: If resolution_bias is not a positive finite number, or
+    ///         prefetch_lookahead is negative or not finite, or
+    ///         max_l2_fill_ratio is outside (0, 1]
+    ///     RuntimeError: If l3_cache_dir can't be created or the I/O pool
+    ///         can't be started
     #[new]
-    #[pyo3(signature = (cache_size_mb=4096, l2_cache_size_mb=32768, prefetch_distance=3))]
-    fn new(cache_size_mb: usize, l2_cache_size_mb: usize, prefetch_distance: u32) -> Self {
-        Self {
-            inner: TileScheduler::new(cache_size_mb, l2_cache_size_mb, prefetch_distance),
+    #[pyo3(signature = (cache_size_mb=None, l2_cache_size_mb=None, prefetch_distance=3, l1_lz4=false, shared_l2=None, l3_cache_dir=None, resolution_bias=1.0, cache_events=false, io_threads=0, mmap_packs=false, prefetch_lookahead=0.0, l1_entry_overhead=None, l1_idle_secs=0, max_l2_fill_ratio=1.0, read_retries=0, read_retry_delay_ms=10, access_counts=false, l3_cache_size_mb=16384))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        py: Python<'_>,
+        cache_size_mb: Option<usize>,
+        l2_cache_size_mb: Option<usize>,
+        prefetch_distance: u32,
+        l1_lz4: bool,
+        shared_l2: Option<&SharedL2>,
+        l3_cache_dir: Option<&str>,
+        resolution_bias: f64,
+        cache_events: bool,
+        io_threads: usize,
+        mmap_packs: bool,
+        prefetch_lookahead: f64,
+        l1_entry_overhead: Option<u64>,
+        l1_idle_secs: u64,
+        max_l2_fill_ratio: f64,
+        read_retries: u32,
+        read_retry_delay_ms: u64,
+        access_counts: bool,
+        l3_cache_size_mb: u64,
+    ) -> PyResult<Self> {
+        if !(resolution_bias.is_finite() && resolution_bias > 0.0) {
+            return Err(PyValueError::new_err(format!(
+                "resolution_bias must be a positive finite number, got {resolution_bias}"
+            )));
+        }
+        if !(prefetch_lookahead.is_finite() && prefetch_lookahead >= 0.0) {
+            return Err(PyValueError::new_err(format!(
+                "prefetch_lookahead must be a non-negative number, got {prefetch_lookahead}"
+            )));
+        }
+        if !(max_l2_fill_ratio > 0.0 && max_l2_fill_ratio <= 1.0) {
+            return Err(PyValueError::new_err(format!(
+                "max_l2_fill_ratio must be in (0, 1], got {max_l2_fill_ratio}"
+            )));
+        }
+        // Only sizes the caller chose are checked against RAM
+        let explicit =
+            cache_size_mb.is_some() || l2_cache_size_mb.is_some() || shared_l2.is_some();
+        let cache_size_mb = cache_size_mb.unwrap_or(memory::DEFAULT_L1_CACHE_MB);
+        let l2_cache_size_mb = l2_cache_size_mb.unwrap_or(memory::DEFAULT_L2_CACHE_MB);
+        let l2_mb = match shared_l2 {
+            Some(shared) => shared.cache.max_bytes() / (1024 * 1024),
+            None => l2_cache_size_mb as u64,
+        };
+        if let Some(msg) = memory::total_memory_bytes()
+            .filter(|_| explicit)
+            .and_then(|total| memory::cache_budget_warning(cache_size_mb as u64, l2_mb, total))
+        {
+            let msg = CString::new(msg).expect("warning text has no NUL");
+            PyErr::warn(py, &py.get_type::<PyUserWarning>(), &msg, 1)?;
+        }
+        let mut inner = match shared_l2 {
+            Some(shared) => {
+                TileScheduler::with_l2(cache_size_mb, Arc::clone(&shared.cache), prefetch_distance)
+            }
+            None => TileScheduler::new(cache_size_mb, l2_cache_size_mb, prefetch_distance),
+        }
+        .with_resolution_bias(resolution_bias)
+        .with_prefetch_lookahead(prefetch_lookahead)
+        .with_l1_idle_secs(l1_idle_secs)
+        .with_max_l2_fill_ratio(max_l2_fill_ratio)
+        .with_read_retries(read_retries, Duration::from_millis(read_retry_delay_ms))
+        .with_io_threads(io_threads)?;
+        if let Some(dir) = l3_cache_dir {
+            inner = inner.with_l3_cache(Path::new(dir), l3_cache_size_mb)?;
+        }
+        if cache_events {
+            inner = inner.with_cache_events();
+        }
+        if mmap_packs {
+            inner = inner.with_mmap_packs();
+        }
+        if access_counts {
+            inner = inner.with_access_counts();
+        }
+        if let Some(bytes) = l1_entry_overhead {
+            inner = inner.with_l1_entry_overhead(bytes);
         }
+        inner.set_l1_lz4(l1_lz4);
+        Ok(Self {
+            inner: Arc::new(inner),
+        })
     }
 
     /// Load a .fastpath directory.
     ///
     /// Args:
     ///     path: Path to the .fastpath directory
+    ///     reconcile_grid: Read each ``tiles/level_N.idx`` header and extend
+    ///         any level grid that metadata.json under-reports (logs a warning)
     ///
     /// Returns:
-    ///     True if loaded successfully
+    ///     The slide's handle (a non-zero int), usable with get_slide_tile
     ///
     /// Raises:
     ///     RuntimeError: If the path doesn't exist or metadata is invalid
-    fn load(&self, path: &str) -> PyResult<bool> {
-        self.inner.load(path)?;
-        Ok(true)
+    #[pyo3(signature = (path, reconcile_grid=false))]
+    fn load(&self, path: &str, reconcile_grid: bool) -> PyResult<u64> {
+        Ok(self.inner.load_with(path, reconcile_grid)?)
+    }
+
+    /// Load a .fastpath directory's tiles with metadata given as a string.
+    ///
+    /// metadata.json is not read, so the metadata can come from a database,
+    /// an object store or a test fixture.
+    ///
+    /// Args:
+    ///     tiles_path: Path to the .fastpath directory holding the tile packs
+    ///     metadata_json: Metadata in the metadata.json schema
+    ///
+    /// Returns:
+    ///     The slide's handle, as returned by load()
+    ///
+    /// Raises:
+    ///     RuntimeError: If the path doesn't exist or metadata is invalid
+    fn load_with_metadata(&self, tiles_path: &str, metadata_json: &str) -> PyResult<u64> {
+        Ok(self.inner.load_with_metadata(tiles_path, metadata_json)?)
     }
 
     /// Close the current slide and clear the cache.
@@ -98,6 +316,78 @@ impl RustTileScheduler {
         self.inner.close();
     }
 
+    /// Drop every tile from the compressed (L2) cache.
+    ///
+    /// For responding to low-memory warnings: close() keeps L2, and this
+    /// releases it without closing the slide. A SharedL2 is cleared for every
+    /// scheduler using it.
+    fn clear_l2(&self, py: Python<'_>) {
+        py.allow_threads(|| self.inner.clear_l2());
+    }
+
+    /// Drop one slide's tiles from the compressed (L2) cache.
+    ///
+    /// Args:
+    ///     path: Path to the .fastpath directory, as passed to load()
+    ///
+    /// Returns:
+    ///     Bytes freed
+    ///
+    /// Raises:
+    ///     RuntimeError: If the path doesn't exist
+    fn clear_l2_for_slide(&self, py: Python<'_>, path: &str) -> PyResult<u64> {
+        Ok(py.allow_threads(|| self.inner.clear_l2_for_slide(path))?)
+    }
+
+    /// Open another slide alongside the loaded one, e.g. for a side-by-side
+    /// comparison view, sharing this scheduler's cache budgets.
+    ///
+    /// Viewport prefetch stays with the slide passed to load().
+    ///
+    /// Args:
+    ///     path: Path to the .fastpath directory
+    ///
+    /// Returns:
+    ///     Handle for get_slide_tile and close_slide
+    ///
+    /// Raises:
+    ///     RuntimeError: If the path doesn't exist or metadata is invalid
+    fn open_slide(&self, path: &str) -> PyResult<u64> {
+        Ok(self.inner.open_slide(path)?)
+    }
+
+    /// Close a slide opened with open_slide.
+    ///
+    /// Returns:
+    ///     False if the handle wasn't open
+    fn close_slide(&self, handle: u64) -> bool {
+        self.inner.close_slide(handle)
+    }
+
+    /// Get a tile of the loaded slide or an opened one as raw RGB bytes.
+    ///
+    /// Args:
+    ///     handle: Handle from load() or open_slide()
+    ///     level: Pyramid level (0 = highest resolution)
+    ///     col: Column index
+    ///     row: Row index
+    ///
+    /// Returns:
+    ///     Tuple of (bytes, width, height) or None if the tile doesn't exist
+    ///     or the handle isn't open
+    fn get_slide_tile<'py>(
+        &self,
+        py: Python<'py>,
+        handle: u64,
+        level: u32,
+        col: u32,
+        row: u32,
+    ) -> Option<(Bound<'py, PyBytes>, u32, u32)> {
+        let tile = py.allow_threads(|| self.inner.get_slide_tile(handle, level, col, row))?;
+        let tile = tile.to_format(PixelFormat::Rgb);
+        Some((PyBytes::new(py, &tile.data), tile.width, tile.height))
+    }
+
     /// Get a tile as raw RGB bytes.
     ///
     /// Args:
@@ -106,15 +396,133 @@ impl RustTileScheduler {
     ///     row: Row index
     ///
     /// Returns:
-    ///     Tuple of (bytes, width, height) or None if tile doesn't exist
+    ///     Tuple of (bytes, width, height) or None if tile doesn't exist.
+    ///     With profiling enabled (see ``set_profiling``), a fourth element
+    ///     ``(disk_us, l2_us, decode_us)`` gives the time spent on each step.
     fn get_tile<'py>(
         &self,
         py: Python<'py>,
         level: u32,
         col: u32,
         row: u32,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let Some((tile, timing)) = self.inner.get_tile_profiled(level, col, row) else {
+            return Ok(None);
+        };
+        let tile = tile.to_format(PixelFormat::Rgb);
+        let data = PyBytes::new(py, &tile.data);
+        let result = match timing {
+            None => (data, tile.width, tile.height).into_pyobject(py)?,
+            Some(t) => (data, tile.width, tile.height, (t.disk_us, t.l2_us, t.decode_us))
+                .into_pyobject(py)?,
+        };
+        Ok(Some(result.into_any()))
+    }
+
+    /// Like ``get_tile``, but raise for coordinates outside the slide.
+    ///
+    /// For catching tiling-math bugs: ``get_tile`` returns None both for a
+    /// missing tile and for a coordinate no level has.
+    ///
+    /// Returns:
+    ///     Tuple of (bytes, width, height), or None if the tile is in range
+    ///     but has no data (or no slide is loaded)
+    ///
+    /// Raises:
+    ///     IndexError: If the level doesn't exist or col/row are outside its grid
+    fn try_get_tile<'py>(
+        &self,
+        py: Python<'py>,
+        level: u32,
+        col: u32,
+        row: u32,
+    ) -> PyResult<Option<(Bound<'py, PyBytes>, u32, u32)>> {
+        let tile = self.inner.try_get_tile(level, col, row)?;
+        Ok(tile.map(|tile| tile.to_format(PixelFormat::Rgb)).map(|tile| {
+            (PyBytes::new(py, &tile.data), tile.width, tile.height)
+        }))
+    }
+
+    /// Get a tile, or an instant low-resolution placeholder for it.
+    ///
+    /// The tile itself if in L1, else the matching part of the nearest
+    /// coarser cached level, else a 1/8-scale decode of the tile's JPEG DC
+    /// coefficients (no IDCT, so fast enough for the UI thread). Placeholders
+    /// are not cached; returning one queues the full decode in the
+    /// background, after which this returns the real tile.
+    ///
+    /// Returns:
+    ///     Tuple of (bytes, width, height, scale) or None if the tile is
+    ///     missing or can't be previewed (e.g. PNG with no coarser tile
+    ///     cached). scale is 1 for the real tile; otherwise draw the
+    ///     placeholder scaled up by this factor.
+    fn get_tile_preview<'py>(
+        &self,
+        py: Python<'py>,
+        level: u32,
+        col: u32,
+        row: u32,
+    ) -> Option<(Bound<'py, PyBytes>, u32, u32, u32)> {
+        let (tile, scale) = self.inner.get_tile_preview(level, col, row)?;
+        let tile = tile.to_format(PixelFormat::Rgb);
+        Some((PyBytes::new(py, &tile.data), tile.width, tile.height, scale))
+    }
+
+    /// Enable or disable per-tile decode timing in ``get_tile`` results.
+    ///
+    /// Off by default; when off, no timing is measured.
+    fn set_profiling(&self, enabled: bool) {
+        self.inner.set_profiling(enabled);
+    }
+
+    /// Latency percentiles of timed tile loads from the pack.
+    ///
+    /// Loads are timed when FASTPATH_TILE_TIMING is set, or by ``get_tile``
+    /// with profiling on (see ``set_profiling``); L1/L2 hits aren't counted.
+    ///
+    /// Returns:
+    ///     Dict with key samples, plus disk_us, l2_insert_us and decode_us,
+    ///     each a dict of p50, p95, p99 and max in microseconds
+    fn timing_stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let stats = self.inner.timing_stats();
+        let dict = PyDict::new(py);
+        dict.set_item("samples", stats.samples)?;
+        for (key, p) in [
+            ("disk_us", stats.disk),
+            ("l2_insert_us", stats.l2_insert),
+            ("decode_us", stats.decode),
+        ] {
+            let step = PyDict::new(py);
+            step.set_item("p50", p.p50)?;
+            step.set_item("p95", p.p95)?;
+            step.set_item("p99", p.p99)?;
+            step.set_item("max", p.max)?;
+            dict.set_item(key, step)?;
+        }
+        Ok(dict)
+    }
+
+    /// Clear the latency histograms behind ``timing_stats``.
+    fn reset_timing_stats(&self) {
+        self.inner.reset_timing_stats();
+    }
+
+    /// Get a tile as raw RGB bytes without caching it in L1 ("scan mode").
+    ///
+    /// For one-off passes over a slide (export, analysis) so the interactive
+    /// L1 working set isn't evicted. Tiles already in L1 are still served.
+    ///
+    /// Returns:
+    ///     Tuple of (bytes, width, height) or None if tile doesn't exist
+    fn get_tile_no_promote<'py>(
+        &self,
+        py: Python<'py>,
+        level: u32,
+        col: u32,
+        row: u32,
     ) -> Option<(Bound<'py, PyBytes>, u32, u32)> {
-        self.inner.get_tile(level, col, row).map(|tile| {
+        self.inner.get_tile_no_promote(level, col, row).map(|tile| {
+            let tile = tile.to_format(PixelFormat::Rgb);
             (PyBytes::new(py, &tile.data), tile.width, tile.height)
         })
     }
@@ -124,24 +532,149 @@ impl RustTileScheduler {
     /// This avoids copying decoded RGB bytes into a Python `bytes` object.
     /// QImage (PySide6) can wrap the returned `TileBuffer` directly.
     ///
+    /// Args:
+    ///     pixel_format: "rgb" (Format_RGB888), "bgra" (Format_ARGB32) or
+    ///         "rgba" (Format_RGBA8888); defaults to the format set by
+    ///         set_default_pixel_format
+    ///
     /// Returns:
     ///     Tuple of (TileBuffer, width, height) or None if tile doesn't exist
+    ///
+    /// Raises:
+    ///     ValueError: If the pixel format name is unknown
+    #[pyo3(signature = (level, col, row, pixel_format=None))]
     fn get_tile_buffer<'py>(
         &self,
         py: Python<'py>,
         level: u32,
         col: u32,
         row: u32,
+        pixel_format: Option<&str>,
     ) -> PyResult<Option<(Bound<'py, TileBuffer>, u32, u32)>> {
-        let Some(tile) = self.inner.get_tile(level, col, row) else {
+        let format = pixel_format.map(parse_pixel_format).transpose()?;
+        let Some(tile) = self.inner.get_tile_pixels(level, col, row, format) else {
             return Ok(None);
         };
         let width = tile.width;
         let height = tile.height;
-        let buf = Py::new(py, TileBuffer::new(tile.data))?;
+        let buf = Py::new(py, TileBuffer::with_format(tile.data, tile.sample_format))?;
         Ok(Some((buf.into_bound(py), width, height)))
     }
 
+    /// Get many tiles as zero-copy buffers in one call.
+    ///
+    /// Misses are decoded in parallel with the GIL released, saving a Python
+    /// round-trip per tile when filling a fresh viewport.
+    ///
+    /// Args:
+    ///     coords: List of (level, col, row)
+    ///     pixel_format: "rgb", "bgra" or "rgba"; defaults to the format set by
+    ///         set_default_pixel_format
+    ///     cancel: Optional CancelToken; once cancelled, tiles not yet loaded
+    ///         come back as None
+    ///
+    /// Returns:
+    ///     List aligned with ``coords`` of (TileBuffer, width, height), or None
+    ///     where a tile doesn't exist (or was skipped after cancellation)
+    ///
+    /// Raises:
+    ///     ValueError: If the pixel format name is unknown
+    #[pyo3(signature = (coords, pixel_format=None, cancel=None))]
+    fn get_tiles<'py>(
+        &self,
+        py: Python<'py>,
+        coords: Vec<(u32, u32, u32)>,
+        pixel_format: Option<&str>,
+        cancel: Option<Py<CancelToken>>,
+    ) -> PyResult<Vec<Option<TileBufferEntry<'py>>>> {
+        let format = pixel_format.map(parse_pixel_format).transpose()?;
+        let cancel = cancel.as_ref().map(|token| token.get().flag());
+        let tiles = py.allow_threads(|| self.inner.get_tiles(&coords, format, cancel));
+        tiles
+            .into_iter()
+            .map(|tile| {
+                tile.map(|tile| {
+                    let buf =
+                        Py::new(py, TileBuffer::with_format(tile.data, tile.sample_format))?;
+                    Ok((buf.into_bound(py), tile.width, tile.height))
+                })
+                .transpose()
+            })
+            .collect()
+    }
+
+    /// Get a tile as a zero-copy buffer with an explicit array layout.
+    ///
+    /// The buffer is C-contiguous with no row padding, so
+    /// `np.frombuffer(buf, dtype=dtype).reshape(shape)` is always correct.
+    ///
+    /// Args:
+    ///     pixel_format: "rgb", "bgra" or "rgba"; defaults to the format set by
+    ///         set_default_pixel_format
+    ///
+    /// Returns:
+    ///     Tuple of (TileBuffer, (height, width, channels), dtype) or None if the
+    ///     tile doesn't exist. dtype is a NumPy dtype name ("uint8", or
+    ///     "uint16" for 16-bit grayscale tiles with preserve_grayscale).
+    ///
+    /// Raises:
+    ///     ValueError: If the pixel format name is unknown
+    #[pyo3(signature = (level, col, row, pixel_format=None))]
+    fn get_tile_array<'py>(
+        &self,
+        py: Python<'py>,
+        level: u32,
+        col: u32,
+        row: u32,
+        pixel_format: Option<&str>,
+    ) -> PyResult<Option<TileArray<'py>>> {
+        let format = pixel_format.map(parse_pixel_format).transpose()?;
+        let Some(tile) = self.inner.get_tile_pixels(level, col, row, format) else {
+            return Ok(None);
+        };
+        let shape = tile.array_shape();
+        let dtype = tile.dtype();
+        let buf = Py::new(py, TileBuffer::with_format(tile.data, tile.sample_format))?;
+        Ok(Some((buf.into_bound(py), shape, dtype)))
+    }
+
+    /// Get a tile as a NumPy array shaped (height, width, channels).
+    ///
+    /// The array is a read-only view over the decoded tile (no copy), so it
+    /// replaces ``np.frombuffer(...).reshape(h, w, 3)`` on ``get_tile``
+    /// bytes. Channels are 3 for RGB, 4 for BGRA/RGBA and 1 for grayscale; dtype
+    /// is uint8 (uint16 for 16-bit grayscale).
+    ///
+    /// Args:
+    ///     pixel_format: "rgb", "bgra" or "rgba"; defaults to the format set by
+    ///         set_default_pixel_format
+    ///
+    /// Returns:
+    ///     numpy.ndarray, or None if the tile doesn't exist
+    ///
+    /// Raises:
+    ///     ValueError: If the pixel format name is unknown
+    ///     ImportError: If NumPy isn't installed
+    #[pyo3(signature = (level, col, row, pixel_format=None))]
+    fn get_tile_numpy<'py>(
+        &self,
+        py: Python<'py>,
+        level: u32,
+        col: u32,
+        row: u32,
+        pixel_format: Option<&str>,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let format = pixel_format.map(parse_pixel_format).transpose()?;
+        let Some(tile) = self.inner.get_tile_pixels(level, col, row, format) else {
+            return Ok(None);
+        };
+        let (h, w, channels) = tile.array_shape();
+        let shape = [h as usize, w as usize, channels as usize];
+        let buf = Py::new(py, TileBuffer::with_shape(tile.data, tile.sample_format, &shape))?;
+        let array = py.import("numpy")?.call_method1("asarray", (buf,))?;
+        Ok(Some(array))
+    }
+
     /// Get a tile as raw JPEG bytes (compressed).
     ///
     /// This is useful for letting Qt decode tiles (`QImage.fromData(...)`) and
@@ -161,6 +694,116 @@ impl RustTileScheduler {
             .map(|jpeg| PyBytes::new(py, jpeg.as_ref()))
     }
 
+    /// Assemble a region (level coordinates) to raw RGB bytes via the caches.
+    ///
+    /// Tiles are served from L1/L2 when cached and read from the pack
+    /// otherwise (and cached). Areas without tiles are white.
+    ///
+    /// Args:
+    ///     level: Pyramid level number
+    ///     x, y: Top-left in level pixels (may be negative)
+    ///     w, h: Region size in pixels (must be positive)
+    ///
+    /// Returns:
+    ///     bytes of length w*h*3 in row-major RGB order
+    ///
+    /// Raises:
+    ///     RuntimeError: If no slide is loaded or the region is invalid
+    fn get_region<'py>(
+        &self,
+        py: Python<'py>,
+        level: u32,
+        x: i64,
+        y: i64,
+        w: u32,
+        h: u32,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let data = py.allow_threads(|| self.inner.get_region(level, x, y, w, h))?;
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// The decoded tiles covering a region (level coordinates), unstitched.
+    ///
+    /// For tiled GPU renderers: upload each tile to its own texture at its
+    /// offset instead of uploading one assembled region. Tiles come from the
+    /// same caches as get_region; missing tiles are omitted.
+    ///
+    /// Args:
+    ///     level: Pyramid level number
+    ///     x, y: Top-left in level pixels (may be negative)
+    ///     w, h: Region size in pixels (must be positive)
+    ///
+    /// Returns:
+    ///     List of (TileBuffer, width, height, dst_x, dst_y) in row-major tile
+    ///     order. dst_x/dst_y place the tile's top-left relative to (x, y) and
+    ///     are negative for tiles that start before the region.
+    ///
+    /// Raises:
+    ///     RuntimeError: If no slide is loaded or the region is invalid
+    fn region_tiles<'py>(
+        &self,
+        py: Python<'py>,
+        level: u32,
+        x: i64,
+        y: i64,
+        w: u32,
+        h: u32,
+    ) -> PyResult<Vec<RegionTileEntry<'py>>> {
+        let tiles = py.allow_threads(|| self.inner.get_region_tiles(level, x, y, w, h))?;
+        tiles
+            .into_iter()
+            .map(|tile| {
+                let buf = Bound::new(py, TileBuffer::new(tile.data))?;
+                Ok((buf, tile.width, tile.height, tile.dst_x, tile.dst_y))
+            })
+            .collect()
+    }
+
+    /// Decode the tiles covering a region into the cache ahead of reading it.
+    ///
+    /// Uses the same level coordinates as get_region. Already-cached tiles
+    /// are skipped; the rest are loaded in parallel.
+    ///
+    /// Args:
+    ///     level: Pyramid level
+    ///     x, y: Region origin in level coordinates
+    ///     w, h: Region size in pixels
+    ///
+    /// Returns:
+    ///     Number of tiles loaded by this call
+    ///
+    /// Raises:
+    ///     RuntimeError: If no slide is loaded, the level doesn't exist or the region is invalid
+    fn warm_region(
+        &self,
+        py: Python<'_>,
+        level: u32,
+        x: i64,
+        y: i64,
+        w: u32,
+        h: u32,
+    ) -> PyResult<usize> {
+        Ok(py.allow_threads(|| self.inner.warm_region(level, x, y, w, h))?)
+    }
+
+    /// Warm the visible tiles of saved bookmark views into L2 in the background.
+    ///
+    /// Tiles are read into L2 only (the current L1 working set is untouched),
+    /// so jumping to a bookmark skips the disk read. A new call replaces any
+    /// warm still in progress; loading or closing a slide cancels it.
+    ///
+    /// Args:
+    ///     views: List of (x, y, width, height, scale) tuples, as in update_viewport
+    ///
+    /// Returns:
+    ///     Number of tiles queued (0 if no slide is loaded)
+    ///
+    /// Raises:
+    ///     RuntimeError: If the background thread can't be started
+    fn warm_bookmarks(&self, views: Vec<(f64, f64, f64, f64, f64)>) -> PyResult<usize> {
+        Ok(self.inner.warm_bookmarks(&views)?)
+    }
+
     /// Update the viewport and trigger prefetching.
     ///
     /// Call this whenever the viewport changes to enable intelligent prefetching
@@ -174,7 +817,12 @@ impl RustTileScheduler {
     ///     scale: Current zoom scale (1.0 = full resolution)
     ///     velocity_x: Horizontal pan velocity (pixels/second)
     ///     velocity_y: Vertical pan velocity (pixels/second)
-    #[pyo3(signature = (x, y, width, height, scale, velocity_x=0.0, velocity_y=0.0))]
+    ///     accel_x: Horizontal pan acceleration (pixels/second^2); speeding up
+    ///         extends the prefetch further ahead
+    ///     accel_y: Vertical pan acceleration (pixels/second^2)
+    #[pyo3(signature = (
+        x, y, width, height, scale, velocity_x=0.0, velocity_y=0.0, accel_x=0.0, accel_y=0.0
+    ))]
     #[allow(clippy::too_many_arguments)]
     fn update_viewport(
         &self,
@@ -185,9 +833,141 @@ impl RustTileScheduler {
         scale: f64,
         velocity_x: f64,
         velocity_y: f64,
+        accel_x: f64,
+        accel_y: f64,
     ) {
-        self.inner
-            .update_viewport(x, y, width, height, scale, velocity_x, velocity_y);
+        let viewport = Viewport::new(x, y, width, height, scale, velocity_x, velocity_y)
+            .with_acceleration(accel_x, accel_y);
+        self.inner.update_viewport_with(viewport);
+    }
+
+    /// Encode exactly what the viewport shows as a PNG or JPEG image.
+    ///
+    /// The level is chosen for `scale`, the visible region is assembled and
+    /// downscaled to display size, and the result is encoded — all in Rust
+    /// with the GIL released.
+    ///
+    /// Args:
+    ///     x, y, width, height: Viewport in slide coordinates
+    ///     scale: Current zoom scale (output is width*scale x height*scale)
+    ///     format: "png", "jpeg" or "webp" (lossless)
+    ///     quality: JPEG quality 1-100 (ignored for PNG)
+    ///
+    /// Returns:
+    ///     Encoded image bytes
+    ///
+    /// Raises:
+    ///     ValueError: If the format name is unknown
+    ///     RuntimeError: If no slide is loaded or encoding fails
+    #[pyo3(signature = (x, y, width, height, scale, format="png", quality=90))]
+    #[allow(clippy::too_many_arguments)]
+    fn snapshot_viewport<'py>(
+        &self,
+        py: Python<'py>,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        scale: f64,
+        format: &str,
+        quality: u8,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let format = ImageFormat::parse(format)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown image format: {format}")))?;
+        let data = py.allow_threads(|| {
+            self.inner
+                .snapshot_viewport(x, y, width, height, scale, format, quality)
+        })?;
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Render the whole slide fitted within max_dim pixels and encode it.
+    ///
+    /// Thumbnails are cached per slide (across slide switches), so repeated
+    /// requests from worklist views are served without re-rendering.
+    ///
+    /// Args:
+    ///     max_dim: Longest side of the thumbnail in pixels
+    ///     format: "png", "jpeg" or "webp" (lossless)
+    ///     quality: JPEG quality 1-100 (ignored for PNG)
+    ///
+    /// Returns:
+    ///     Encoded image bytes
+    ///
+    /// Raises:
+    ///     ValueError: If format is unknown
+    ///     RuntimeError: If no slide is loaded, max_dim is 0 or encoding fails
+    #[pyo3(signature = (max_dim=256, format="jpeg", quality=85))]
+    fn get_thumbnail<'py>(
+        &self,
+        py: Python<'py>,
+        max_dim: u32,
+        format: &str,
+        quality: u8,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let format = ImageFormat::parse(format)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown image format: {format}")))?;
+        let data = py.allow_threads(|| self.inner.get_thumbnail(max_dim, format, quality))?;
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Assemble the whole slide as raw RGB for overviews such as a minimap.
+    ///
+    /// Uses the finest pyramid level that fits within max_dim (the coarsest
+    /// level, scaled down, if none does). Missing tiles are filled white.
+    /// Results are cached per slide and survive close() and slide switches.
+    ///
+    /// Args:
+    ///     max_dim: Longest side of the result in pixels
+    ///
+    /// Returns:
+    ///     Tuple of (bytes, width, height) with RGB pixel data
+    ///
+    /// Raises:
+    ///     RuntimeError: If no slide is loaded or max_dim is 0
+    #[pyo3(signature = (max_dim=256))]
+    fn get_thumbnail_rgb<'py>(
+        &self,
+        py: Python<'py>,
+        max_dim: u32,
+    ) -> PyResult<(Bound<'py, PyBytes>, u32, u32)> {
+        let (data, width, height) = py.allow_threads(|| self.inner.get_thumbnail_rgb(max_dim))?;
+        Ok((PyBytes::new(py, &data), width, height))
+    }
+
+    /// Decode an associated image (label, macro, overview) listed in metadata.
+    ///
+    /// Args:
+    ///     name: Key in the slide's ``associated_images`` map, e.g. "label"
+    ///
+    /// Returns:
+    ///     Tuple of (bytes, width, height) with RGB pixel data, or None if the
+    ///     slide doesn't have that image
+    ///
+    /// Raises:
+    ///     RuntimeError: If no slide is loaded or the image can't be read
+    fn get_associated_image<'py>(
+        &self,
+        py: Python<'py>,
+        name: &str,
+    ) -> PyResult<Option<(Bound<'py, PyBytes>, u32, u32)>> {
+        let image = py.allow_threads(|| self.inner.get_associated_image(name))?;
+        Ok(image.map(|image| (PyBytes::new(py, &image.data), image.width, image.height)))
+    }
+
+    /// Get the slide's embedded ICC color profile.
+    ///
+    /// Pass it to Qt (QColorSpace.fromIccProfile) to color-manage the
+    /// display. Read from the tiles, so no per-slide metadata is needed.
+    ///
+    /// Returns:
+    ///     Raw ICC profile bytes, or None if the tiles carry no profile
+    ///
+    /// Raises:
+    ///     RuntimeError: If no slide is loaded or the tile header is invalid
+    fn get_icc_profile<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyBytes>>> {
+        let profile = py.allow_threads(|| self.inner.get_icc_profile())?;
+        Ok(profile.map(|profile| PyBytes::new(py, &profile)))
     }
 
     /// Pre-warm cache with low-resolution level tiles.
@@ -199,11 +979,34 @@ impl RustTileScheduler {
         self.inner.prefetch_low_res_levels();
     }
 
+    /// Start prefetch_low_res_levels() on a background thread and return at once.
+    ///
+    /// Use this right after load() to avoid stalling the UI thread. A load()
+    /// or close() while it runs stops it without touching the new slide.
+    ///
+    /// Raises:
+    ///     RuntimeError: If the background thread can't be started
+    fn start_prefetch_low_res_levels(&self) -> PyResult<()> {
+        Ok(self.inner.start_prefetch_low_res_levels()?)
+    }
+
+    /// Whether a background low-res prefetch is still running.
+    fn is_prefetching_low_res(&self) -> bool {
+        self.inner.is_prefetching_low_res()
+    }
+
+    /// Block until the background low-res prefetch (if any) has finished.
+    fn wait_low_res_prefetch(&self, py: Python<'_>) {
+        py.allow_threads(|| self.inner.wait_low_res_prefetch());
+    }
+
     /// Get cache statistics for both L1 and L2 caches.
     ///
     /// Returns:
     ///     Dict with L1 keys: hits, misses, hit_ratio, size_bytes, num_tiles
     ///     and L2 keys: l2_hits, l2_misses, l2_hit_ratio, l2_size_bytes, l2_num_tiles
+    ///     and thumbnail keys: thumbnail_hits, thumbnail_misses, thumbnail_size_bytes
+    ///     and decode_failures (tiles whose bytes failed to decode)
     fn cache_stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
         let stats = self.inner.cache_stats();
         let dict = PyDict::new(py);
@@ -219,9 +1022,78 @@ impl RustTileScheduler {
         dict.set_item("l2_hit_ratio", stats.l2.hit_ratio)?;
         dict.set_item("l2_size_bytes", stats.l2.size_bytes)?;
         dict.set_item("l2_num_tiles", stats.l2.num_tiles)?;
+        // Thumbnail cache keys
+        dict.set_item("thumbnail_hits", stats.thumbnail.hits)?;
+        dict.set_item("thumbnail_misses", stats.thumbnail.misses)?;
+        dict.set_item("thumbnail_size_bytes", stats.thumbnail.size_bytes)?;
+        // L3 keys (zero when no l3_cache_dir is configured)
+        let l3 = stats.l3.unwrap_or_default();
+        dict.set_item("l3_hits", l3.hits)?;
+        dict.set_item("l3_misses", l3.misses)?;
+        dict.set_item("l3_size_bytes", l3.size_bytes)?;
+        dict.set_item("decode_failures", stats.decode_failures)?;
+        Ok(dict)
+    }
+
+    /// L1 tiles removed since the last call, oldest first.
+    ///
+    /// Only recorded when the scheduler was created with cache_events=True.
+    /// At most 4096 events are buffered between calls; later ones are dropped.
+    ///
+    /// Returns:
+    ///     List of (level, col, row, reason) tuples; reason is "size"
+    ///     (evicted for capacity), "expired" or "explicit" (cleared/invalidated)
+    fn drain_cache_events(&self) -> Vec<(u32, u32, u32, &'static str)> {
+        self.inner
+            .drain_cache_events()
+            .into_iter()
+            .map(|(c, reason)| (c.level, c.col, c.row, reason))
+            .collect()
+    }
+
+    /// How often each tile was requested via get_tile since the last load.
+    ///
+    /// Only recorded when the scheduler was created with access_counts=True.
+    /// Prefetch and batch reads (get_tiles, regions) are not counted.
+    ///
+    /// Returns:
+    ///     List of (level, col, row, count) tuples sorted by level, row, col
+    fn access_heatmap(&self) -> Vec<(u32, u32, u32, u64)> {
+        self.inner.access_heatmap()
+    }
+
+    /// Snapshot cache internals for diagnosing eviction patterns.
+    ///
+    /// Walks every resident entry, so don't call this per frame.
+    ///
+    /// Returns:
+    ///     Dict with "l1" and "l2" dicts, each holding entry_count,
+    ///     weighted_size, max_bytes, levels ({level: resident tiles}) and
+    ///     slides ({slide_id: {entries, bytes, oldest, newest}}, L2 only;
+    ///     oldest/newest are (level, col, row) by insertion order)
+    fn cache_debug<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let debug = self.inner.cache_debug();
+        let dict = PyDict::new(py);
+        dict.set_item("l1", cache_debug_dict(py, &debug.l1)?)?;
+        dict.set_item("l2", cache_debug_dict(py, &debug.l2)?)?;
         Ok(dict)
     }
 
+    /// L1 occupancy per pyramid level.
+    ///
+    /// Walks every resident entry, so don't call this per frame.
+    ///
+    /// Returns:
+    ///     Dict of {level: (num_tiles, size_bytes)}; size_bytes counts stored
+    ///     (LZ4-compressed, if enabled) bytes, as in cache_stats
+    fn cache_stats_by_level(&self) -> BTreeMap<u32, (usize, usize)> {
+        self.inner
+            .cache_stats_by_level()
+            .into_iter()
+            .map(|(level, usage)| (level, (usage.num_tiles, usage.size_bytes)))
+            .collect()
+    }
+
     /// Reset cache hit/miss counters to zero.
     fn reset_cache_stats(&self) {
         self.inner.reset_cache_stats();
@@ -257,6 +1129,28 @@ impl RustTileScheduler {
         self.inner.dimensions().1
     }
 
+    /// Pyramid level whose resolution is closest to a physical pixel size.
+    ///
+    /// Each level's resolution is the slide's mpp times its downsample;
+    /// closeness is by ratio, with ties going to the finer level.
+    ///
+    /// Args:
+    ///     mpp: Target microns per pixel
+    ///
+    /// Returns:
+    ///     Level index, or None if no slide is loaded
+    ///
+    /// Raises:
+    ///     ValueError: If mpp is not a positive finite number
+    fn best_level_for_mpp(&self, mpp: f64) -> PyResult<Option<u32>> {
+        if !(mpp.is_finite() && mpp > 0.0) {
+            return Err(PyValueError::new_err(format!(
+                "mpp must be a positive finite number, got {mpp}"
+            )));
+        }
+        Ok(self.inner.best_level_for_mpp(mpp))
+    }
+
     /// Get slide metadata.
     ///
     /// Returns:
@@ -281,11 +1175,115 @@ impl RustTileScheduler {
     ///
     /// Args:
     ///     level: Level index
+    ///     as_dict: Return a dict with the derived sizes as well
+    ///
+    /// Returns:
+    ///     Tuple of (downsample, cols, rows), or with as_dict a dict with
+    ///     downsample, cols, rows, tile_count, width and height (the level's
+    ///     size in pixels) and tile_size; None if the level doesn't exist
+    #[pyo3(signature = (level, as_dict=false))]
+    fn get_level_info<'py>(
+        &self,
+        py: Python<'py>,
+        level: u32,
+        as_dict: bool,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        if !as_dict {
+            let info = self.inner.get_level_info(level);
+            return info.map(|t| Ok(t.into_pyobject(py)?.into_any())).transpose();
+        }
+        let Some((info, (width, height), tile_size)) = self.inner.get_level_details(level) else {
+            return Ok(None);
+        };
+        let dict = PyDict::new(py);
+        dict.set_item("downsample", info.downsample)?;
+        dict.set_item("cols", info.cols)?;
+        dict.set_item("rows", info.rows)?;
+        dict.set_item("tile_count", info.tile_count())?;
+        dict.set_item("width", width)?;
+        dict.set_item("height", height)?;
+        dict.set_item("tile_size", tile_size)?;
+        Ok(Some(dict.into_any()))
+    }
+
+    /// Pixel size of one tile; edge tiles are smaller than tile_size.
+    ///
+    /// Returns:
+    ///     (width, height), or None if the tile is outside the level's grid
+    ///     (or no slide is loaded)
+    fn get_tile_dimensions(&self, level: u32, col: u32, row: u32) -> Option<(u32, u32)> {
+        self.inner.tile_dimensions(level, col, row)
+    }
+
+    /// Region the slide should open on, from its metadata.
+    ///
+    /// Returns:
+    ///     (x, y, width, height) in level-0 pixels, or None if the slide
+    ///     doesn't specify one (or no slide is loaded)
+    fn default_view(&self) -> Option<(f64, f64, f64, f64)> {
+        self.inner.default_view()
+    }
+
+    /// Named sub-regions of the slide (e.g. TMA cores) from its metadata.
+    ///
+    /// Returns:
+    ///     List of (name, (x, y, width, height)) in level-0 pixels, sorted by
+    ///     name; empty if the slide declares none (or no slide is loaded)
+    fn list_subslides(&self) -> Vec<(String, (u32, u32, u32, u32))> {
+        self.inner
+            .list_subslides()
+            .into_iter()
+            .map(|(name, r)| (name, (r.x, r.y, r.width, r.height)))
+            .collect()
+    }
+
+    /// Assemble a region of a subslide at a pyramid level, as get_region.
+    ///
+    /// Args:
+    ///     name: Subslide name, as from list_subslides
+    ///     level: Pyramid level number
+    ///     region: (x, y, w, h) in level pixels relative to the subslide's
+    ///         top-left (default: the whole subslide)
     ///
     /// Returns:
-    ///     Tuple of (downsample, cols, rows) or None if level doesn't exist
-    fn get_level_info(&self, level: u32) -> Option<(u32, u32, u32)> {
-        self.inner.get_level_info(level)
+    ///     Tuple of (bytes, width, height) with row-major RGB bytes
+    ///
+    /// Raises:
+    ///     RuntimeError: If no slide is loaded, or the subslide or level is
+    ///         unknown
+    #[pyo3(signature = (name, level, region=None))]
+    fn get_subslide_region<'py>(
+        &self,
+        py: Python<'py>,
+        name: &str,
+        level: u32,
+        region: Option<(i64, i64, u32, u32)>,
+    ) -> PyResult<(Bound<'py, PyBytes>, u32, u32)> {
+        let (data, w, h) =
+            py.allow_threads(|| self.inner.get_subslide_region(name, level, region))?;
+        Ok((PyBytes::new(py, &data), w, h))
+    }
+
+    /// Magnification the user sees at the given display scale.
+    ///
+    /// The level drawn and the display zoom applied to it cancel out to
+    /// target magnification times `scale`, continuous across level switches.
+    ///
+    /// Args:
+    ///     scale: Screen pixels per level-0 pixel (same as update_viewport)
+    ///
+    /// Returns:
+    ///     Effective magnification, or None if no slide is loaded
+    ///
+    /// Raises:
+    ///     ValueError: If scale is not a positive finite number
+    fn effective_magnification(&self, scale: f64) -> PyResult<Option<f64>> {
+        if !(scale.is_finite() && scale > 0.0) {
+            return Err(PyValueError::new_err(format!(
+                "scale must be a positive finite number, got {scale}"
+            )));
+        }
+        Ok(self.inner.effective_magnification(scale))
     }
 
     /// Filter a list of tiles to only those that are cached.
@@ -299,6 +1297,22 @@ impl RustTileScheduler {
         self.inner.filter_cached_tiles(&tiles)
     }
 
+    /// Whether a tile is cached in L1 or L2, without reading or decoding it.
+    ///
+    /// Returns:
+    ///     True if get_tile would be served from memory
+    fn is_tile_cached(&self, level: u32, col: u32, row: u32) -> bool {
+        self.inner.is_tile_cached(level, col, row)
+    }
+
+    /// Levels of the current slide whose tiles are all resident in L2.
+    ///
+    /// Returns:
+    ///     List of level numbers that are fully preloaded (empty if no slide)
+    fn l2_complete_levels(&self) -> Vec<u32> {
+        self.inner.l2_complete_levels()
+    }
+
     /// Start background preloading of directory slides into L2 cache.
     ///
     /// Args:
@@ -308,6 +1322,16 @@ impl RustTileScheduler {
         self.inner.start_bulk_preload(slide_paths);
     }
 
+    /// Start background preloading of the loaded slide's tiles into L2.
+    ///
+    /// Same as start_bulk_preload with only the current slide's path.
+    ///
+    /// Returns:
+    ///     False if no slide is loaded
+    fn warm_current_slide(&self) -> bool {
+        self.inner.warm_current_slide()
+    }
+
     /// Cancel any running bulk preload operation.
     fn cancel_bulk_preload(&self) {
         self.inner.cancel_bulk_preload();
@@ -318,6 +1342,194 @@ impl RustTileScheduler {
     fn is_bulk_preloading(&self) -> bool {
         self.inner.is_bulk_preloading()
     }
+
+    /// Set the order tiles are fetched when bulk-preloading a level.
+    ///
+    /// Args:
+    ///     order: "row_major" (pack storage order, default) or "morton"
+    ///         (Z-order; keeps spatially adjacent tiles together so a
+    ///         partially completed run covers a contiguous area)
+    ///
+    /// Raises:
+    ///     ValueError: If the order name is unknown
+    fn set_fetch_order(&self, order: &str) -> PyResult<()> {
+        let order = TileOrder::parse(order)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown fetch order: {order}")))?;
+        self.inner.set_tile_order(order);
+        Ok(())
+    }
+
+    /// Set the pixel format tiles are decoded into and cached in.
+    ///
+    /// get_tile_buffer, get_tiles, get_tile_array and get_tile_numpy return
+    /// this format without a copy when none is given. The bytes getters
+    /// (get_tile and friends) still return RGB.
+    ///
+    /// Args:
+    ///     pixel_format: "rgb" (default), "bgra" or "rgba". The 4-byte
+    ///         formats let QImage wrap tiles without repacking, but weigh
+    ///         4/3 as much, so cache_size_mb holds fewer tiles.
+    ///
+    /// Raises:
+    ///     ValueError: If the pixel format name is unknown
+    fn set_default_pixel_format(&self, pixel_format: &str) -> PyResult<()> {
+        self.inner
+            .set_default_pixel_format(parse_pixel_format(pixel_format)?);
+        Ok(())
+    }
+
+    /// Decode grayscale tiles single-channel instead of expanding them to RGB.
+    ///
+    /// When True, grayscale tiles come back as "gray8", and 16-bit grayscale
+    /// PNG tiles as "gray16" (native-endian uint16, QImage.Format_Grayscale16).
+    /// Check TileBuffer.sample_format, or the dtype/shape from get_tile_array,
+    /// to pick the QImage format. Color tiles and get_region stay RGB.
+    #[getter]
+    fn preserve_grayscale(&self) -> bool {
+        self.inner.preserve_grayscale()
+    }
+
+    #[setter]
+    fn set_preserve_grayscale(&self, enabled: bool) {
+        self.inner.set_preserve_grayscale(enabled);
+    }
+
+    /// Set a per-pixel transform applied to tiles as they are decoded.
+    ///
+    /// Transformed tiles are cached separately per transform, so toggling
+    /// never serves stale pixels and switching back reuses cached tiles.
+    ///
+    /// Args:
+    ///     kind: "none", "gamma" (params: [gamma]) or "invert" (no params)
+    ///     params: Transform parameters
+    ///
+    /// Raises:
+    ///     ValueError: If the kind is unknown or params don't match it
+    #[pyo3(signature = (kind, params=Vec::new()))]
+    fn set_tile_transform(&self, kind: &str, params: Vec<f64>) -> PyResult<()> {
+        let transform = TileTransform::parse(kind, &params).ok_or_else(|| {
+            PyValueError::new_err(format!("Invalid tile transform: {kind} {params:?}"))
+        })?;
+        self.inner.set_tile_transform(transform);
+        Ok(())
+    }
+
+    /// Mark the viewer as actively zooming/panning.
+    ///
+    /// While active, background bulk preload is paused so interactive
+    /// prefetch isn't competing for I/O. Set False to resume it.
+    ///
+    /// Args:
+    ///     active: True on interaction start, False after it settles
+    fn set_interactive(&self, active: bool) {
+        self.inner.set_interactive(active);
+    }
+
+    /// Whether the viewer is currently marked interactive.
+    #[getter]
+    fn is_interactive(&self) -> bool {
+        self.inner.is_interactive()
+    }
+
+    /// Largest fraction of the L2 cache a single slide may occupy (1.0 = no cap).
+    ///
+    /// A slide at its cap evicts its own oldest tiles rather than other
+    /// slides', keeping bulk-preloaded neighbors balanced.
+    #[getter]
+    fn l2_per_slide_max_fraction(&self) -> f64 {
+        self.inner.l2_per_slide_max_fraction()
+    }
+
+    #[setter]
+    fn set_l2_per_slide_max_fraction(&self, fraction: f64) -> PyResult<()> {
+        if !(fraction > 0.0 && fraction <= 1.0) {
+            return Err(PyValueError::new_err(format!(
+                "l2_per_slide_max_fraction must be in (0, 1], got {fraction}"
+            )));
+        }
+        self.inner.set_l2_per_slide_max_fraction(fraction);
+        Ok(())
+    }
+
+    /// Milliseconds a foreground get_tile miss waits for an in-flight prefetch
+    /// of the same tile, reusing its result instead of decoding twice.
+    ///
+    /// On timeout the foreground decodes the tile itself. 0 (default) disables.
+    #[getter]
+    fn coalesce_wait_ms(&self) -> f64 {
+        self.inner.coalesce_wait().as_secs_f64() * 1000.0
+    }
+
+    #[setter]
+    fn set_coalesce_wait_ms(&self, wait_ms: f64) -> PyResult<()> {
+        let wait = Duration::try_from_secs_f64(wait_ms / 1000.0).map_err(|_| {
+            PyValueError::new_err(format!(
+                "coalesce_wait_ms must be a non-negative duration, got {wait_ms}"
+            ))
+        })?;
+        self.inner.set_coalesce_wait(wait);
+        Ok(())
+    }
+
+    /// Largest prefetch batch decoded on the calling thread instead of rayon.
+    ///
+    /// At high zoom a viewport update covers only a tile or two, where pool
+    /// dispatch overhead dominates. 0 (default) always uses rayon.
+    #[getter]
+    fn sync_batch_max_tiles(&self) -> usize {
+        self.inner.sync_batch_max_tiles()
+    }
+
+    #[setter]
+    fn set_sync_batch_max_tiles(&self, max_tiles: usize) {
+        self.inner.set_sync_batch_max_tiles(max_tiles);
+    }
+
+    /// Whether L1 stores decoded tiles LZ4-compressed.
+    #[getter]
+    fn l1_lz4(&self) -> bool {
+        self.inner.l1_lz4()
+    }
+
+    /// How many of the highest-priority slides bulk preload re-checks after a run.
+    ///
+    /// Tiles of those slides that later slides evicted from L2 are re-loaded,
+    /// so the current slide (default: 1) ends the run fully resident. 0 disables.
+    #[getter]
+    fn bulk_preload_verify_slides(&self) -> usize {
+        self.inner.bulk_preload_verify_slides()
+    }
+
+    #[setter]
+    fn set_bulk_preload_verify_slides(&self, count: usize) {
+        self.inner.set_bulk_preload_verify_slides(count);
+    }
+}
+
+fn parse_pixel_format(name: &str) -> PyResult<PixelFormat> {
+    PixelFormat::parse(name)
+        .ok_or_else(|| PyValueError::new_err(format!("Unknown pixel format: {name}")))
+}
+
+fn cache_debug_dict<'py>(py: Python<'py>, debug: &CacheDebug) -> PyResult<Bound<'py, PyDict>> {
+    let tile = |c: TileCoord| (c.level, c.col, c.row);
+    let slides = PyDict::new(py);
+    for (slide_id, part) in &debug.partitions {
+        let d = PyDict::new(py);
+        d.set_item("entries", part.entries)?;
+        d.set_item("bytes", part.bytes)?;
+        d.set_item("oldest", part.oldest.map(tile))?;
+        d.set_item("newest", part.newest.map(tile))?;
+        slides.set_item(slide_id, d)?;
+    }
+
+    let dict = PyDict::new(py);
+    dict.set_item("entry_count", debug.entry_count)?;
+    dict.set_item("weighted_size", debug.weighted_size)?;
+    dict.set_item("max_bytes", debug.max_bytes)?;
+    dict.set_item("levels", debug.level_counts.clone())?;
+    dict.set_item("slides", slides)?;
+    Ok(dict)
 }
 
 /// Pack dzsave output tiles_files into per-level tiles/level_N.pack + level_N.idx.
@@ -326,30 +1538,44 @@ impl RustTileScheduler {
 ///   path: Path to the .fastpath directory (must contain tiles_files from dzsave)
 ///   levels: List of (level, cols, rows) entries
 ///   progress_cb: Optional callable(level_index, total_levels) called after each level
+///   compression_level: Zstd level (1-22) for non-JPEG tiles such as PNG
+///     masks; None (default) stores every tile raw. JPEG tiles are never
+///     recompressed.
 #[pyfunction]
-#[pyo3(signature = (path, levels, progress_cb=None))]
+#[pyo3(signature = (path, levels, progress_cb=None, compression_level=None))]
 fn pack_dzsave_tiles(
     py: Python<'_>,
     path: &str,
     levels: Vec<(u32, u32, u32)>,
     progress_cb: Option<PyObject>,
+    compression_level: Option<i32>,
 ) -> PyResult<()> {
-    let cb = progress_cb.map(|py_cb| -> Box<dyn Fn(u32, u32) + Send + Sync> {
-        let py_cb = std::sync::Mutex::new(py_cb);
-        Box::new(move |level_idx: u32, total_levels: u32| {
-            let py_cb = py_cb.lock().unwrap();
-            Python::with_gil(|py| {
-                if let Err(e) = py_cb.call1(py, (level_idx, total_levels)) {
-                    eprintln!("[PACK] Progress callback error: {e}");
-                }
-            });
-        })
-    });
+    let compression = compression_level
+        .map(pack::ZstdLevel::new)
+        .transpose()
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let cb = progress_cb.map(level_progress_callback);
 
-    py.allow_threads(|| pack::pack_dzsave_tiles(Path::new(path), &levels, cb))?;
+    py.allow_threads(|| {
+        pack::pack_dzsave_tiles(Path::new(path), &levels, compression, cb)
+    })?;
     Ok(())
 }
 
+/// Wrap a Python `callable(done, total)` for per-level progress reported
+/// from worker threads; errors it raises are logged, not propagated.
+fn level_progress_callback(py_cb: PyObject) -> Box<dyn Fn(u32, u32) + Send + Sync> {
+    let py_cb = std::sync::Mutex::new(py_cb);
+    Box::new(move |level_idx: u32, total_levels: u32| {
+        let py_cb = py_cb.lock().unwrap();
+        Python::with_gil(|py| {
+            if let Err(e) = py_cb.call1(py, (level_idx, total_levels)) {
+                log::warn!("Progress callback error: {e}");
+            }
+        });
+    })
+}
+
 /// Benchmark: old sequential + per-tile stat packing (no cleanup).
 #[pyfunction]
 fn bench_pack_seq_stat(py: Python<'_>, path: &str, levels: Vec<(u32, u32, u32)>) -> PyResult<()> {
@@ -379,22 +1605,282 @@ fn bench_pack_parallel(
     Ok(())
 }
 
+/// Benchmark: decode one tile repeatedly (for tuning tile size/quality).
+///
+/// Args:
+///   jpeg_bytes: Encoded tile (JPEG, PNG, or WebP)
+///   iterations: Number of decodes to time
+///
+/// Returns:
+///   Dict with keys: total_ms, per_tile_ms, megapixels_per_sec, lz4_per_tile_ms,
+///   lz4_ratio. The lz4 keys time decompressing the decoded tile from LZ4 (the
+///   cost of an L1 hit with l1_lz4=True) and its compression ratio.
+#[pyfunction]
+fn bench_decode<'py>(
+    py: Python<'py>,
+    jpeg_bytes: &[u8],
+    iterations: u32,
+) -> PyResult<Bound<'py, PyDict>> {
+    let bytes = bytes::Bytes::copy_from_slice(jpeg_bytes);
+    let bench = py.allow_threads(|| decoder::bench_decode(bytes, iterations))?;
+    let dict = PyDict::new(py);
+    dict.set_item("total_ms", bench.total_ms)?;
+    dict.set_item("per_tile_ms", bench.per_tile_ms)?;
+    dict.set_item("megapixels_per_sec", bench.megapixels_per_sec)?;
+    dict.set_item("lz4_per_tile_ms", bench.lz4_per_tile_ms)?;
+    dict.set_item("lz4_ratio", bench.lz4_ratio)?;
+    Ok(dict)
+}
+
+/// List the tiles whose content differs between two packed versions of a slide.
+///
+/// For surgical cache invalidation after a re-scan. Compares stored CRC32s
+/// when both packs have them, otherwise the tile bytes (in parallel, with the
+/// GIL released). A tile present in only one pack counts as changed.
+///
+/// Args:
+///   old_dir: Path to the previous .fastpath directory
+///   new_dir: Path to the re-scanned .fastpath directory
+///
+/// Returns:
+///   List of (level, col, row) tuples, in level, row, col order
+///
+/// Raises:
+///   RuntimeError: If either pack cannot be opened
+#[pyfunction]
+fn diff_packs(py: Python<'_>, old_dir: &str, new_dir: &str) -> PyResult<Vec<(u32, u32, u32)>> {
+    Ok(py.allow_threads(|| pack::diff_packs(Path::new(old_dir), Path::new(new_dir)))?)
+}
+
+/// Check that every tile of a packed slide can be read and decoded.
+///
+/// Reads each non-empty tile (verifying its checksum) and parses its image
+/// header, levels in parallel with the GIL released. Bad tiles are collected
+/// rather than raised.
+///
+/// Args:
+///   path: Path to the .fastpath directory
+///
+/// Returns:
+///   Dict with keys: levels, total_tiles, empty_tiles, corrupt_tiles (list of
+///   (level, col, row) tuples in level, row, col order)
+///
+/// Raises:
+///   RuntimeError: If the pack cannot be opened
+#[pyfunction]
+fn validate_slide<'py>(py: Python<'py>, path: &str) -> PyResult<Bound<'py, PyDict>> {
+    let summary = py.allow_threads(|| pack::validate_pack(Path::new(path)))?;
+    let dict = PyDict::new(py);
+    dict.set_item("levels", summary.levels)?;
+    dict.set_item("total_tiles", summary.total_tiles)?;
+    dict.set_item("empty_tiles", summary.empty_tiles)?;
+    dict.set_item("corrupt_tiles", summary.corrupt_tiles)?;
+    Ok(dict)
+}
+
+/// Re-encode a packed slide's JPEG tiles at a lower quality, in place.
+///
+/// Tiles are decoded and re-encoded in parallel with the GIL released. A tile
+/// keeps its bytes when re-encoding would not shrink it; non-JPEG tiles are
+/// copied unchanged. Reload the slide afterwards: cached tiles are stale.
+///
+/// Args:
+///   path: Path to the .fastpath directory
+///   quality: JPEG quality 1-100
+///
+/// Returns:
+///   (bytes_before, bytes_after) of stored tile data
+///
+/// Raises:
+///   ValueError: If quality is outside 1-100
+///   RuntimeError: If the pack cannot be read or rewritten
+#[pyfunction]
+fn repack_slide(py: Python<'_>, path: &str, quality: u8) -> PyResult<(u64, u64)> {
+    if !(1..=100).contains(&quality) {
+        return Err(PyValueError::new_err(format!(
+            "quality must be 1-100, got {quality}"
+        )));
+    }
+    Ok(py.allow_threads(|| pack::repack_slide(Path::new(path), quality))?)
+}
+
+/// Convert a packed slide's JPEG tiles to another codec, in place.
+///
+/// Every JPEG tile is decoded and re-encoded, levels in parallel with the GIL
+/// released. A tile keeps its original bytes unless the new encoding is
+/// smaller, and ICC profiles are carried over; empty tiles stay empty and
+/// non-JPEG tiles are copied unchanged. Reload the slide afterwards: cached
+/// tiles are stale.
+///
+/// Args:
+///   path: Path to the .fastpath directory
+///   codec: "jpeg" or "png" ("webp" is rejected: only lossless WebP can be
+///     encoded, which would grow lossy tiles)
+///   quality: JPEG quality 1-100 (ignored for PNG)
+///   progress_cb: Optional callable(levels_done, total_levels) called after
+///     each level
+///
+/// Returns:
+///   (bytes_before, bytes_after) of stored tile data
+///
+/// Raises:
+///   ValueError: If codec is unknown or quality is outside 1-100
+///   RuntimeError: If codec is "webp" or the pack cannot be read or rewritten
+#[pyfunction]
+#[pyo3(signature = (path, codec, quality=90, progress_cb=None))]
+fn transcode_pack(
+    py: Python<'_>,
+    path: &str,
+    codec: &str,
+    quality: u8,
+    progress_cb: Option<PyObject>,
+) -> PyResult<(u64, u64)> {
+    let format = ImageFormat::parse(codec)
+        .ok_or_else(|| PyValueError::new_err(format!("Unknown codec: {codec}")))?;
+    if !(1..=100).contains(&quality) {
+        return Err(PyValueError::new_err(format!(
+            "quality must be 1-100, got {quality}"
+        )));
+    }
+    let cb = progress_cb.map(level_progress_callback);
+    Ok(py.allow_threads(|| pack::transcode_pack(Path::new(path), format, quality, cb))?)
+}
+
+/// Read the metadata of every .fastpath slide under a directory tree.
+///
+/// Metadata files are loaded in parallel with the GIL released.
+///
+/// Args:
+///   root: Directory to scan recursively
+///
+/// Returns:
+///   List of dicts sorted by path. Valid slides have keys: path, width, height,
+///   tile_size, num_levels, mpp, magnification. Invalid slides have keys: path, error.
+///
+/// Raises:
+///   RuntimeError: If root cannot be read
+#[pyfunction]
+fn catalog_dir<'py>(py: Python<'py>, root: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let entries = py.allow_threads(|| format::catalog_dir(Path::new(root)))?;
+    entries
+        .into_iter()
+        .map(|entry| {
+            let dict = PyDict::new(py);
+            dict.set_item("path", entry.path.to_string_lossy())?;
+            match entry.metadata {
+                Ok(m) => {
+                    dict.set_item("width", m.dimensions.0)?;
+                    dict.set_item("height", m.dimensions.1)?;
+                    dict.set_item("tile_size", m.tile_size)?;
+                    dict.set_item("num_levels", m.num_levels())?;
+                    dict.set_item("mpp", m.target_mpp)?;
+                    dict.set_item("magnification", m.target_magnification)?;
+                }
+                Err(e) => dict.set_item("error", e)?,
+            }
+            Ok(dict)
+        })
+        .collect()
+}
+
+/// Check a slide's metadata.json and report every problem at once.
+///
+/// Args:
+///   path: Path to the .fastpath directory
+///
+/// Returns:
+///   List of violation messages (empty if the metadata is valid)
+///
+/// Raises:
+///   RuntimeError: If metadata.json is missing or not valid JSON
+#[pyfunction]
+fn validate_metadata(path: &str) -> PyResult<Vec<String>> {
+    Ok(format::SlideMetadata::validate_file(Path::new(path))?)
+}
+
+/// Route the extension's log output to Python's ``logging`` module.
+///
+/// The extension is silent until this is called. Records go to loggers
+/// named after the Rust module, e.g. ``fastpath_core::scheduler`` (tile
+/// errors at WARNING, prefetch batches and FASTPATH_TILE_TIMING output at
+/// DEBUG), so handlers and per-logger levels configured in Python apply.
+/// Calling again changes the level.
+///
+/// Args:
+///   level: Most verbose level passed on: "off", "error", "warning", "info",
+///     "debug" or "trace" (default: "info")
+///
+/// Raises:
+///   ValueError: If the level name is unknown
+///   OSError: If the forwarding thread can't be started
+#[pyfunction]
+#[pyo3(signature = (level="info"))]
+fn init_logging(level: &str) -> PyResult<()> {
+    let filter = logging::parse_level(level)
+        .ok_or_else(|| PyValueError::new_err(format!("Unknown log level: {level}")))?;
+    logging::init(filter)?;
+    Ok(())
+}
+
 /// Whether the Rust extension was compiled without optimizations (debug build).
 #[pyfunction]
 fn is_debug_build() -> bool {
     cfg!(debug_assertions)
 }
 
+/// Report SIMD features of this CPU and what the decoder uses.
+///
+/// Returns:
+///     Dict with arch (str), sse2/sse41/avx/avx2/neon (bool, detected at
+///     runtime), jpeg_simd (path the JPEG decoder selects: "avx2", "neon"
+///     or "scalar") and compiled (target features the build assumes)
+#[pyfunction]
+fn cpu_features(py: Python<'_>) -> PyResult<Bound<'_, PyDict>> {
+    let cpu = CpuFeatures::detect();
+    let dict = PyDict::new(py);
+    dict.set_item("arch", cpu.arch)?;
+    dict.set_item("sse2", cpu.sse2)?;
+    dict.set_item("sse41", cpu.sse41)?;
+    dict.set_item("avx", cpu.avx)?;
+    dict.set_item("avx2", cpu.avx2)?;
+    dict.set_item("neon", cpu.neon)?;
+    dict.set_item("jpeg_simd", cpu.jpeg_simd())?;
+    dict.set_item("compiled", cpu::compiled_features())?;
+    Ok(dict)
+}
+
 /// FastPATH Core - High-performance tile scheduler for WSI viewing.
 #[pymodule]
 fn fastpath_core(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    // Fail the import cleanly rather than SIGILL on the first decode
+    let missing = cpu::missing_compiled_features(&CpuFeatures::detect());
+    if !missing.is_empty() {
+        return Err(PyImportError::new_err(format!(
+            "fastpath_core was built for CPU features this machine lacks: {}; \
+             rebuild without target-cpu/target-feature flags",
+            missing.join(", ")
+        )));
+    }
+
     m.add_class::<RustTileScheduler>()?;
+    m.add_class::<SharedL2>()?;
     m.add_class::<TileBuffer>()?;
     m.add_class::<FastpathTileReader>()?;
+    m.add_class::<LevelTileIter>()?;
+    m.add_class::<CancelToken>()?;
     m.add_function(wrap_pyfunction!(pack_dzsave_tiles, m)?)?;
     m.add_function(wrap_pyfunction!(bench_pack_seq_stat, m)?)?;
     m.add_function(wrap_pyfunction!(bench_pack_seq_prescan, m)?)?;
     m.add_function(wrap_pyfunction!(bench_pack_parallel, m)?)?;
+    m.add_function(wrap_pyfunction!(bench_decode, m)?)?;
+    m.add_function(wrap_pyfunction!(diff_packs, m)?)?;
+    m.add_function(wrap_pyfunction!(repack_slide, m)?)?;
+    m.add_function(wrap_pyfunction!(transcode_pack, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_slide, m)?)?;
+    m.add_function(wrap_pyfunction!(init_logging, m)?)?;
+    m.add_function(wrap_pyfunction!(catalog_dir, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_metadata, m)?)?;
     m.add_function(wrap_pyfunction!(is_debug_build, m)?)?;
+    m.add_function(wrap_pyfunction!(cpu_features, m)?)?;
     Ok(())
 }
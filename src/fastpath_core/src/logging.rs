@@ -0,0 +1,131 @@
+//! Bridge from the `log` crate to Python's `logging` module.
+//!
+//! Rust modules log with `log::{warn, info, debug}`; nothing is emitted until
+//! Python calls `init_logging`. Records then go to
+//! `logging.getLogger(<target>)`, e.g. `fastpath_core::scheduler`, where the
+//! application's handlers and levels apply.
+//!
+//! Records are queued and forwarded by one thread: logging call sites run on
+//! worker threads that may hold scheduler locks, and taking the GIL there
+//! could deadlock against a Python thread waiting on those locks.
+
+use std::sync::OnceLock;
+
+use crossbeam_channel::{Receiver, Sender};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use pyo3::prelude::*;
+
+/// Records buffered before new ones are dropped (e.g. a flood of tile errors
+/// while Python is busy).
+const LOG_QUEUE_CAPACITY: usize = 4096;
+
+struct QueuedRecord {
+    target: String,
+    level: Level,
+    message: String,
+}
+
+struct PythonLogger {
+    tx: Sender<QueuedRecord>,
+}
+
+impl Log for PythonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // Full queue: drop the record rather than block the caller
+        let _ = self.tx.try_send(QueuedRecord {
+            target: record.target().to_string(),
+            level: record.level(),
+            message: record.args().to_string(),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: OnceLock<PythonLogger> = OnceLock::new();
+
+/// Parse a level name as Python or `log` spell it (case-insensitive).
+pub fn parse_level(name: &str) -> Option<LevelFilter> {
+    match name.to_ascii_lowercase().as_str() {
+        "off" | "none" => Some(LevelFilter::Off),
+        "critical" | "error" => Some(LevelFilter::Error),
+        "warning" | "warn" => Some(LevelFilter::Warn),
+        "info" => Some(LevelFilter::Info),
+        "debug" => Some(LevelFilter::Debug),
+        "trace" => Some(LevelFilter::Trace),
+        _ => None,
+    }
+}
+
+/// Python `logging` level number for `level`.
+fn python_level(level: Level) -> u32 {
+    match level {
+        Level::Error => 40,
+        Level::Warn => 30,
+        Level::Info => 20,
+        Level::Debug => 10,
+        Level::Trace => 5,
+    }
+}
+
+fn forward(rx: Receiver<QueuedRecord>) {
+    for record in rx {
+        Python::with_gil(|py| {
+            let result = py
+                .import("logging")
+                .and_then(|logging| logging.call_method1("getLogger", (record.target,)))
+                .and_then(|logger| {
+                    logger.call_method1("log", (python_level(record.level), record.message))
+                });
+            if let Err(e) = result {
+                e.print(py);
+            }
+        });
+    }
+}
+
+/// Install the Python bridge (first call only) and set the level.
+///
+/// Later calls just change the level.
+pub fn init(level: LevelFilter) -> std::io::Result<()> {
+    if LOGGER.get().is_none() {
+        let (tx, rx) = crossbeam_channel::bounded(LOG_QUEUE_CAPACITY);
+        // Spawn before publishing the logger, so a failed spawn leaves init
+        // retryable instead of a logger whose queue nothing drains. If another
+        // call wins the race, our sender is dropped and the thread just exits.
+        std::thread::Builder::new()
+            .name("log-bridge".into())
+            .spawn(move || forward(rx))?;
+        if LOGGER.set(PythonLogger { tx }).is_ok() {
+            if let Some(logger) = LOGGER.get() {
+                // Fails only if another logger was installed first
+                let _ = log::set_logger(logger);
+            }
+        }
+    }
+    log::set_max_level(level);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level_accepts_python_and_log_names() {
+        assert_eq!(parse_level("WARNING"), Some(LevelFilter::Warn));
+        assert_eq!(parse_level("warn"), Some(LevelFilter::Warn));
+        assert_eq!(parse_level("critical"), Some(LevelFilter::Error));
+        assert_eq!(parse_level("Debug"), Some(LevelFilter::Debug));
+        assert_eq!(parse_level("off"), Some(LevelFilter::Off));
+        assert_eq!(parse_level("verbose"), None);
+        assert_eq!(python_level(Level::Warn), 30);
+    }
+}
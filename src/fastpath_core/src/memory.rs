@@ -0,0 +1,66 @@
+//! System memory checks for cache sizing.
+//!
+//! L1 and L2 fill up to their configured sizes over a long session, so a
+//! cache budget larger than physical RAM doesn't fail at construction: it
+//! swaps and then gets the process OOM-killed hours later. The constructor
+//! compares a caller-chosen budget against total RAM up front and warns
+//! instead. The defaults are generous and not checked: they would warn on
+//! most machines without the caller having asked for anything.
+
+use sysinfo::{MemoryRefreshKind, RefreshKind, System};
+
+/// L1 size when the constructor isn't given one.
+pub const DEFAULT_L1_CACHE_MB: usize = 4096;
+
+/// L2 size when the constructor isn't given one.
+pub const DEFAULT_L2_CACHE_MB: usize = 32768;
+
+/// Share of total RAM the caches may claim before we warn; the rest is left
+/// for decodes in flight, the viewer itself, and the rest of the machine.
+pub const SAFE_RAM_FRACTION: f64 = 0.75;
+
+/// Total physical memory in bytes, or None if the OS doesn't report it.
+pub fn total_memory_bytes() -> Option<u64> {
+    let refresh = RefreshKind::nothing().with_memory(MemoryRefreshKind::nothing().with_ram());
+    let total = System::new_with_specifics(refresh).total_memory();
+    (total > 0).then_some(total)
+}
+
+/// Warning text when `l1_mb + l2_mb` exceeds `SAFE_RAM_FRACTION` of
+/// `total_bytes`, else None.
+pub fn cache_budget_warning(l1_mb: u64, l2_mb: u64, total_bytes: u64) -> Option<String> {
+    const MB: u64 = 1024 * 1024;
+    let budget_mb = l1_mb.saturating_add(l2_mb);
+    let safe_mb = (total_bytes as f64 * SAFE_RAM_FRACTION) as u64 / MB;
+    (budget_mb > safe_mb).then(|| {
+        format!(
+            "cache_size_mb + l2_cache_size_mb = {} MB exceeds {:.0}% of system RAM \
+             ({} MB); the process may be killed once the caches fill. \
+             Consider a combined size of at most {} MB.",
+            budget_mb,
+            SAFE_RAM_FRACTION * 100.0,
+            total_bytes / MB,
+            safe_mb
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_budget_warning_threshold() {
+        let eight_gb = 8 * 1024 * 1024 * 1024;
+        // 6144 MB is exactly 75% of 8 GB
+        assert!(cache_budget_warning(4096, 2048, eight_gb).is_none());
+        let msg = cache_budget_warning(12288, 0, eight_gb).unwrap();
+        assert!(msg.contains("12288 MB"), "{msg}");
+        assert!(msg.contains("at most 6144 MB"), "{msg}");
+        assert!(cache_budget_warning(4096, 2049, eight_gb).is_some());
+        // May be unknown on some platforms; when reported it must be real
+        if let Some(total) = total_memory_bytes() {
+            assert!(total > 0);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Background drain for visible tiles beyond the foreground batch cap.
+//!
+//! A zoomed-out viewport can cover more than `MAX_VISIBLE_TILES` uncached
+//! tiles. The foreground batch loads the first `MAX_VISIBLE_TILES`; the rest
+//! are handed to this drain, which reads them into L2 one at a time on a
+//! single low-priority thread so the far edges of the view eventually load.
+//!
+//! The same drain warms saved bookmark views (a separate instance, so the
+//! two don't cancel each other).
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use parking_lot::{Condvar, Mutex, MutexGuard};
+
+use crate::cache::{CompressedTileCache, SlideTileCoord, TileCoord};
+use crate::decoder::CompressedTileData;
+use crate::error::TileResult;
+use crate::slide_pool::SlideEntry;
+
+/// A submitted list of tiles, valid while its ticket is the latest.
+struct Job {
+    ticket: u64,
+    slide_id: u64,
+    entry: Arc<SlideEntry>,
+    tiles: Vec<TileCoord>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Next job for the worker; a newer submission replaces it.
+    pending: Option<Job>,
+    /// Ticket of the job the worker is reading, if any.
+    running: Option<u64>,
+    worker_started: bool,
+    shutdown: bool,
+}
+
+/// State shared with the worker thread.
+struct Shared {
+    l2_cache: Arc<CompressedTileCache>,
+    /// Bumped by every submit and cancel; a job whose ticket is no longer
+    /// current stops at its next tile.
+    ticket: AtomicU64,
+    state: Mutex<State>,
+    /// Signalled when a job is queued or the drain is dropped.
+    wake: Condvar,
+    /// Signalled when the worker goes idle.
+    idle: Condvar,
+}
+
+/// Single-thread background reader for overflow visible tiles.
+///
+/// The worker thread is spawned on the first submission and lives as long
+/// as the drain.
+pub struct OverflowDrain {
+    shared: Arc<Shared>,
+    thread_name: &'static str,
+}
+
+impl OverflowDrain {
+    pub fn new(l2_cache: Arc<CompressedTileCache>, thread_name: &'static str) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                l2_cache,
+                ticket: AtomicU64::new(0),
+                state: Mutex::new(State::default()),
+                wake: Condvar::new(),
+                idle: Condvar::new(),
+            }),
+            thread_name,
+        }
+    }
+
+    /// Replace any pending drain with `tiles` from `entry`.
+    ///
+    /// Tiles already in L2 are skipped. Only the latest viewport's overflow
+    /// matters, so a new submission cancels the previous one. Fails only if
+    /// the worker thread can't be spawned.
+    pub fn submit(
+        &self,
+        slide_id: u64,
+        entry: Arc<SlideEntry>,
+        tiles: Vec<TileCoord>,
+    ) -> TileResult<()> {
+        let ticket = self.shared.ticket.fetch_add(1, Ordering::AcqRel) + 1;
+        if slide_id == 0 || tiles.is_empty() {
+            return Ok(());
+        }
+
+        let mut state = self.shared.state.lock();
+        self.start_worker(&mut state)?;
+        // A racing submit that took a later ticket wins
+        if state.pending.as_ref().is_none_or(|job| job.ticket < ticket) {
+            state.pending = Some(Job {
+                ticket,
+                slide_id,
+                entry,
+                tiles,
+            });
+            self.shared.wake.notify_one();
+        }
+        Ok(())
+    }
+
+    fn start_worker(&self, state: &mut MutexGuard<'_, State>) -> TileResult<()> {
+        if state.worker_started {
+            return Ok(());
+        }
+        let shared = Arc::clone(&self.shared);
+        std::thread::Builder::new()
+            .name(self.thread_name.into())
+            .spawn(move || shared.run())?;
+        state.worker_started = true;
+        Ok(())
+    }
+
+    /// Cancel any pending drain without waiting: the worker drops a queued
+    /// job and stops a running one at its next tile.
+    pub fn cancel(&self) {
+        self.shared.ticket.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Wait until the worker has nothing left to do.
+    #[cfg(test)]
+    pub fn wait(&self) {
+        let mut state = self.shared.state.lock();
+        while state.running.is_some() || state.pending.is_some() {
+            self.shared.idle.wait(&mut state);
+        }
+    }
+
+    /// Whether an uncancelled drain is queued or running.
+    #[cfg(test)]
+    pub fn is_running(&self) -> bool {
+        let state = self.shared.state.lock();
+        let current = self.shared.ticket.load(Ordering::Acquire);
+        state.running == Some(current)
+            || state.pending.as_ref().is_some_and(|job| job.ticket == current)
+    }
+}
+
+impl Shared {
+    fn is_current(&self, ticket: u64) -> bool {
+        self.ticket.load(Ordering::Acquire) == ticket
+    }
+
+    /// Worker loop: run queued jobs until the drain is dropped.
+    fn run(&self) {
+        let mut state = self.state.lock();
+        loop {
+            if state.shutdown {
+                return;
+            }
+            let Some(job) = state.pending.take() else {
+                self.idle.notify_all();
+                self.wake.wait(&mut state);
+                continue;
+            };
+            if !self.is_current(job.ticket) {
+                continue;
+            }
+            state.running = Some(job.ticket);
+            MutexGuard::unlocked(&mut state, || self.drain(job));
+            state.running = None;
+        }
+    }
+
+    fn drain(&self, job: Job) {
+        for coord in &job.tiles {
+            if !self.is_current(job.ticket) {
+                return;
+            }
+
+            let l2_coord = SlideTileCoord::new(job.slide_id, coord.level, coord.col, coord.row);
+            if self.l2_cache.contains(&l2_coord) {
+                continue;
+            }
+
+            let Some(tile_ref) = job.entry.pack.tile_ref(coord.level, coord.col, coord.row) else {
+                continue;
+            };
+
+            if let Ok(bytes) = job.entry.pack.read_tile_bytes(tile_ref) {
+                let compressed = CompressedTileData {
+                    jpeg_bytes: bytes,
+                    width: 0,
+                    height: 0,
+                };
+                self.l2_cache.insert(l2_coord, compressed);
+            }
+        }
+    }
+}
+
+impl Drop for OverflowDrain {
+    fn drop(&mut self) {
+        self.cancel();
+        self.shared.state.lock().shutdown = true;
+        self.shared.wake.notify_all();
+    }
+}
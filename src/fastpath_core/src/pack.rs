@@ -2,24 +2,81 @@
 
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufWriter, Write};
-use std::path::Path;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU32, Ordering};
 
 use bytes::Bytes;
+use log::{info, warn};
 use rayon::prelude::*;
 
+use crate::decoder::{read_tile_header, transcode_tile, CompressedTileData};
 use crate::error::{TileError, TileResult};
-
-const LEVEL_MAGIC: &[u8; 8] = b"FPLIDX1\0";
-const LEVEL_VERSION: u32 = 1;
+use crate::format::{level_dir_name, read_level_dir_names};
+use crate::imaging::ImageFormat;
+
+/// Current index format: entries carry a CRC32 of the tile bytes.
+const LEVEL_MAGIC: &[u8; 8] = b"FPLIDX2\0";
+const LEVEL_VERSION: u32 = 2;
+const LEVEL_ENTRY_SIZE: usize = 16;
+/// Legacy index format without checksums; still readable.
+const LEVEL_MAGIC_V1: &[u8; 8] = b"FPLIDX1\0";
+const LEVEL_VERSION_V1: u32 = 1;
+const LEVEL_ENTRY_SIZE_V1: usize = 12;
 const LEVEL_HEADER_SIZE: usize = 16;
-const LEVEL_ENTRY_SIZE: usize = 12;
+/// Set in an `FPLIDX2` entry's length when the stored bytes are Zstd-wrapped.
+/// Tiles are far below 2 GiB, so the top bit is never part of a real length.
+const ZSTD_FLAG: u32 = 1 << 31;
+
+/// Zstd level for `pack_dzsave_tiles` (1 = fastest, 22 = smallest).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZstdLevel(i32);
+
+impl ZstdLevel {
+    pub fn new(level: i32) -> TileResult<Self> {
+        let max = *zstd::compression_level_range().end();
+        if !(1..=max).contains(&level) {
+            return Err(TileError::Validation(format!(
+                "Zstd level must be between 1 and {}, got {}",
+                max, level
+            )));
+        }
+        Ok(Self(level))
+    }
+}
 
 #[derive(Debug, Clone, Copy)]
 struct TileEntry {
     offset: u64,
     length: u32,
+    /// CRC32 of the tile bytes; `None` for `FPLIDX1` indexes.
+    crc32: Option<u32>,
+    /// Stored bytes are Zstd-compressed (`FPLIDX2` only).
+    zstd: bool,
+}
+
+/// Backing storage for a level's `.pack` file.
+#[derive(Debug)]
+enum PackData {
+    /// Positioned reads (`read_at`/`seek_read`), one syscall per tile.
+    File(File),
+    /// Whole-file mapping; reads are slice copies.
+    Mmap(memmap2::Mmap),
+}
+
+impl PackData {
+    fn open(file: File, pack_len: u64, mmap: bool) -> std::io::Result<Self> {
+        // Zero-length files can't be mapped on every platform, and there is
+        // nothing to read from them anyway.
+        if !mmap || pack_len == 0 {
+            return Ok(Self::File(file));
+        }
+        // SAFETY: pack files are written once by the packer and then only
+        // read. Truncating one while it is mapped is unsupported, as it is
+        // for any reader of the slide.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Self::Mmap(map))
+    }
 }
 
 #[derive(Debug)]
@@ -28,12 +85,18 @@ struct LevelPack {
     cols: u32,
     rows: u32,
     entries: Vec<TileEntry>,
-    pack: File,
+    /// `None` when `level_N.pack` is missing; the level's tiles are unavailable.
+    pack: Option<PackData>,
     pack_len: u64,
 }
 
 impl LevelPack {
-    fn parse(level: u32, idx_bytes: &[u8], pack: File, pack_len: u64) -> TileResult<Self> {
+    fn parse(
+        level: u32,
+        idx_bytes: &[u8],
+        pack: Option<PackData>,
+        pack_len: u64,
+    ) -> TileResult<Self> {
         if idx_bytes.len() < LEVEL_HEADER_SIZE {
             return Err(TileError::Validation(format!(
                 "level_{}.idx is too small",
@@ -42,15 +105,19 @@ impl LevelPack {
         }
 
         let magic = &idx_bytes[0..8];
-        if magic != LEVEL_MAGIC {
+        let (expected_version, entry_size) = if magic == LEVEL_MAGIC {
+            (LEVEL_VERSION, LEVEL_ENTRY_SIZE)
+        } else if magic == LEVEL_MAGIC_V1 {
+            (LEVEL_VERSION_V1, LEVEL_ENTRY_SIZE_V1)
+        } else {
             return Err(TileError::Validation(format!(
                 "level_{}.idx magic mismatch",
                 level
             )));
-        }
+        };
 
         let version = u32::from_le_bytes(idx_bytes[8..12].try_into().unwrap());
-        if version != LEVEL_VERSION {
+        if version != expected_version {
             return Err(TileError::Validation(format!(
                 "Unsupported level_{}.idx version: {}",
                 level, version
@@ -68,7 +135,7 @@ impl LevelPack {
 
         let entry_count = (cols as u64).saturating_mul(rows as u64);
         let entries_bytes = entry_count
-            .checked_mul(LEVEL_ENTRY_SIZE as u64)
+            .checked_mul(entry_size as u64)
             .ok_or_else(|| {
                 TileError::Validation(format!("level_{}.idx entry table overflow", level))
             })?;
@@ -80,14 +147,36 @@ impl LevelPack {
             )));
         }
 
+        // Catch a corrupt entry table here rather than at some later tile
+        // read; a missing pack has no bytes to bound.
+        let check_bounds = pack.is_some();
         let mut entries = Vec::with_capacity(entry_count as usize);
         let mut cursor = LEVEL_HEADER_SIZE;
         for _ in 0..entry_count {
             let offset = u64::from_le_bytes(idx_bytes[cursor..cursor + 8].try_into().unwrap());
-            let length =
+            let mut length =
                 u32::from_le_bytes(idx_bytes[cursor + 8..cursor + 12].try_into().unwrap());
-            entries.push(TileEntry { offset, length });
-            cursor += LEVEL_ENTRY_SIZE;
+            let crc32 = (entry_size == LEVEL_ENTRY_SIZE).then(|| {
+                u32::from_le_bytes(idx_bytes[cursor + 12..cursor + 16].try_into().unwrap())
+            });
+            let zstd = crc32.is_some() && length & ZSTD_FLAG != 0;
+            length &= if crc32.is_some() { !ZSTD_FLAG } else { u32::MAX };
+            let end = offset.saturating_add(length as u64);
+            if check_bounds && length != 0 && end > pack_len {
+                let idx = entries.len() as u32;
+                return Err(TileError::Validation(format!(
+                    "level_{}.idx entry for tile ({}, {}) spans bytes {}..{} \
+                     past the end of the {}-byte pack",
+                    level,
+                    idx % cols,
+                    idx / cols,
+                    offset,
+                    end,
+                    pack_len
+                )));
+            }
+            entries.push(TileEntry { offset, length, crc32, zstd });
+            cursor += entry_size;
         }
 
         Ok(Self {
@@ -106,6 +195,11 @@ pub struct PackTileRef {
     pub level: u32,
     pub offset: u64,
     pub length: u32,
+    /// Expected CRC32 of the stored bytes, checked on read (`FPLIDX2` only).
+    pub crc32: Option<u32>,
+    /// Stored bytes are Zstd-compressed; `read_tile_bytes` decompresses them,
+    /// so `length` and `crc32` describe the stored, not the returned, bytes.
+    pub zstd: bool,
 }
 
 #[derive(Debug)]
@@ -114,7 +208,25 @@ pub struct TilePack {
 }
 
 impl TilePack {
+    /// Open every `level_N.idx` + `level_N.pack` pair under `tiles/`.
+    ///
+    /// A level whose `.pack` is missing (e.g. a partially-synced slide) is
+    /// kept but marked unavailable: `tile_ref` returns `None` for it and the
+    /// remaining levels stay readable.
     pub fn open(fastpath_dir: &Path) -> TileResult<Self> {
+        Self::open_with(fastpath_dir, false)
+    }
+
+    /// Like [`open`](Self::open), but memory-maps each `.pack` file so tile
+    /// reads are slice copies instead of a syscall per tile.
+    ///
+    /// Prefer `open` where mapping is undesirable, e.g. packs on network
+    /// shares that may change underneath the viewer.
+    pub fn open_mmap(fastpath_dir: &Path) -> TileResult<Self> {
+        Self::open_with(fastpath_dir, true)
+    }
+
+    fn open_with(fastpath_dir: &Path, mmap: bool) -> TileResult<Self> {
         let tiles_dir = fastpath_dir.join("tiles");
         if !tiles_dir.exists() {
             return Err(TileError::Validation(format!(
@@ -124,6 +236,7 @@ impl TilePack {
         }
 
         let mut levels = Vec::new();
+        let mut missing_packs = Vec::new();
         for entry in std::fs::read_dir(&tiles_dir)? {
             let entry = entry?;
             if !entry.file_type()?.is_file() {
@@ -143,10 +256,20 @@ impl TilePack {
                 TileError::Validation(format!("Invalid level index: {}", level_str))
             })?;
 
-            let idx_bytes = std::fs::read(entry.path())?;
+            let idx_path = recover_level_idx(&tiles_dir, level, entry.path());
+            let idx_bytes = std::fs::read(idx_path)?;
             let pack_path = tiles_dir.join(format!("level_{}.pack", level));
-            let pack = File::open(&pack_path)?;
-            let pack_len = pack.metadata()?.len();
+            let (pack, pack_len) = match File::open(&pack_path) {
+                Ok(pack) => {
+                    let pack_len = pack.metadata()?.len();
+                    (Some(PackData::open(pack, pack_len, mmap)?), pack_len)
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    missing_packs.push(level);
+                    (None, 0)
+                }
+                Err(e) => return Err(e.into()),
+            };
 
             let level_pack = LevelPack::parse(level, &idx_bytes, pack, pack_len)?;
             levels.push(level_pack);
@@ -167,6 +290,15 @@ impl TilePack {
                 )));
             }
         }
+
+        if !missing_packs.is_empty() {
+            missing_packs.sort_unstable();
+            warn!(
+                "{}: missing pack file for level(s) {:?}; those levels are unavailable",
+                fastpath_dir.display(),
+                missing_packs
+            );
+        }
         Ok(Self { levels })
     }
 
@@ -176,6 +308,7 @@ impl TilePack {
 
     pub fn tile_ref(&self, level: u32, col: u32, row: u32) -> Option<PackTileRef> {
         let info = self.find_level(level)?;
+        info.pack.as_ref()?;
         if col >= info.cols || row >= info.rows {
             return None;
         }
@@ -190,10 +323,73 @@ impl TilePack {
             level,
             offset: entry.offset,
             length: entry.length,
+            crc32: entry.crc32,
+            zstd: entry.zstd,
         })
     }
 
+    /// Present tiles of `level` as `(col, row, tile_ref)` in pack offset
+    /// order, so reading them in turn walks the `.pack` file sequentially.
+    ///
+    /// `None` for an unknown level; empty when its pack file is missing.
+    pub fn level_tiles_by_offset(&self, level: u32) -> Option<Vec<(u32, u32, PackTileRef)>> {
+        let info = self.find_level(level)?;
+        if info.pack.is_none() {
+            return Some(Vec::new());
+        }
+        let mut tiles: Vec<_> = info
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.length > 0)
+            .map(|(idx, entry)| {
+                let tile_ref = PackTileRef {
+                    level,
+                    offset: entry.offset,
+                    length: entry.length,
+                    crc32: entry.crc32,
+                    zstd: entry.zstd,
+                };
+                (idx as u32 % info.cols, idx as u32 / info.cols, tile_ref)
+            })
+            .collect();
+        tiles.sort_by_key(|(_, _, tile_ref)| tile_ref.offset);
+        Some(tiles)
+    }
+
+    /// Total stored tile bytes per level, from the index alone (no data reads).
+    ///
+    /// Levels whose pack file is missing report 0. Returns `(level, bytes)` pairs in ascending level order.
+    pub fn level_byte_sizes(&self) -> Vec<(u32, u64)> {
+        self.levels
+            .iter()
+            .map(|info| {
+                let total = match info.pack {
+                    Some(_) => info.entries.iter().map(|e| e.length as u64).sum(),
+                    None => 0,
+                };
+                (info.level, total)
+            })
+            .collect()
+    }
+
     pub fn read_tile_bytes(&self, tile_ref: PackTileRef) -> TileResult<Bytes> {
+        let buf = self.read_stored_bytes(tile_ref)?;
+        if tile_ref.zstd {
+            let raw = zstd::decode_all(buf.as_slice()).map_err(|e| {
+                TileError::Decode(format!(
+                    "Zstd tile at level {} offset {}: {}",
+                    tile_ref.level, tile_ref.offset, e
+                ))
+            })?;
+            return Ok(Bytes::from(raw));
+        }
+        Ok(Bytes::from(buf))
+    }
+
+    /// The tile's bytes as stored in the pack (still Zstd-wrapped if
+    /// flagged), checked against the index CRC.
+    fn read_stored_bytes(&self, tile_ref: PackTileRef) -> TileResult<Vec<u8>> {
         if tile_ref.length == 0 {
             return Err(TileError::Validation("zero-length tile".into()));
         }
@@ -202,6 +398,10 @@ impl TilePack {
             TileError::Validation(format!("Unknown level {}", tile_ref.level))
         })?;
 
+        let pack = level.pack.as_ref().ok_or_else(|| {
+            TileError::Validation(format!("Missing pack file for level {}", tile_ref.level))
+        })?;
+
         let end = tile_ref
             .offset
             .checked_add(tile_ref.length as u64)
@@ -212,22 +412,345 @@ impl TilePack {
             ));
         }
 
-        let mut buf = vec![0u8; tile_ref.length as usize];
-        read_at(&level.pack, tile_ref.offset, &mut buf)?;
-        Ok(Bytes::from(buf))
+        let buf = match pack {
+            PackData::File(file) => {
+                let mut buf = vec![0u8; tile_ref.length as usize];
+                read_at(file, tile_ref.offset, &mut buf)?;
+                buf
+            }
+            PackData::Mmap(map) => {
+                // The mapping is taken at open; re-check in case it is
+                // shorter than the recorded length.
+                let range = tile_ref.offset as usize..end as usize;
+                map.get(range)
+                    .ok_or_else(|| {
+                        TileError::Validation("tile byte range exceeds pack size".into())
+                    })?
+                    .to_vec()
+            }
+        };
+        if let Some(expected) = tile_ref.crc32 {
+            let actual = crc32fast::hash(&buf);
+            if actual != expected {
+                return Err(TileError::Validation(format!(
+                    "Checksum mismatch for level {} tile at offset {}: expected {:08x}, got {:08x}",
+                    tile_ref.level, tile_ref.offset, expected, actual
+                )));
+            }
+        }
+        Ok(buf)
+    }
+}
+
+/// Re-encode the JPEG tiles of a packed slide at `quality` (1-100),
+/// rewriting every level's `.pack` and `.idx` in place.
+///
+/// A tile keeps its original bytes when re-encoding would not shrink it, so
+/// repacking at a higher quality than the source never grows the slide.
+/// Non-JPEG tiles (including Zstd-wrapped ones) are copied unchanged. Levels
+/// whose pack file is missing are left alone. New files are written beside
+/// the old ones and renamed over them only once every level succeeded.
+///
+/// Returns `(bytes_before, bytes_after)` of stored tile data.
+pub fn repack_slide(fastpath_dir: &Path, quality: u8) -> TileResult<(u64, u64)> {
+    transcode_pack(fastpath_dir, ImageFormat::Jpeg, quality, None)
+}
+
+/// Re-encode the JPEG tiles of a packed slide as `format` (`quality` 1-100,
+/// JPEG only), rewriting every level's `.pack` and `.idx` in place.
+///
+/// As in `repack_slide`, a tile keeps its original bytes unless the new
+/// encoding is smaller, and ICC profiles are carried over. Empty entries
+/// stay empty and non-JPEG tiles are copied unchanged; the decoder sniffs
+/// each tile's format, so readers need no other change. WebP is rejected:
+/// its encoder is lossless only, so it would grow lossy JPEG tiles. Levels
+/// are transcoded in parallel and `progress_cb(done, total)` is called as
+/// each finishes.
+///
+/// Returns `(bytes_before, bytes_after)` of stored tile data.
+pub fn transcode_pack(
+    fastpath_dir: &Path,
+    format: ImageFormat,
+    quality: u8,
+    progress_cb: Option<Box<dyn Fn(u32, u32) + Send + Sync>>,
+) -> TileResult<(u64, u64)> {
+    if format == ImageFormat::WebP {
+        return Err(TileError::Validation(
+            "WebP transcoding is not supported: only lossless WebP can be encoded".into(),
+        ));
+    }
+    rewrite_jpeg_tiles(fastpath_dir, progress_cb.as_deref(), &move |original: &CompressedTileData| {
+        let recoded = transcode_tile(original, format, quality)?.jpeg_bytes;
+        Ok((recoded.len() < original.jpeg_bytes.len()).then_some(recoded))
+    })
+}
+
+/// Replacement bytes for a JPEG tile, or None to keep the original.
+type TileRecode = dyn Fn(&CompressedTileData) -> TileResult<Option<Bytes>> + Sync;
+
+/// Shared body of `repack_slide` and `transcode_pack`: rewrite each level
+/// with `recode` applied to its JPEG tiles. Levels whose pack file is
+/// missing are left alone. New files are written beside the old ones,
+/// checked to match each other, and renamed over them only once every level
+/// succeeded: each level's `.pack` first, then its `.idx`. A crash between
+/// the two is finished by the next `TilePack::open` (see
+/// `recover_level_idx`).
+fn rewrite_jpeg_tiles(
+    fastpath_dir: &Path,
+    progress_cb: Option<&(dyn Fn(u32, u32) + Send + Sync)>,
+    recode: &TileRecode,
+) -> TileResult<(u64, u64)> {
+    let pack = TilePack::open(fastpath_dir)?;
+    let tiles_dir = fastpath_dir.join("tiles");
+
+    let levels: Vec<&LevelPack> = pack.levels.iter().filter(|info| info.pack.is_some()).collect();
+    let total_levels = levels.len() as u32;
+    let completed = AtomicU32::new(0);
+    let written = levels
+        .par_iter()
+        .map(|info| {
+            let written = rewrite_level(&pack, info, &tiles_dir, recode)?;
+            let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+            if let Some(cb) = progress_cb {
+                cb(done, total_levels);
+            }
+            Ok(written)
+        })
+        .collect::<TileResult<Vec<(u32, u64)>>>();
+    let tmp_path = |level: u32, ext: &str| tiles_dir.join(format!("level_{level}.{ext}.tmp"));
+    let written = written.and_then(|written| {
+        for info in &levels {
+            let (idx, pack) = (tmp_path(info.level, "idx"), tmp_path(info.level, "pack"));
+            if index_fills_pack(info.level, &idx, &pack) != Some((info.cols, info.rows)) {
+                return Err(TileError::Validation(format!(
+                    "rewritten level {} index doesn't match its pack",
+                    info.level
+                )));
+            }
+        }
+        Ok(written)
+    });
+    let written = match written {
+        Ok(written) => written,
+        Err(e) => {
+            for info in &pack.levels {
+                let _ = std::fs::remove_file(tmp_path(info.level, "pack"));
+                let _ = std::fs::remove_file(tmp_path(info.level, "idx"));
+            }
+            return Err(e);
+        }
+    };
+
+    let before: u64 = pack.level_byte_sizes().iter().map(|&(_, bytes)| bytes).sum();
+    // Close the old packs before replacing them (required on Windows)
+    drop(pack);
+    for &(level, _) in &written {
+        for ext in ["pack", "idx"] {
+            let path = tiles_dir.join(format!("level_{level}.{ext}"));
+            std::fs::rename(tmp_path(level, ext), path)?;
+        }
+    }
+    Ok((before, written.iter().map(|&(_, bytes)| bytes).sum()))
+}
+
+/// Write `level_N.pack.tmp` + `level_N.idx.tmp` for `rewrite_jpeg_tiles`;
+/// returns the level and its stored tile bytes.
+fn rewrite_level(
+    pack: &TilePack,
+    info: &LevelPack,
+    tiles_dir: &Path,
+    recode: &TileRecode,
+) -> TileResult<(u32, u64)> {
+    let level = info.level;
+    let mut pack_writer =
+        BufWriter::new(File::create(tiles_dir.join(format!("level_{level}.pack.tmp")))?);
+    let mut idx_writer =
+        BufWriter::new(File::create(tiles_dir.join(format!("level_{level}.idx.tmp")))?);
+
+    idx_writer.write_all(LEVEL_MAGIC)?;
+    idx_writer.write_all(&LEVEL_VERSION.to_le_bytes())?;
+    idx_writer.write_all(&(info.cols as u16).to_le_bytes())?;
+    idx_writer.write_all(&(info.rows as u16).to_le_bytes())?;
+
+    let mut pack_offset: u64 = 0;
+    for row in 0..info.rows {
+        for col in 0..info.cols {
+            let Some(tile_ref) = pack.tile_ref(level, col, row) else {
+                write_level_entry(&mut idx_writer, 0, &[])?;
+                continue;
+            };
+            let mut data = Bytes::from(pack.read_stored_bytes(tile_ref)?);
+            if !tile_ref.zstd && data.starts_with(&[0xFF, 0xD8]) {
+                let (width, height) = read_tile_header(&data)?;
+                let original = CompressedTileData {
+                    jpeg_bytes: data.clone(),
+                    width,
+                    height,
+                };
+                if let Some(recoded) = recode(&original)? {
+                    data = recoded;
+                }
+            }
+
+            pack_writer.write_all(&data)?;
+            write_level_entry_with(&mut idx_writer, pack_offset, &data, tile_ref.zstd)?;
+            pack_offset += data.len() as u64;
+        }
     }
+
+    idx_writer.flush()?;
+    pack_writer.flush()?;
+    Ok((level, pack_offset))
+}
+
+/// Tiles whose content differs between two packed versions of a slide.
+///
+/// Compares every coordinate of every level in either pack: a tile present
+/// in only one counts as changed. Stored CRC32s are compared when both
+/// indexes have them (`FPLIDX2`); otherwise the tile bytes are read and
+/// compared. A tile that fails to read is reported as changed. Returns
+/// `(level, col, row)` in level, row, col order.
+pub fn diff_packs(old_dir: &Path, new_dir: &Path) -> TileResult<Vec<(u32, u32, u32)>> {
+    let old = TilePack::open(old_dir)?;
+    let new = TilePack::open(new_dir)?;
+
+    let mut grids: Vec<(u32, u32, u32)> = old
+        .levels
+        .iter()
+        .chain(&new.levels)
+        .map(|info| (info.level, info.cols, info.rows))
+        .collect();
+    grids.sort_unstable();
+    grids.dedup_by(|b, a| {
+        if a.0 != b.0 {
+            return false;
+        }
+        a.1 = a.1.max(b.1);
+        a.2 = a.2.max(b.2);
+        true
+    });
+
+    let coords: Vec<(u32, u32, u32)> = grids
+        .iter()
+        .flat_map(|&(level, cols, rows)| {
+            (0..rows).flat_map(move |row| (0..cols).map(move |col| (level, col, row)))
+        })
+        .collect();
+
+    Ok(coords
+        .into_par_iter()
+        .filter(|&(level, col, row)| {
+            match (old.tile_ref(level, col, row), new.tile_ref(level, col, row)) {
+                (None, None) => false,
+                (Some(a), Some(b)) if a.length != b.length => true,
+                (Some(a), Some(b)) => match (a.crc32, b.crc32) {
+                    (Some(crc_a), Some(crc_b)) => crc_a != crc_b,
+                    _ => match (old.read_tile_bytes(a), new.read_tile_bytes(b)) {
+                        (Ok(bytes_a), Ok(bytes_b)) => bytes_a != bytes_b,
+                        _ => true,
+                    },
+                },
+                _ => true,
+            }
+        })
+        .collect())
+}
+
+/// Integrity summary from [`validate_pack`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackValidation {
+    /// Levels with an index file.
+    pub levels: u32,
+    /// Grid entries across all levels, empty ones included.
+    pub total_tiles: u64,
+    /// Entries recorded as missing (zero length).
+    pub empty_tiles: u64,
+    /// `(level, col, row)` of tiles that failed to read or decode, in level,
+    /// row, col order.
+    pub corrupt_tiles: Vec<(u32, u32, u32)>,
+}
+
+/// Check that every non-empty tile of a packed slide reads (bounds, CRC32,
+/// Zstd) and has a decodable image header.
+///
+/// Levels are checked in parallel and every tile is visited: a bad tile is
+/// recorded, not returned as an error. Tiles of a level whose pack file is
+/// missing all count as corrupt. Only opening the pack can fail, which
+/// includes an index entry pointing past the end of its pack.
+pub fn validate_pack(fastpath_dir: &Path) -> TileResult<PackValidation> {
+    let pack = TilePack::open(fastpath_dir)?;
+
+    let per_level: Vec<PackValidation> = pack
+        .levels
+        .par_iter()
+        .map(|info| {
+            let mut summary = PackValidation {
+                levels: 1,
+                total_tiles: info.entries.len() as u64,
+                ..Default::default()
+            };
+            for (idx, entry) in info.entries.iter().enumerate() {
+                if entry.length == 0 {
+                    summary.empty_tiles += 1;
+                    continue;
+                }
+                let tile_ref = PackTileRef {
+                    level: info.level,
+                    offset: entry.offset,
+                    length: entry.length,
+                    crc32: entry.crc32,
+                    zstd: entry.zstd,
+                };
+                let valid = pack
+                    .read_tile_bytes(tile_ref)
+                    .and_then(|bytes| read_tile_header(&bytes))
+                    .is_ok();
+                if !valid {
+                    let (col, row) = (idx as u32 % info.cols, idx as u32 / info.cols);
+                    summary.corrupt_tiles.push((info.level, col, row));
+                }
+            }
+            summary
+        })
+        .collect();
+
+    // Levels are sorted and each level's tiles were visited row by row
+    Ok(per_level.into_iter().fold(PackValidation::default(), |mut acc, level| {
+        acc.levels += level.levels;
+        acc.total_tiles += level.total_tiles;
+        acc.empty_tiles += level.empty_tiles;
+        acc.corrupt_tiles.extend(level.corrupt_tiles);
+        acc
+    }))
 }
 
 /// Pack dzsave output (tiles_files) into per-level tiles/level_N.pack + level_N.idx
 /// and remove dzsave files.
 ///
 /// The dzsave layout is expected to be:
-/// `fastpath_dir/tiles_files/<level>/<col>_<row>.jpg` (or `.jpeg`).
+/// `fastpath_dir/tiles_files/<level>/<col>_<row>.jpg` (or `.jpeg`, `.png`).
+/// If a `metadata.json` is already present, its `level_dir_names` rename
+/// levels.
+///
+/// Missing tiles are written as zero-length entries. Tiles past a level's
+/// declared `cols`/`rows` (converter rounding) grow the packed grid instead
+/// of being dropped, with a warning; `SlideMetadata::reconcile_grid` picks
+/// the larger grid up at load.
+///
+/// With `compression`, each non-JPEG tile (e.g. a PNG mask) is stored
+/// Zstd-wrapped when that makes it smaller, and flagged in the index so
+/// `TilePack::read_tile_bytes` decompresses it. JPEG tiles are always stored
+/// raw: they don't compress further and their decode cost stays unchanged.
 ///
-/// Missing tiles are written as zero-length entries.
+/// Each level is written to `level_N.pack.tmp` / `.idx.tmp` and renamed into
+/// place when complete. Levels whose final files already exist and check out
+/// (see `packed_level_is_complete`) are skipped, so re-running after an
+/// interrupted pack resumes where it stopped. dzsave files are only removed
+/// once every level is packed.
 pub fn pack_dzsave_tiles(
     fastpath_dir: &Path,
     levels: &[(u32, u32, u32)],
+    compression: Option<ZstdLevel>,
     progress_cb: Option<Box<dyn Fn(u32, u32) + Send + Sync>>,
 ) -> TileResult<()> {
     let tiles_dir = fastpath_dir.join("tiles_files");
@@ -238,6 +761,7 @@ pub fn pack_dzsave_tiles(
         )));
     }
 
+    let level_dir_names = read_level_dir_names(fastpath_dir)?;
     let out_dir = fastpath_dir.join("tiles");
     std::fs::create_dir_all(&out_dir)?;
 
@@ -245,7 +769,21 @@ pub fn pack_dzsave_tiles(
     let completed = AtomicU32::new(0);
 
     levels.par_iter().try_for_each(|(level, cols, rows)| -> TileResult<()> {
-        let level_dir = tiles_dir.join(level.to_string());
+        let pack_path = out_dir.join(format!("level_{}.pack", level));
+        let idx_path = out_dir.join(format!("level_{}.idx", level));
+        let report_done = || {
+            let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+            if let Some(ref cb) = progress_cb {
+                cb(done, total_levels);
+            }
+        };
+        if packed_level_is_complete(*level, &idx_path, &pack_path, *cols, *rows) {
+            info!("level {} already packed, skipping", level);
+            report_done();
+            return Ok(());
+        }
+
+        let level_dir = tiles_dir.join(level_dir_name(&level_dir_names, *level));
         if !level_dir.exists() {
             return Err(TileError::Validation(format!(
                 "Missing level directory: {}",
@@ -253,13 +791,6 @@ pub fn pack_dzsave_tiles(
             )));
         }
 
-        let cols_u16 = u16::try_from(*cols).map_err(|_| {
-            TileError::Validation(format!("level {} cols exceeds u16: {}", level, cols))
-        })?;
-        let rows_u16 = u16::try_from(*rows).map_err(|_| {
-            TileError::Validation(format!("level {} rows exceeds u16: {}", level, rows))
-        })?;
-
         // One readdir per level instead of 2 * cols * rows stat calls
         let mut tile_files: HashMap<String, std::path::PathBuf> = HashMap::new();
         for entry in std::fs::read_dir(&level_dir)? {
@@ -268,18 +799,41 @@ pub fn pack_dzsave_tiles(
             let name_str = name.to_string_lossy();
             if let Some(stem) = name_str.strip_suffix(".jpg")
                 .or_else(|| name_str.strip_suffix(".jpeg"))
+                .or_else(|| name_str.strip_suffix(".png"))
             {
                 tile_files.insert(stem.to_string(), entry.path());
             }
         }
 
-        let pack_path = out_dir.join(format!("level_{}.pack", level));
-        let idx_path = out_dir.join(format!("level_{}.idx", level));
+        let (cols, rows) = match max_tile_coord(tile_files.keys()) {
+            Some((max_col, max_row)) if max_col >= *cols || max_row >= *rows => {
+                let grown = ((*cols).max(max_col + 1), (*rows).max(max_row + 1));
+                warn!(
+                    "{}: level {} has tiles outside its declared {}x{} grid; packing {}x{}",
+                    level_dir.display(),
+                    level,
+                    cols,
+                    rows,
+                    grown.0,
+                    grown.1
+                );
+                grown
+            }
+            _ => (*cols, *rows),
+        };
+        let cols_u16 = u16::try_from(cols).map_err(|_| {
+            TileError::Validation(format!("level {} cols exceeds u16: {}", level, cols))
+        })?;
+        let rows_u16 = u16::try_from(rows).map_err(|_| {
+            TileError::Validation(format!("level {} rows exceeds u16: {}", level, rows))
+        })?;
 
-        let pack_file = File::create(&pack_path)?;
-        let idx_file = File::create(&idx_path)?;
-        let mut pack_writer = BufWriter::new(pack_file);
-        let mut idx_writer = BufWriter::new(idx_file);
+        // Written beside the final files and renamed once complete, so an
+        // interrupted run never leaves a half-written level that looks packed
+        let pack_tmp = out_dir.join(format!("level_{}.pack.tmp", level));
+        let idx_tmp = out_dir.join(format!("level_{}.idx.tmp", level));
+        let mut pack_writer = BufWriter::new(File::create(&pack_tmp)?);
+        let mut idx_writer = BufWriter::new(File::create(&idx_tmp)?);
 
         idx_writer.write_all(LEVEL_MAGIC)?;
         idx_writer.write_all(&LEVEL_VERSION.to_le_bytes())?;
@@ -287,30 +841,37 @@ pub fn pack_dzsave_tiles(
         idx_writer.write_all(&rows_u16.to_le_bytes())?;
 
         let mut pack_offset: u64 = 0;
-        for row in 0..*rows {
-            for col in 0..*cols {
+        for row in 0..rows {
+            for col in 0..cols {
                 let key = format!("{}_{}", col, row);
                 let tile_path = tile_files.get(&key);
 
                 let Some(tile_path) = tile_path else {
-                    idx_writer.write_all(&0u64.to_le_bytes())?;
-                    idx_writer.write_all(&0u32.to_le_bytes())?;
+                    write_level_entry(&mut idx_writer, 0, &[])?;
                     continue;
                 };
 
                 let data = std::fs::read(tile_path)?;
-                let length: u32 = data.len().try_into().map_err(|_e| {
-                    TileError::Validation(format!(
-                        "Tile too large to pack ({} bytes): {}",
-                        data.len(),
-                        tile_path.display()
-                    ))
-                })?;
+                let (data, zstd) = match compression {
+                    Some(level) => compress_tile(data, level)?,
+                    None => (data, false),
+                };
+                let length: u32 = data
+                    .len()
+                    .try_into()
+                    .ok()
+                    .filter(|&len| len & ZSTD_FLAG == 0)
+                    .ok_or_else(|| {
+                        TileError::Validation(format!(
+                            "Tile too large to pack ({} bytes): {}",
+                            data.len(),
+                            tile_path.display()
+                        ))
+                    })?;
 
                 pack_writer.write_all(&data)?;
 
-                idx_writer.write_all(&pack_offset.to_le_bytes())?;
-                idx_writer.write_all(&length.to_le_bytes())?;
+                write_level_entry_with(&mut idx_writer, pack_offset, &data, zstd)?;
 
                 pack_offset = pack_offset
                     .checked_add(length as u64)
@@ -318,17 +879,17 @@ pub fn pack_dzsave_tiles(
             }
         }
 
-        idx_writer.flush()?;
-        pack_writer.flush()?;
+        pack_writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        idx_writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        // The index goes last: its presence marks the level as packed
+        std::fs::rename(&pack_tmp, &pack_path)?;
+        std::fs::rename(&idx_tmp, &idx_path)?;
 
-        let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
-        if let Some(ref cb) = progress_cb {
-            cb(done, total_levels);
-        }
+        report_done();
         Ok(())
     })?;
 
-    // Clean up dzsave output to save disk space.
+    // Every level is packed; clean up dzsave output to save disk space.
     std::fs::remove_dir_all(&tiles_dir)?;
     let dzi_path = fastpath_dir.join("tiles.dzi");
     if dzi_path.exists() {
@@ -338,6 +899,88 @@ pub fn pack_dzsave_tiles(
     Ok(())
 }
 
+/// Whether a previous `pack_dzsave_tiles` run finished this level: the
+/// index parses with at least the expected grid (more if it was grown to
+/// fit stray tiles) and its tiles exactly fill the pack.
+fn packed_level_is_complete(
+    level: u32,
+    idx_path: &Path,
+    pack_path: &Path,
+    cols: u32,
+    rows: u32,
+) -> bool {
+    index_fills_pack(level, idx_path, pack_path)
+        .is_some_and(|(idx_cols, idx_rows)| idx_cols >= cols && idx_rows >= rows)
+}
+
+/// Largest column and row among dzsave tile stems (`<col>_<row>`), if any.
+fn max_tile_coord<'a>(stems: impl Iterator<Item = &'a String>) -> Option<(u32, u32)> {
+    stems
+        .filter_map(|stem| {
+            let (col, row) = stem.split_once('_')?;
+            Some((col.parse::<u32>().ok()?, row.parse::<u32>().ok()?))
+        })
+        .reduce(|(max_col, max_row), (col, row)| (max_col.max(col), max_row.max(row)))
+}
+
+/// The (cols, rows) grid recorded in a `level_N.idx` header.
+///
+/// Reads only the header, so it is cheap enough to run on every load.
+pub fn read_level_grid(idx_path: &Path) -> TileResult<(u32, u32)> {
+    let mut header = [0u8; LEVEL_HEADER_SIZE];
+    File::open(idx_path)?.read_exact(&mut header)?;
+    let magic = &header[0..8];
+    if magic != LEVEL_MAGIC && magic != LEVEL_MAGIC_V1 {
+        return Err(TileError::Validation(format!(
+            "{} magic mismatch",
+            idx_path.display()
+        )));
+    }
+    let cols = u16::from_le_bytes(header[12..14].try_into().unwrap()) as u32;
+    let rows = u16::from_le_bytes(header[14..16].try_into().unwrap()) as u32;
+    Ok((cols, rows))
+}
+
+/// The index's (cols, rows) if it parses and its tiles exactly fill the
+/// pack, as every pack writer here lays them out.
+fn index_fills_pack(level: u32, idx_path: &Path, pack_path: &Path) -> Option<(u32, u32)> {
+    let idx_bytes = std::fs::read(idx_path).ok()?;
+    let pack_len = std::fs::metadata(pack_path).ok()?.len();
+    let info = LevelPack::parse(level, &idx_bytes, None, pack_len).ok()?;
+    let end = info
+        .entries
+        .iter()
+        .map(|e| e.offset.saturating_add(e.length as u64))
+        .max()
+        .unwrap_or(0);
+    (end == info.pack_len).then_some((info.cols, info.rows))
+}
+
+/// Index to read for `level`: `idx_path`, unless a `rewrite_jpeg_tiles`
+/// was interrupted after renaming the level's new `.pack` into place but
+/// before its `.idx`. Then `level_N.idx.tmp` (with no `.pack.tmp` left)
+/// matches the pack and is renamed over the stale index, or read directly
+/// if the directory isn't writable.
+fn recover_level_idx(tiles_dir: &Path, level: u32, idx_path: PathBuf) -> PathBuf {
+    let idx_tmp = tiles_dir.join(format!("level_{level}.idx.tmp"));
+    let pack_path = tiles_dir.join(format!("level_{level}.pack"));
+    if !idx_tmp.is_file()
+        || tiles_dir.join(format!("level_{level}.pack.tmp")).exists()
+        || index_fills_pack(level, &idx_tmp, &pack_path).is_none()
+    {
+        return idx_path;
+    }
+    warn!(
+        "{}: finishing an interrupted rewrite of level {}",
+        tiles_dir.display(),
+        level
+    );
+    match std::fs::rename(&idx_tmp, &idx_path) {
+        Ok(()) => idx_path,
+        Err(_) => idx_tmp,
+    }
+}
+
 /// Old sequential packing with per-tile stat calls (for benchmarking only).
 /// Does NOT remove tiles_files or tiles.dzi (caller handles cleanup).
 pub fn pack_dzsave_tiles_bench_seq_stat(
@@ -383,8 +1026,7 @@ pub fn pack_dzsave_tiles_bench_seq_stat(
                 };
 
                 let Some(tile_path) = tile_path else {
-                    idx_writer.write_all(&0u64.to_le_bytes())?;
-                    idx_writer.write_all(&0u32.to_le_bytes())?;
+                    write_level_entry(&mut idx_writer, 0, &[])?;
                     continue;
                 };
 
@@ -398,8 +1040,7 @@ pub fn pack_dzsave_tiles_bench_seq_stat(
                 })?;
 
                 pack_writer.write_all(&data)?;
-                idx_writer.write_all(&pack_offset.to_le_bytes())?;
-                idx_writer.write_all(&length.to_le_bytes())?;
+                write_level_entry(&mut idx_writer, pack_offset, &data)?;
 
                 pack_offset = pack_offset
                     .checked_add(length as u64)
@@ -464,8 +1105,7 @@ pub fn pack_dzsave_tiles_bench_seq_prescan(
                 let tile_path = tile_files.get(&key);
 
                 let Some(tile_path) = tile_path else {
-                    idx_writer.write_all(&0u64.to_le_bytes())?;
-                    idx_writer.write_all(&0u32.to_le_bytes())?;
+                    write_level_entry(&mut idx_writer, 0, &[])?;
                     continue;
                 };
 
@@ -479,8 +1119,7 @@ pub fn pack_dzsave_tiles_bench_seq_prescan(
                 })?;
 
                 pack_writer.write_all(&data)?;
-                idx_writer.write_all(&pack_offset.to_le_bytes())?;
-                idx_writer.write_all(&length.to_le_bytes())?;
+                write_level_entry(&mut idx_writer, pack_offset, &data)?;
 
                 pack_offset = pack_offset
                     .checked_add(length as u64)
@@ -543,8 +1182,7 @@ pub fn pack_dzsave_tiles_bench_parallel(
                 let tile_path = tile_files.get(&key);
 
                 let Some(tile_path) = tile_path else {
-                    idx_writer.write_all(&0u64.to_le_bytes())?;
-                    idx_writer.write_all(&0u32.to_le_bytes())?;
+                    write_level_entry(&mut idx_writer, 0, &[])?;
                     continue;
                 };
 
@@ -558,8 +1196,7 @@ pub fn pack_dzsave_tiles_bench_parallel(
                 })?;
 
                 pack_writer.write_all(&data)?;
-                idx_writer.write_all(&pack_offset.to_le_bytes())?;
-                idx_writer.write_all(&length.to_le_bytes())?;
+                write_level_entry(&mut idx_writer, pack_offset, &data)?;
 
                 pack_offset = pack_offset
                     .checked_add(length as u64)
@@ -575,6 +1212,43 @@ pub fn pack_dzsave_tiles_bench_parallel(
     Ok(())
 }
 
+/// Append one index entry; an empty `data` marks a missing tile.
+fn write_level_entry(w: &mut impl Write, offset: u64, data: &[u8]) -> std::io::Result<()> {
+    write_level_entry_with(w, offset, data, false)
+}
+
+/// `write_level_entry` for stored bytes that may be Zstd-wrapped.
+fn write_level_entry_with(
+    w: &mut impl Write,
+    offset: u64,
+    data: &[u8],
+    zstd: bool,
+) -> std::io::Result<()> {
+    let (offset, crc32) = if data.is_empty() {
+        (0, 0)
+    } else {
+        (offset, crc32fast::hash(data))
+    };
+    let flag = if zstd { ZSTD_FLAG } else { 0 };
+    w.write_all(&offset.to_le_bytes())?;
+    w.write_all(&(data.len() as u32 | flag).to_le_bytes())?;
+    w.write_all(&crc32.to_le_bytes())
+}
+
+/// Bytes to store for one tile: Zstd-wrapped (flag set) when it is not a
+/// JPEG and compression actually shrinks it, otherwise the original bytes.
+fn compress_tile(data: Vec<u8>, level: ZstdLevel) -> TileResult<(Vec<u8>, bool)> {
+    if data.starts_with(&[0xFF, 0xD8]) {
+        return Ok((data, false));
+    }
+    let compressed = zstd::bulk::compress(&data, level.0)?;
+    if compressed.len() < data.len() {
+        Ok((compressed, true))
+    } else {
+        Ok((data, false))
+    }
+}
+
 #[cfg(windows)]
 fn read_at(file: &File, offset: u64, buf: &mut [u8]) -> std::io::Result<()> {
     use std::os::windows::fs::FileExt;
@@ -592,10 +1266,13 @@ fn read_at(file: &File, offset: u64, buf: &mut [u8]) -> std::io::Result<()> {
 #[cfg(test)]
 mod tests {
     use std::fs;
+    use std::sync::Arc;
 
     use tempfile::TempDir;
 
     use super::*;
+    use crate::decoder::read_icc_profile;
+    use crate::imaging::{encode_rgb, encode_rgb_with_icc};
     use crate::test_utils::test_jpeg_bytes;
 
     #[test]
@@ -616,7 +1293,7 @@ mod tests {
 
         fs::write(dir.join("tiles.dzi"), b"dummy").unwrap();
 
-        pack_dzsave_tiles(dir, &[(0, 2, 1), (1, 1, 1)], None).unwrap();
+        pack_dzsave_tiles(dir, &[(0, 2, 1), (1, 1, 1)], None, None).unwrap();
 
         assert!(!tiles_dir.exists(), "tiles_files should be removed");
         assert!(!dir.join("tiles.dzi").exists(), "tiles.dzi should be removed");
@@ -641,7 +1318,523 @@ mod tests {
         assert_eq!(b1.as_ref(), jpeg.as_slice());
     }
 
+    #[test]
+    fn test_pack_dzsave_tiles_resumes_completed_levels() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let tiles_dir = dir.join("tiles_files");
+        let write_level = |level: &str, data: &[u8]| {
+            fs::create_dir_all(tiles_dir.join(level)).unwrap();
+            fs::write(tiles_dir.join(level).join("0_0.jpg"), data).unwrap();
+        };
+        let jpeg = test_jpeg_bytes();
+        let read = |level: u32| {
+            let pack = TilePack::open(dir).unwrap();
+            pack.read_tile_bytes(pack.tile_ref(level, 0, 0).unwrap()).unwrap()
+        };
+
+        // A run that got through level 0 only
+        write_level("0", &jpeg);
+        pack_dzsave_tiles(dir, &[(0, 1, 1)], None, None).unwrap();
+
+        // Re-running with both levels packs level 1 and leaves level 0 alone
+        let mut changed = jpeg.clone();
+        changed.push(0);
+        write_level("0", &changed);
+        write_level("1", &jpeg);
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&calls);
+        let cb = Box::new(move |_: u32, _: u32| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        pack_dzsave_tiles(dir, &[(0, 1, 1), (1, 1, 1)], None, Some(cb)).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        assert_eq!(read(0).as_ref(), jpeg.as_slice());
+        assert_eq!(read(1).as_ref(), jpeg.as_slice());
+        assert!(!tiles_dir.exists());
+        let leftovers: Vec<_> = fs::read_dir(dir.join("tiles"))
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+
+        // A truncated pack doesn't count as done and is packed again
+        let pack_path = dir.join("tiles").join("level_0.pack");
+        File::options().write(true).open(&pack_path).unwrap().set_len(1).unwrap();
+        write_level("0", &changed);
+        pack_dzsave_tiles(dir, &[(0, 1, 1)], None, None).unwrap();
+        assert_eq!(read(0).as_ref(), changed.as_slice());
+    }
+
+    #[test]
+    fn test_pack_dzsave_tiles_grows_grid_to_fit_stray_tiles() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let level_dir = dir.join("tiles_files").join("0");
+        fs::create_dir_all(&level_dir).unwrap();
+        let jpeg = test_jpeg_bytes();
+        // Declared 1x1, but dzsave wrote a column and a row past it
+        for name in ["0_0.jpg", "1_0.jpg", "0_2.jpeg"] {
+            fs::write(level_dir.join(name), &jpeg).unwrap();
+        }
+
+        pack_dzsave_tiles(dir, &[(0, 1, 1)], None, None).unwrap();
+        let idx_path = dir.join("tiles").join("level_0.idx");
+        assert_eq!(read_level_grid(&idx_path).unwrap(), (2, 3));
+        let pack = TilePack::open(dir).unwrap();
+        for (col, row) in [(0, 0), (1, 0), (0, 2)] {
+            let bytes = pack.read_tile_bytes(pack.tile_ref(0, col, row).unwrap()).unwrap();
+            assert_eq!(bytes.as_ref(), jpeg.as_slice());
+        }
+        assert!(pack.tile_ref(0, 1, 1).is_none());
+
+        // The grown level counts as packed when the same grid is requested again
+        assert!(packed_level_is_complete(0, &idx_path, &dir.join("tiles/level_0.pack"), 1, 1));
+    }
+
+    #[test]
+    fn test_level_byte_sizes() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+
+        let tiles_dir = dir.join("tiles_files");
+        fs::create_dir_all(tiles_dir.join("0")).unwrap();
+        fs::create_dir_all(tiles_dir.join("1")).unwrap();
+
+        fs::write(tiles_dir.join("0").join("0_0.jpg"), vec![1u8; 100]).unwrap();
+        fs::write(tiles_dir.join("1").join("0_0.jpg"), vec![2u8; 300]).unwrap();
+        fs::write(tiles_dir.join("1").join("1_0.jpg"), vec![3u8; 45]).unwrap();
+        // 1/0_1.jpg and 1/1_1.jpg missing: zero-length entries add nothing
+
+        pack_dzsave_tiles(dir, &[(0, 1, 1), (1, 2, 2)], None, None).unwrap();
+
+        let pack = TilePack::open(dir).unwrap();
+        assert_eq!(pack.level_byte_sizes(), vec![(0, 100), (1, 345)]);
+    }
+
+    #[test]
+    fn test_pack_custom_level_dir_names() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+
+        let tiles_dir = dir.join("tiles_files");
+        fs::create_dir_all(tiles_dir.join("ds2")).unwrap();
+        fs::create_dir_all(tiles_dir.join("ds1")).unwrap();
+        fs::write(tiles_dir.join("ds2").join("0_0.jpg"), vec![1u8; 10]).unwrap();
+        fs::write(tiles_dir.join("ds1").join("1_0.jpg"), vec![2u8; 20]).unwrap();
+        fs::write(
+            dir.join("metadata.json"),
+            r#"{"level_dir_names": {"0": "ds2", "1": "ds1"}}"#,
+        )
+        .unwrap();
+
+        pack_dzsave_tiles(dir, &[(0, 1, 1), (1, 2, 1)], None, None).unwrap();
+
+        let pack = TilePack::open(dir).unwrap();
+        let t0 = pack.tile_ref(0, 0, 0).unwrap();
+        assert_eq!(pack.read_tile_bytes(t0).unwrap().as_ref(), &[1u8; 10]);
+        let t1 = pack.tile_ref(1, 1, 0).unwrap();
+        assert_eq!(pack.read_tile_bytes(t1).unwrap().as_ref(), &[2u8; 20]);
+    }
+
+    #[test]
+    fn test_diff_packs_reports_changed_tiles() {
+        let make = |tiles: &[(u32, u32, u32, u8)]| {
+            let temp = TempDir::new().unwrap();
+            for &(level, col, row, fill) in tiles {
+                let level_dir = temp.path().join("tiles_files").join(level.to_string());
+                fs::create_dir_all(&level_dir).unwrap();
+                fs::write(level_dir.join(format!("{col}_{row}.jpg")), vec![fill; 50]).unwrap();
+            }
+            pack_dzsave_tiles(temp.path(), &[(0, 1, 1), (1, 2, 2)], None, None).unwrap();
+            temp
+        };
+
+        let old = make(&[(0, 0, 0, 1), (1, 0, 0, 2), (1, 1, 0, 3), (1, 0, 1, 4)]);
+        // Level 1: (1,0) re-scanned, (0,1) dropped, (1,1) added
+        let new = make(&[(0, 0, 0, 1), (1, 0, 0, 2), (1, 1, 0, 9), (1, 1, 1, 5)]);
+
+        assert!(diff_packs(old.path(), old.path()).unwrap().is_empty());
+        assert_eq!(
+            diff_packs(old.path(), new.path()).unwrap(),
+            vec![(1, 1, 0), (1, 0, 1), (1, 1, 1)]
+        );
+    }
+
+    #[test]
+    fn test_missing_level_pack_is_unavailable() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+
+        let tiles_dir = dir.join("tiles_files");
+        fs::create_dir_all(tiles_dir.join("0")).unwrap();
+        fs::create_dir_all(tiles_dir.join("1")).unwrap();
+
+        let jpeg = test_jpeg_bytes();
+        fs::write(tiles_dir.join("0").join("0_0.jpg"), &jpeg).unwrap();
+        fs::write(tiles_dir.join("1").join("0_0.jpg"), &jpeg).unwrap();
+
+        pack_dzsave_tiles(dir, &[(0, 1, 1), (1, 1, 1)], None, None).unwrap();
+        fs::remove_file(dir.join("tiles").join("level_1.pack")).unwrap();
+
+        let pack = TilePack::open(dir).unwrap();
+
+        let t0 = pack.tile_ref(0, 0, 0).unwrap();
+        assert_eq!(pack.read_tile_bytes(t0).unwrap().as_ref(), jpeg.as_slice());
+
+        assert!(pack.tile_ref(1, 0, 0).is_none());
+        assert_eq!(pack.level_byte_sizes(), vec![(0, jpeg.len() as u64), (1, 0)]);
+    }
+
+    #[test]
+    fn test_corrupted_tile_fails_checksum() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+
+        let level_dir = dir.join("tiles_files").join("0");
+        fs::create_dir_all(&level_dir).unwrap();
+        let jpeg = test_jpeg_bytes();
+        fs::write(level_dir.join("0_0.jpg"), &jpeg).unwrap();
+        fs::write(level_dir.join("1_0.jpg"), &jpeg).unwrap();
+        pack_dzsave_tiles(dir, &[(0, 2, 1)], None, None).unwrap();
+
+        let idx = fs::read(dir.join("tiles").join("level_0.idx")).unwrap();
+        assert_eq!(&idx[0..8], LEVEL_MAGIC);
+
+        // Flip one byte inside the second tile
+        let pack_path = dir.join("tiles").join("level_0.pack");
+        let mut pack_bytes = fs::read(&pack_path).unwrap();
+        pack_bytes[jpeg.len() + 10] ^= 0xFF;
+        fs::write(&pack_path, pack_bytes).unwrap();
+
+        let pack = TilePack::open(dir).unwrap();
+        let good = pack.tile_ref(0, 0, 0).unwrap();
+        assert_eq!(good.crc32, Some(crc32fast::hash(&jpeg)));
+        assert_eq!(pack.read_tile_bytes(good).unwrap().as_ref(), jpeg.as_slice());
+
+        let bad = pack.tile_ref(0, 1, 0).unwrap();
+        let err = pack.read_tile_bytes(bad).unwrap_err().to_string();
+        assert!(err.contains("Checksum mismatch"), "{err}");
+        assert!(err.contains("level 0"), "{err}");
+        assert!(err.contains(&format!("offset {}", jpeg.len())), "{err}");
+    }
+
+    #[test]
+    fn test_v1_index_opens_without_checksums() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let tiles_dir = dir.join("tiles");
+        fs::create_dir_all(&tiles_dir).unwrap();
+
+        let jpeg = test_jpeg_bytes();
+        fs::write(tiles_dir.join("level_0.pack"), &jpeg).unwrap();
+        let mut idx = Vec::new();
+        idx.extend_from_slice(LEVEL_MAGIC_V1);
+        idx.extend_from_slice(&LEVEL_VERSION_V1.to_le_bytes());
+        idx.extend_from_slice(&1u16.to_le_bytes());
+        idx.extend_from_slice(&1u16.to_le_bytes());
+        idx.extend_from_slice(&0u64.to_le_bytes());
+        idx.extend_from_slice(&(jpeg.len() as u32).to_le_bytes());
+        fs::write(tiles_dir.join("level_0.idx"), idx).unwrap();
+
+        let pack = TilePack::open(dir).unwrap();
+        let tile_ref = pack.tile_ref(0, 0, 0).unwrap();
+        assert_eq!(tile_ref.crc32, None);
+        assert_eq!(pack.read_tile_bytes(tile_ref).unwrap().as_ref(), jpeg.as_slice());
+    }
+
+    #[test]
+    fn test_level_tiles_by_offset_follows_pack_order() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let tiles_dir = dir.join("tiles");
+        fs::create_dir_all(&tiles_dir).unwrap();
+
+        // 3x1 grid stored back to front, with (1, 0) missing
+        let (a, c) = (b"tile a".to_vec(), b"tile c!".to_vec());
+        fs::write(tiles_dir.join("level_0.pack"), [c.clone(), a.clone()].concat()).unwrap();
+        let mut idx = Vec::new();
+        idx.extend_from_slice(LEVEL_MAGIC);
+        idx.extend_from_slice(&LEVEL_VERSION.to_le_bytes());
+        idx.extend_from_slice(&3u16.to_le_bytes());
+        idx.extend_from_slice(&1u16.to_le_bytes());
+        write_level_entry(&mut idx, c.len() as u64, &a).unwrap();
+        write_level_entry(&mut idx, 0, &[]).unwrap();
+        write_level_entry(&mut idx, 0, &c).unwrap();
+        fs::write(tiles_dir.join("level_0.idx"), idx).unwrap();
+
+        let pack = TilePack::open(dir).unwrap();
+        let tiles = pack.level_tiles_by_offset(0).unwrap();
+        let order: Vec<_> = tiles.iter().map(|&(col, row, _)| (col, row)).collect();
+        assert_eq!(order, [(2, 0), (0, 0)]);
+        let bytes: Vec<_> = tiles
+            .into_iter()
+            .map(|(_, _, tile_ref)| pack.read_tile_bytes(tile_ref).unwrap())
+            .collect();
+        assert_eq!(bytes, [c, a]);
+
+        assert!(pack.level_tiles_by_offset(1).is_none());
+    }
+
+    #[test]
+    fn test_zstd_compresses_non_jpeg_tiles_only() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+
+        let level_dir = dir.join("tiles_files").join("0");
+        fs::create_dir_all(&level_dir).unwrap();
+        let jpeg = test_jpeg_bytes();
+        let mask = [vec![0u8; 2048], vec![255u8; 2048]].concat();
+        let noise: Vec<u8> = (0..64u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+        fs::write(level_dir.join("0_0.jpg"), &jpeg).unwrap();
+        fs::write(level_dir.join("1_0.png"), &mask).unwrap();
+        fs::write(level_dir.join("2_0.png"), &noise).unwrap();
+        pack_dzsave_tiles(dir, &[(0, 3, 1)], Some(ZstdLevel::new(3).unwrap()), None).unwrap();
+
+        let pack = TilePack::open(dir).unwrap();
+        let refs: Vec<_> = (0..3).map(|col| pack.tile_ref(0, col, 0).unwrap()).collect();
+        // JPEG stays raw; the mask shrinks; incompressible bytes stay raw
+        assert_eq!(refs.iter().map(|r| r.zstd).collect::<Vec<_>>(), [false, true, false]);
+        assert!((refs[1].length as usize) < mask.len());
+        assert_eq!(refs[2].length as usize, noise.len());
+
+        for (tile_ref, expected) in refs.into_iter().zip([&jpeg, &mask, &noise]) {
+            assert_eq!(pack.read_tile_bytes(tile_ref).unwrap().as_ref(), expected.as_slice());
+        }
+
+        assert!(ZstdLevel::new(0).is_err());
+        assert!(ZstdLevel::new(23).is_err());
+    }
+
+    #[test]
+    fn test_repack_slide_shrinks_jpegs_and_keeps_other_tiles() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+
+        let level_dir = dir.join("tiles_files").join("0");
+        fs::create_dir_all(&level_dir).unwrap();
+        let noise: Vec<u8> = (0..64 * 64 * 3u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+        let icc = vec![7u8; 300];
+        let jpeg =
+            encode_rgb_with_icc(&noise, 64, 64, ImageFormat::Jpeg, 100, Some(&icc)).unwrap();
+        let mask = [vec![0u8; 2048], vec![255u8; 2048]].concat();
+        fs::write(level_dir.join("0_0.jpg"), &jpeg).unwrap();
+        fs::write(level_dir.join("1_0.png"), &mask).unwrap();
+        pack_dzsave_tiles(dir, &[(0, 3, 1)], Some(ZstdLevel::new(3).unwrap()), None).unwrap();
+        let mask_ref = TilePack::open(dir).unwrap().tile_ref(0, 1, 0).unwrap();
+
+        let (before, after) = repack_slide(dir, 30).unwrap();
+        assert!(after < before);
+        let pack = TilePack::open(dir).unwrap();
+        let tile_ref = pack.tile_ref(0, 0, 0).unwrap();
+        assert!((tile_ref.length as usize) < jpeg.len());
+        let bytes = pack.read_tile_bytes(tile_ref).unwrap();
+        assert_eq!(read_tile_header(&bytes).unwrap(), (64, 64));
+        assert_eq!(read_icc_profile(&bytes).unwrap(), Some(icc));
+
+        // The Zstd-wrapped mask is copied as stored; the gap stays a gap
+        let repacked_mask = pack.tile_ref(0, 1, 0).unwrap();
+        assert!(repacked_mask.zstd);
+        assert_eq!((repacked_mask.length, repacked_mask.crc32), (mask_ref.length, mask_ref.crc32));
+        assert_eq!(pack.read_tile_bytes(repacked_mask).unwrap().as_ref(), mask.as_slice());
+        assert!(pack.tile_ref(0, 2, 0).is_none());
+        drop(pack);
+
+        // A higher quality than the tiles already have doesn't grow them
+        assert_eq!(repack_slide(dir, 100).unwrap(), (after, after));
+        let leftovers: Vec<_> = fs::read_dir(dir.join("tiles"))
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().path().extension().unwrap() == "tmp")
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    #[test]
+    fn test_open_finishes_interrupted_rewrite() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+
+        let level_dir = dir.join("tiles_files").join("0");
+        fs::create_dir_all(&level_dir).unwrap();
+        let noise: Vec<u8> = (0..64 * 64 * 3u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+        let jpeg = encode_rgb(&noise, 64, 64, ImageFormat::Jpeg, 100).unwrap();
+        fs::write(level_dir.join("0_0.jpg"), &jpeg).unwrap();
+        pack_dzsave_tiles(dir, &[(0, 1, 1)], None, None).unwrap();
+        let idx_path = dir.join("tiles").join("level_0.idx");
+        let old_idx = fs::read(&idx_path).unwrap();
+        repack_slide(dir, 30).unwrap();
+
+        // New pack in place, new index still at .tmp, old index at .idx
+        let idx_tmp = dir.join("tiles").join("level_0.idx.tmp");
+        fs::rename(&idx_path, &idx_tmp).unwrap();
+        fs::write(&idx_path, &old_idx).unwrap();
+
+        let pack = TilePack::open(dir).unwrap();
+        let bytes = pack.read_tile_bytes(pack.tile_ref(0, 0, 0).unwrap()).unwrap();
+        assert!(bytes.len() < jpeg.len());
+        assert!(!idx_tmp.exists());
+        assert_ne!(fs::read(&idx_path).unwrap(), old_idx);
+
+        // A stray .idx.tmp that doesn't fit the pack is ignored
+        fs::write(&idx_tmp, &old_idx).unwrap();
+        assert!(TilePack::open(dir).is_ok());
+        assert!(idx_tmp.exists());
+    }
+
+    #[test]
+    fn test_transcode_pack_converts_jpegs_and_keeps_gaps() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+
+        let jpeg = test_jpeg_bytes();
+        let mask = [vec![0u8; 2048], vec![255u8; 2048]].concat();
+        for (level, name) in [(0, "0_0.jpg"), (0, "2_0.png"), (1, "0_0.jpg")] {
+            let level_dir = dir.join("tiles_files").join(level.to_string());
+            fs::create_dir_all(&level_dir).unwrap();
+            let data = if name.ends_with(".png") { &mask } else { &jpeg };
+            fs::write(level_dir.join(name), data).unwrap();
+        }
+        pack_dzsave_tiles(dir, &[(0, 3, 1), (1, 1, 1)], None, None).unwrap();
+        let original = TilePack::open(dir).unwrap();
+        let original_tile = original.read_tile_bytes(original.tile_ref(0, 0, 0).unwrap()).unwrap();
+        let dims = read_tile_header(&original_tile).unwrap();
+        drop(original);
+
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let cb = {
+            let calls = Arc::clone(&calls);
+            Box::new(move |done, total| calls.lock().unwrap().push((done, total)))
+        };
+        let err = transcode_pack(dir, ImageFormat::WebP, 90, None).unwrap_err();
+        assert!(matches!(err, TileError::Validation(_)));
+
+        // A 1x1 tile is smaller as PNG than as JPEG, so every JPEG converts
+        let (before, after) = transcode_pack(dir, ImageFormat::Png, 90, Some(cb)).unwrap();
+        assert_eq!(before, 2 * jpeg.len() as u64 + mask.len() as u64);
+        assert!(after < before);
+        let mut calls = calls.lock().unwrap().clone();
+        calls.sort_unstable();
+        assert_eq!(calls, [(1, 2), (2, 2)]);
+
+        let pack = TilePack::open(dir).unwrap();
+        for (level, col) in [(0, 0), (1, 0)] {
+            let bytes = pack.read_tile_bytes(pack.tile_ref(level, col, 0).unwrap()).unwrap();
+            assert!(bytes.starts_with(b"\x89PNG"));
+            assert_eq!(read_tile_header(&bytes).unwrap(), dims);
+        }
+
+        assert!(pack.tile_ref(0, 1, 0).is_none());
+        let mask_bytes = pack.read_tile_bytes(pack.tile_ref(0, 2, 0).unwrap()).unwrap();
+        assert_eq!(mask_bytes.as_ref(), mask.as_slice());
+        drop(pack);
+
+        // Only JPEG tiles are re-encoded, so the PNGs are left as they are
+        assert_eq!(transcode_pack(dir, ImageFormat::Jpeg, 90, None).unwrap(), (after, after));
+    }
+
+    #[test]
+    fn test_validate_pack_reports_every_corrupt_tile() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+
+        let jpeg = test_jpeg_bytes();
+        for level in ["0", "1"] {
+            let level_dir = dir.join("tiles_files").join(level);
+            fs::create_dir_all(&level_dir).unwrap();
+            fs::write(level_dir.join("0_0.jpg"), &jpeg).unwrap();
+        }
+        let level_dir = dir.join("tiles_files").join("1");
+        fs::write(level_dir.join("1_0.jpg"), b"not an image").unwrap();
+        fs::write(level_dir.join("0_1.jpg"), &jpeg).unwrap();
+        pack_dzsave_tiles(dir, &[(0, 1, 1), (1, 2, 2)], None, None).unwrap();
+
+        let clean = validate_pack(dir).unwrap();
+        assert_eq!((clean.levels, clean.total_tiles, clean.empty_tiles), (2, 5, 1));
+        assert_eq!(clean.corrupt_tiles, vec![(1, 1, 0)]);
+
+        // Flip a byte of the level 1 tile at (0, 1): the CRC catches it and
+        // the scan carries on past the first failure
+        let pack_path = dir.join("tiles").join("level_1.pack");
+        let mut bytes = fs::read(&pack_path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        fs::write(&pack_path, bytes).unwrap();
+        let damaged = validate_pack(dir).unwrap();
+        assert_eq!(damaged.corrupt_tiles, vec![(1, 1, 0), (1, 0, 1)]);
+    }
+
+    #[test]
+    fn test_open_rejects_entry_past_pack_end() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+
+        let level_dir = dir.join("tiles_files").join("0");
+        fs::create_dir_all(&level_dir).unwrap();
+        let jpeg = test_jpeg_bytes();
+        fs::write(level_dir.join("0_0.jpg"), &jpeg).unwrap();
+        fs::write(level_dir.join("1_0.jpg"), &jpeg).unwrap();
+        pack_dzsave_tiles(dir, &[(0, 2, 1)], None, None).unwrap();
+
+        // Point tile (1, 0) one byte past where the pack ends
+        let idx_path = dir.join("tiles").join("level_0.idx");
+        let mut idx = fs::read(&idx_path).unwrap();
+        let entry = LEVEL_HEADER_SIZE + LEVEL_ENTRY_SIZE;
+        idx[entry..entry + 8].copy_from_slice(&(jpeg.len() as u64 + 1).to_le_bytes());
+        fs::write(&idx_path, idx).unwrap();
+
+        for open in [TilePack::open, TilePack::open_mmap] {
+            let err = open(dir).unwrap_err();
+            assert!(matches!(err, TileError::Validation(_)));
+            let err = err.to_string();
+            assert!(err.contains("tile (1, 0)"), "{err}");
+            assert!(err.contains("past the end"), "{err}");
+        }
+
+        // Without its pack the level opens, with its tiles unavailable
+        fs::remove_file(dir.join("tiles").join("level_0.pack")).unwrap();
+        assert!(TilePack::open(dir).unwrap().tile_ref(0, 1, 0).is_none());
+    }
+
+    #[test]
+    fn test_open_mmap_reads_match_file_reads() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+
+        let level_dir = dir.join("tiles_files").join("0");
+        fs::create_dir_all(&level_dir).unwrap();
+        let jpeg = test_jpeg_bytes();
+        fs::write(level_dir.join("0_0.jpg"), &jpeg).unwrap();
+        fs::write(level_dir.join("1_0.jpg"), &jpeg[..jpeg.len() / 2]).unwrap();
+        pack_dzsave_tiles(dir, &[(0, 2, 1)], None, None).unwrap();
+
+        let file_pack = TilePack::open(dir).unwrap();
+        let mmap_pack = TilePack::open_mmap(dir).unwrap();
+        assert!(matches!(mmap_pack.levels[0].pack, Some(PackData::Mmap(_))));
+        for col in 0..2 {
+            let tile_ref = mmap_pack.tile_ref(0, col, 0).unwrap();
+            assert_eq!(
+                mmap_pack.read_tile_bytes(tile_ref).unwrap(),
+                file_pack.read_tile_bytes(tile_ref).unwrap()
+            );
+        }
+
+        // Bounds checks still apply to the mapped slice
+        let mut past_end = mmap_pack.tile_ref(0, 1, 0).unwrap();
+        past_end.offset += 1;
+        past_end.crc32 = None;
+        let err = mmap_pack.read_tile_bytes(past_end).unwrap_err().to_string();
+        assert!(err.contains("exceeds pack size"), "{err}");
+    }
+
     /// Old sequential implementation (for benchmarking comparison).
+    #[allow(dead_code)]
     fn pack_dzsave_tiles_sequential(
         fastpath_dir: &Path,
         levels: &[(u32, u32, u32)],
@@ -685,8 +1878,7 @@ mod tests {
                     };
 
                     let Some(tile_path) = tile_path else {
-                        idx_writer.write_all(&0u64.to_le_bytes())?;
-                        idx_writer.write_all(&0u32.to_le_bytes())?;
+                        write_level_entry(&mut idx_writer, 0, &[])?;
                         continue;
                     };
 
@@ -694,8 +1886,7 @@ mod tests {
                     let length: u32 = data.len().try_into().unwrap();
 
                     pack_writer.write_all(&data)?;
-                    idx_writer.write_all(&pack_offset.to_le_bytes())?;
-                    idx_writer.write_all(&length.to_le_bytes())?;
+                    write_level_entry(&mut idx_writer, pack_offset, &data)?;
                     pack_offset += length as u64;
                 }
             }
@@ -750,8 +1941,7 @@ mod tests {
                     };
 
                     let Some(tile_path) = tile_path else {
-                        idx_writer.write_all(&0u64.to_le_bytes())?;
-                        idx_writer.write_all(&0u32.to_le_bytes())?;
+                        write_level_entry(&mut idx_writer, 0, &[])?;
                         continue;
                     };
 
@@ -759,8 +1949,7 @@ mod tests {
                     let length: u32 = data.len().try_into().unwrap();
 
                     pack_writer.write_all(&data)?;
-                    idx_writer.write_all(&pack_offset.to_le_bytes())?;
-                    idx_writer.write_all(&length.to_le_bytes())?;
+                    write_level_entry(&mut idx_writer, pack_offset, &data)?;
                     pack_offset += length as u64;
                 }
             }
@@ -819,8 +2008,7 @@ mod tests {
                     let tile_path = tile_files.get(&key);
 
                     let Some(tile_path) = tile_path else {
-                        idx_writer.write_all(&0u64.to_le_bytes())?;
-                        idx_writer.write_all(&0u32.to_le_bytes())?;
+                        write_level_entry(&mut idx_writer, 0, &[])?;
                         continue;
                     };
 
@@ -828,8 +2016,7 @@ mod tests {
                     let length: u32 = data.len().try_into().unwrap();
 
                     pack_writer.write_all(&data)?;
-                    idx_writer.write_all(&pack_offset.to_le_bytes())?;
-                    idx_writer.write_all(&length.to_le_bytes())?;
+                    write_level_entry(&mut idx_writer, pack_offset, &data)?;
                     pack_offset += length as u64;
                 }
             }
@@ -914,7 +2101,7 @@ mod tests {
             // --- New: parallel + prescan ---
             let (temp, levels) = create_bench_tiles(NUM_LEVELS, TILES_PER_SIDE, TILE_SIZE);
             let start = Instant::now();
-            pack_dzsave_tiles(temp.path(), &levels, None).unwrap();
+            pack_dzsave_tiles(temp.path(), &levels, None, None).unwrap();
             let elapsed = start.elapsed();
             par_times.push(elapsed);
             let par_ms = elapsed.as_secs_f64() * 1000.0;
@@ -20,6 +20,10 @@ pub struct Viewport {
     pub velocity_x: f64,
     /// Vertical velocity (pixels per second).
     pub velocity_y: f64,
+    /// Horizontal acceleration (pixels per second squared).
+    pub acceleration_x: f64,
+    /// Vertical acceleration (pixels per second squared).
+    pub acceleration_y: f64,
 }
 
 impl Viewport {
@@ -40,8 +44,55 @@ impl Viewport {
             scale,
             velocity_x,
             velocity_y,
+            acceleration_x: 0.0,
+            acceleration_y: 0.0,
         }
     }
+
+    /// Set the pan acceleration (pixels per second squared).
+    pub fn with_acceleration(mut self, acceleration_x: f64, acceleration_y: f64) -> Self {
+        self.acceleration_x = acceleration_x;
+        self.acceleration_y = acceleration_y;
+        self
+    }
+}
+
+/// Order in which enumerated tiles are queued for fetching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileOrder {
+    /// Row by row, matching the pack's storage order.
+    #[default]
+    RowMajor,
+    /// Z-order (Morton) curve: spatially adjacent tiles stay together in the
+    /// work queue, so partial completion covers a contiguous area.
+    Morton,
+}
+
+impl TileOrder {
+    /// Parse an order name (`"row_major"` or `"morton"`, case-insensitive).
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "row_major" | "rowmajor" => Some(Self::RowMajor),
+            "morton" | "z" | "zorder" => Some(Self::Morton),
+            _ => None,
+        }
+    }
+}
+
+/// Spread the bits of `v` so they occupy the even bit positions of a u64.
+fn spread_bits(v: u32) -> u64 {
+    let mut x = v as u64;
+    x = (x | (x << 16)) & 0x0000_FFFF_0000_FFFF;
+    x = (x | (x << 8)) & 0x00FF_00FF_00FF_00FF;
+    x = (x | (x << 4)) & 0x0F0F_0F0F_0F0F_0F0F;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    x = (x | (x << 1)) & 0x5555_5555_5555_5555;
+    x
+}
+
+/// Morton (Z-order) code interleaving `col` (even bits) and `row` (odd bits).
+pub fn morton_code(col: u32, row: u32) -> u64 {
+    spread_bits(col) | (spread_bits(row) << 1)
 }
 
 /// Configuration for prefetching behavior.
@@ -55,6 +106,17 @@ pub struct PrefetchConfig {
     pub prefetch_levels: bool,
     /// Minimum velocity to trigger directional prefetch.
     pub min_velocity: f64,
+    /// How much upscaling `level_for_scale` tolerates before it switches to a
+    /// finer level. 1.0 never upscales; 2.0 accepts drawing each level pixel
+    /// up to 2 screen pixels wide, trading sharpness for fewer tiles.
+    pub resolution_bias: f64,
+    /// Seconds of motion to project ahead when panning (0 = off). The
+    /// extension in the movement direction is `v*t + 0.5*a*t^2`, clamped
+    /// between `tiles_ahead` and `max_tiles_ahead` tiles.
+    pub lookahead_secs: f64,
+    /// Cap on the lookahead extension per axis, in tiles, so a fling or a
+    /// velocity spike can't queue a whole row of the slide.
+    pub max_tiles_ahead: u32,
 }
 
 impl Default for PrefetchConfig {
@@ -64,6 +126,9 @@ impl Default for PrefetchConfig {
             tiles_around: 1,
             prefetch_levels: true,
             min_velocity: 50.0, // pixels per second
+            resolution_bias: 1.0,
+            lookahead_secs: 0.0,
+            max_tiles_ahead: 8,
         }
     }
 }
@@ -78,13 +143,23 @@ impl PrefetchCalculator {
         Self { config }
     }
 
+    /// Set `PrefetchConfig::resolution_bias` (non-finite or <= 0 means 1.0).
+    pub fn set_resolution_bias(&mut self, bias: f64) {
+        self.config.resolution_bias = if bias.is_finite() && bias > 0.0 { bias } else { 1.0 };
+    }
+
+    /// Set `PrefetchConfig::lookahead_secs` (non-finite or negative means 0).
+    pub fn set_lookahead(&mut self, secs: f64) {
+        self.config.lookahead_secs = if secs.is_finite() && secs > 0.0 { secs } else { 0.0 };
+    }
+
     /// Get the best pyramid level for a given scale.
     ///
     /// Convention-independent: picks the level with the largest downsample
-    /// that's still <= target. Falls back to the highest-resolution level
-    /// (smallest downsample) if none qualify.
+    /// that's still <= target (`resolution_bias / scale`). Falls back to the
+    /// highest-resolution level (smallest downsample) if none qualify.
     pub fn level_for_scale(&self, metadata: &SlideMetadata, scale: f64) -> u32 {
-        let target_downsample = 1.0 / scale;
+        let target_downsample = self.config.resolution_bias / scale;
 
         metadata
             .levels
@@ -106,7 +181,7 @@ impl PrefetchCalculator {
 
         if let Some(level_info) = metadata.get_level(level) {
             self.tiles_in_rect(
-                metadata.tile_size,
+                metadata,
                 level_info,
                 viewport.x,
                 viewport.y,
@@ -152,7 +227,7 @@ impl PrefetchCalculator {
         if let Some(level_info) = metadata.get_level(level) {
             // Add tiles from extended viewport (based on velocity)
             let extended_tiles = self.tiles_in_rect(
-                metadata.tile_size,
+                metadata,
                 level_info,
                 ext_x,
                 ext_y,
@@ -170,7 +245,7 @@ impl PrefetchCalculator {
                 if level + 1 < metadata.num_levels() as u32 {
                     if let Some(up_level) = metadata.get_level(level + 1) {
                         let up_tiles = self.tiles_in_rect(
-                            metadata.tile_size,
+                            metadata,
                             up_level,
                             viewport.x,
                             viewport.y,
@@ -190,7 +265,7 @@ impl PrefetchCalculator {
                         let small_height = viewport.height / 4.0;
 
                         let down_tiles = self.tiles_in_rect(
-                            metadata.tile_size,
+                            metadata,
                             down_level,
                             center_x - small_width / 2.0,
                             center_y - small_height / 2.0,
@@ -206,39 +281,53 @@ impl PrefetchCalculator {
         tiles
     }
 
-    /// Calculate extended viewport based on velocity.
+    /// Distance to extend ahead along one axis, signed like `velocity`.
+    ///
+    /// Projects `v*t + 0.5*a*t^2` over the lookahead horizon, so an
+    /// accelerating pan reaches further than a steady one. A strong
+    /// deceleration never shrinks the extension below `min_ext`, and no
+    /// velocity stretches it past `max_ext`.
+    fn axis_extension(&self, velocity: f64, acceleration: f64, min_ext: f64, max_ext: f64) -> f64 {
+        if velocity.abs() <= self.config.min_velocity {
+            return 0.0;
+        }
+        let t = self.config.lookahead_secs;
+        // Acceleration measured along the direction of travel
+        let accel = acceleration * velocity.signum();
+        let projected = velocity.abs() * t + 0.5 * accel * t * t;
+        velocity.signum() * projected.min(max_ext).max(min_ext)
+    }
+
+    /// Calculate extended viewport based on velocity and acceleration.
+    ///
+    /// The result is one rectangle spanning both axis extensions, so a
+    /// diagonal pan also covers the corner tiles ahead of it rather than an
+    /// L-shape along the two edges.
     fn extended_viewport(
         &self,
         viewport: &Viewport,
         tile_size: u32,
     ) -> (f64, f64, f64, f64) {
         let tile_size = tile_size as f64;
-        let tiles_ahead = self.config.tiles_ahead as f64;
+        let min_ahead = tile_size * self.config.tiles_ahead as f64;
+        let max_ahead = tile_size * self.config.max_tiles_ahead as f64;
 
         // Base extension around viewport
         let base_ext = tile_size * (self.config.tiles_around as f64);
 
-        // Velocity-based extension
-        let (vel_ext_x, vel_ext_y) = if viewport.velocity_x.abs() > self.config.min_velocity
-            || viewport.velocity_y.abs() > self.config.min_velocity
-        {
-            // Extend in direction of movement
-            let ext_x = if viewport.velocity_x.abs() > self.config.min_velocity {
-                viewport.velocity_x.signum() * tile_size * tiles_ahead
-            } else {
-                0.0
-            };
-
-            let ext_y = if viewport.velocity_y.abs() > self.config.min_velocity {
-                viewport.velocity_y.signum() * tile_size * tiles_ahead
-            } else {
-                0.0
-            };
-
-            (ext_x, ext_y)
-        } else {
-            (0.0, 0.0)
-        };
+        // Motion-based extension in the direction of movement
+        let vel_ext_x = self.axis_extension(
+            viewport.velocity_x,
+            viewport.acceleration_x,
+            min_ahead,
+            max_ahead,
+        );
+        let vel_ext_y = self.axis_extension(
+            viewport.velocity_y,
+            viewport.acceleration_y,
+            min_ahead,
+            max_ahead,
+        );
 
         // Calculate extended rectangle
         let x = viewport.x - base_ext + vel_ext_x.min(0.0);
@@ -249,24 +338,35 @@ impl PrefetchCalculator {
         (x, y, w, h)
     }
 
-    /// Get tiles that intersect a rectangle.
+    /// Get tiles that intersect a rectangle (level-0 coordinates).
+    ///
+    /// The rectangle is clipped to the slide and the grid is derived from
+    /// `metadata.dimensions`, so the partial last column and row are included
+    /// even when the slide size isn't a multiple of the level tile size.
     fn tiles_in_rect(
         &self,
-        tile_size: u32,
+        metadata: &SlideMetadata,
         level_info: &LevelInfo,
         x: f64,
         y: f64,
         width: f64,
         height: f64,
     ) -> Vec<TileCoord> {
-        let level_tile_size = (tile_size * level_info.downsample) as f64;
+        let level_tile_size = (metadata.tile_size * level_info.downsample) as f64;
+        let (slide_w, slide_h) = metadata.dimensions;
+        let (cols, rows) = level_grid(metadata, level_info);
 
+        let x_end = (x + width).min(slide_w as f64);
+        let y_end = (y + height).min(slide_h as f64);
+        if x_end <= x || y_end <= y {
+            return Vec::new();
+        }
         let col_start = ((x / level_tile_size).floor() as i32).max(0) as u32;
-        let col_end = (((x + width) / level_tile_size).ceil() as i32).max(0) as u32;
-        let col_end = col_end.min(level_info.cols);
+        let col_end = ((x_end / level_tile_size).ceil() as i32).max(0) as u32;
+        let col_end = col_end.min(cols);
         let row_start = ((y / level_tile_size).floor() as i32).max(0) as u32;
-        let row_end = (((y + height) / level_tile_size).ceil() as i32).max(0) as u32;
-        let row_end = row_end.min(level_info.rows);
+        let row_end = ((y_end / level_tile_size).ceil() as i32).max(0) as u32;
+        let row_end = row_end.min(rows);
 
         // Viewport may be entirely outside slide bounds (e.g. extended prefetch
         // rect during fast panning) — col_start > col_end is possible.
@@ -288,6 +388,20 @@ impl PrefetchCalculator {
     }
 }
 
+/// Tile grid of `level_info` covering the whole slide: each axis is
+/// `ceil(ceil(dim / downsample) / tile_size)`, counting a partial edge tile.
+///
+/// Falls back to the metadata's cols/rows when the slide has no size.
+fn level_grid(metadata: &SlideMetadata, level_info: &LevelInfo) -> (u32, u32) {
+    let (width, height) = metadata.dimensions;
+    let tile_size = metadata.tile_size.max(1);
+    if width == 0 || height == 0 {
+        return (level_info.cols, level_info.rows);
+    }
+    let (level_w, level_h) = level_info.dimensions(metadata.dimensions);
+    (level_w.div_ceil(tile_size), level_h.div_ceil(tile_size))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -318,6 +432,11 @@ mod tests {
             ],
             target_mpp: 0.5,
             target_magnification: 20.0,
+            tile_border: 0,
+            default_view: None,
+            level_dir_names: Default::default(),
+            associated_images: Default::default(),
+            subslides: Default::default(),
         }
     }
 
@@ -340,6 +459,54 @@ mod tests {
         assert_eq!(calc.level_for_scale(&metadata, 0.75), 2);
     }
 
+    #[test]
+    fn test_level_for_scale_resolution_bias() {
+        let mut calc = PrefetchCalculator::new(PrefetchConfig::default());
+        let metadata = test_metadata();
+        assert_eq!(calc.level_for_scale(&metadata, 0.6), 2); // ds=1
+
+        // bias 2.0 → target_downsample=3.33 → level 1 (ds=2), upscaled 1.2x
+        calc.set_resolution_bias(2.0);
+        assert_eq!(calc.level_for_scale(&metadata, 0.6), 1);
+        assert_eq!(calc.level_for_scale(&metadata, 1.0), 1);
+
+        // The visible tiles come from the biased level
+        let viewport = Viewport::new(0.0, 0.0, 1000.0, 1000.0, 0.6, 0.0, 0.0);
+        let visible = calc.visible_tiles(&metadata, &viewport);
+        assert!(!visible.is_empty());
+        assert!(visible.iter().all(|t| t.level == 1));
+
+        calc.set_resolution_bias(f64::NAN);
+        assert_eq!(calc.level_for_scale(&metadata, 0.6), 2);
+    }
+
+    #[test]
+    fn test_morton_order_small_grid() {
+        let mut coords: Vec<(u32, u32)> = (0..4)
+            .flat_map(|row| (0..4).map(move |col| (col, row)))
+            .collect();
+        coords.sort_by_key(|&(col, row)| morton_code(col, row));
+
+        // Each 2x2 quadrant is visited before moving on
+        assert_eq!(
+            &coords[..8],
+            &[(0, 0), (1, 0), (0, 1), (1, 1), (2, 0), (3, 0), (2, 1), (3, 1)]
+        );
+
+        // Every tile is still covered exactly once
+        let mut sorted = coords.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 16);
+    }
+
+    #[test]
+    fn test_tile_order_parse() {
+        assert_eq!(TileOrder::parse("morton"), Some(TileOrder::Morton));
+        assert_eq!(TileOrder::parse("Row_Major"), Some(TileOrder::RowMajor));
+        assert_eq!(TileOrder::parse("hilbert"), None);
+    }
+
     #[test]
     fn test_visible_tiles() {
         let calc = PrefetchCalculator::new(PrefetchConfig::default());
@@ -360,6 +527,9 @@ mod tests {
             tiles_around: 1,
             prefetch_levels: false,
             min_velocity: 50.0,
+            resolution_bias: 1.0,
+            lookahead_secs: 0.5,
+            max_tiles_ahead: 8,
         });
         let metadata = test_metadata();
 
@@ -372,6 +542,120 @@ mod tests {
         assert!(!tiles.is_empty());
     }
 
+    #[test]
+    fn test_acceleration_extends_further_than_constant_velocity() {
+        let calc = PrefetchCalculator::new(PrefetchConfig {
+            tiles_ahead: 1,
+            lookahead_secs: 0.5,
+            ..Default::default()
+        });
+        let steady = Viewport::new(0.0, 0.0, 1024.0, 1024.0, 1.0, 1000.0, 0.0);
+        let (_, _, steady_w, _) = calc.extended_viewport(&steady, 512);
+        // 1000 * 0.5 = 500 px projected, below the one-tile minimum
+        assert_eq!(steady_w, 1024.0 + 2.0 * 512.0 + 512.0);
+
+        // 1000 * 0.5 + 0.5 * 4000 * 0.25 = 1000 px projected
+        let accelerating = steady.with_acceleration(4000.0, 0.0);
+        let (x, _, accel_w, accel_h) = calc.extended_viewport(&accelerating, 512);
+        assert!(accel_w > steady_w);
+        assert_eq!(accel_w, 1024.0 + 2.0 * 512.0 + 1000.0);
+        assert_eq!(x, -512.0);
+        assert_eq!(accel_h, 1024.0 + 2.0 * 512.0);
+
+        // Moving left while speeding up extends the left edge instead
+        let left = Viewport::new(0.0, 0.0, 1024.0, 1024.0, 1.0, -1000.0, 0.0)
+            .with_acceleration(-4000.0, 0.0);
+        let (x, _, w, _) = calc.extended_viewport(&left, 512);
+        assert_eq!((x, w), (-512.0 - 1000.0, accel_w));
+
+        // Braking never shrinks below tiles_ahead
+        let braking = steady.with_acceleration(-8000.0, 0.0);
+        assert_eq!(calc.extended_viewport(&braking, 512).2, steady_w);
+
+        // Acceleration alone doesn't pass the min_velocity gate
+        let slow = Viewport::new(0.0, 0.0, 1024.0, 1024.0, 1.0, 10.0, 0.0)
+            .with_acceleration(4000.0, 0.0);
+        assert_eq!(calc.extended_viewport(&slow, 512).2, 1024.0 + 2.0 * 512.0);
+    }
+
+    #[test]
+    fn test_lookahead_capped_at_max_tiles_ahead() {
+        let calc = PrefetchCalculator::new(PrefetchConfig {
+            tiles_ahead: 1,
+            lookahead_secs: 0.5,
+            max_tiles_ahead: 4,
+            ..Default::default()
+        });
+        // A fling: 1e6 px/s would project 500,000 px ahead
+        let fling = Viewport::new(0.0, 0.0, 1024.0, 1024.0, 1.0, 1e6, -1e6)
+            .with_acceleration(1e7, -1e7);
+        let (_, y, w, h) = calc.extended_viewport(&fling, 512);
+        assert_eq!(w, 1024.0 + 2.0 * 512.0 + 4.0 * 512.0);
+        assert_eq!((y, h), (-512.0 - 4.0 * 512.0, w));
+
+        // Off by default: only tiles_ahead, whatever the speed
+        let calc = PrefetchCalculator::new(PrefetchConfig {
+            tiles_ahead: 1,
+            ..Default::default()
+        });
+        assert_eq!(calc.extended_viewport(&fling, 512).2, 1024.0 + 3.0 * 512.0);
+    }
+
+    #[test]
+    fn test_diagonal_velocity_prefetches_corner_tiles() {
+        let calc = PrefetchCalculator::new(PrefetchConfig {
+            prefetch_levels: false,
+            ..Default::default()
+        });
+        let metadata = test_metadata();
+        // Level 2 (512 px tiles): viewport plus one tile around is cols/rows
+        // 0..3; moving adds tiles_ahead = 2 more along each moving axis
+        let corner = TileCoord::new(2, 4, 4);
+
+        let diagonal = Viewport::new(0.0, 0.0, 1024.0, 1024.0, 1.0, 100.0, 100.0);
+        let tiles = calc.prefetch_tiles(&metadata, &diagonal, &|_| false);
+        assert!(tiles.contains(&corner));
+        assert!(tiles.contains(&TileCoord::new(2, 4, 0)));
+        assert!(tiles.contains(&TileCoord::new(2, 0, 4)));
+
+        // Moving right only extends the columns, not the corner rows
+        let right = Viewport::new(0.0, 0.0, 1024.0, 1024.0, 1.0, 100.0, 0.0);
+        let tiles = calc.prefetch_tiles(&metadata, &right, &|_| false);
+        assert!(tiles.contains(&TileCoord::new(2, 4, 0)));
+        assert!(!tiles.contains(&corner));
+    }
+
+    #[test]
+    fn test_tiles_in_rect_includes_partial_edge_tiles() {
+        // 1100x700 at 512px tiles: 3x2 at full resolution, 2x1 at 2x. The
+        // metadata grid under-reports (floored) to show the slide size wins.
+        let metadata = SlideMetadata {
+            dimensions: (1100, 700),
+            levels: vec![
+                LevelInfo { level: 0, downsample: 2, cols: 1, rows: 1 },
+                LevelInfo { level: 1, downsample: 1, cols: 2, rows: 1 },
+            ],
+            ..test_metadata()
+        };
+        let calc = PrefetchCalculator::new(PrefetchConfig::default());
+        let full = metadata.get_level(1).unwrap();
+        let low = metadata.get_level(0).unwrap();
+
+        let tiles = calc.tiles_in_rect(&metadata, full, 0.0, 0.0, 1100.0, 700.0);
+        assert_eq!(tiles.len(), 6);
+        assert!(tiles.contains(&TileCoord::new(1, 2, 1)));
+        let tiles = calc.tiles_in_rect(&metadata, low, 0.0, 0.0, 1100.0, 700.0);
+        assert_eq!(tiles, vec![TileCoord::new(0, 0, 0), TileCoord::new(0, 1, 0)]);
+
+        // A view inside the last partial column, and one running past the
+        // slide edge, both get exactly the edge tiles
+        let tiles = calc.tiles_in_rect(&metadata, full, 1030.0, 600.0, 60.0, 90.0);
+        assert_eq!(tiles, vec![TileCoord::new(1, 2, 1)]);
+        let tiles = calc.tiles_in_rect(&metadata, full, 1030.0, 0.0, 5000.0, 100.0);
+        assert_eq!(tiles, vec![TileCoord::new(1, 2, 0)]);
+        assert!(calc.tiles_in_rect(&metadata, full, 1100.0, 0.0, 500.0, 100.0).is_empty());
+    }
+
     #[test]
     fn test_prefetch_filters_cached() {
         let calc = PrefetchCalculator::new(PrefetchConfig {
@@ -415,6 +699,9 @@ mod tests {
             tiles_around: 1,
             prefetch_levels: true,
             min_velocity: 50.0,
+            resolution_bias: 1.0,
+            lookahead_secs: 0.5,
+            max_tiles_ahead: 8,
         });
         let metadata = test_metadata();
 
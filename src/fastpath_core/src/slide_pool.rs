@@ -1,22 +1,34 @@
 //! Metadata pool for .fastpath directories.
 //!
 //! Caches `SlideEntry` (metadata + pack index) by slide_id so that
-//! revisiting a slide with a warm L2 cache skips re-parsing metadata.json.
+//! revisiting a slide with a warm L2 cache skips re-parsing metadata.json,
+//! plus a small cache of rendered slide overviews for the same reason.
 
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use parking_lot::RwLock;
 
+use crate::cache::{OverviewCache, OverviewKey};
+use crate::decoder::TileData;
 use crate::error::TileResult;
 use crate::format::SlideMetadata;
 use crate::pack::TilePack;
 
+/// Size of the overview cache. A 256px RGB overview is ~200 KB, so this
+/// holds the overviews of a few dozen slides.
+const OVERVIEW_CACHE_MB: usize = 8;
+
 /// Cached slide state: metadata + tile pack index.
 pub struct SlideEntry {
+    /// The .fastpath directory the entry was loaded from.
+    pub dir: PathBuf,
     pub metadata: SlideMetadata,
     pub pack: TilePack,
+    /// Whether level grids were reconciled against the packed indexes at load.
+    pub grid_reconciled: bool,
 }
 
 /// Pool of loaded slide metadata, keyed by slide_id hash.
@@ -25,15 +37,38 @@ pub struct SlideEntry {
 /// negligible (~300 bytes per slide) compared to tile data.
 pub struct SlidePool {
     entries: RwLock<HashMap<u64, Arc<SlideEntry>>>,
+    /// Open packs with `TilePack::open_mmap` instead of positioned reads.
+    mmap: AtomicBool,
+    /// Rendered overviews; slide content is immutable, so never invalidated.
+    overviews: OverviewCache,
 }
 
 impl SlidePool {
     pub fn new() -> Self {
         Self {
             entries: RwLock::new(HashMap::new()),
+            mmap: AtomicBool::new(false),
+            overviews: OverviewCache::new(OVERVIEW_CACHE_MB),
         }
     }
 
+    /// Overview rendered earlier for `key`, if still cached.
+    pub fn overview(&self, key: &OverviewKey) -> Option<TileData> {
+        self.overviews.get(key)
+    }
+
+    /// Keep a rendered overview for later `overview` calls.
+    pub fn insert_overview(&self, key: OverviewKey, overview: TileData) {
+        self.overviews.insert(key, overview);
+    }
+
+    /// Memory-map the packs of slides loaded from now on.
+    ///
+    /// Entries already in the pool keep their current reader.
+    pub fn set_mmap(&self, enabled: bool) {
+        self.mmap.store(enabled, Ordering::Relaxed);
+    }
+
     /// Get a cached entry or load from disk.
     ///
     /// Uses double-checked locking: after acquiring the write lock, re-checks
@@ -41,9 +76,27 @@ impl SlidePool {
     /// two threads from simultaneously parsing metadata + opening pack files
     /// for the same slide.
     pub fn load_or_get(&self, slide_id: u64, fastpath_dir: &Path) -> TileResult<Arc<SlideEntry>> {
+        self.load_or_get_with(slide_id, fastpath_dir, false)
+    }
+
+    /// Like `load_or_get`, optionally reconciling level grids with the tiles
+    /// on disk (see `SlideMetadata::reconcile_grid`).
+    ///
+    /// A cached entry loaded without reconciliation is reloaded when
+    /// `reconcile_grid` is requested; a reconciled entry satisfies both.
+    pub fn load_or_get_with(
+        &self,
+        slide_id: u64,
+        fastpath_dir: &Path,
+        reconcile_grid: bool,
+    ) -> TileResult<Arc<SlideEntry>> {
+        let usable = |entry: &SlideEntry| entry.grid_reconciled || !reconcile_grid;
+
         // Fast path: already cached (read lock)
         if let Some(entry) = self.entries.read().get(&slide_id) {
-            return Ok(Arc::clone(entry));
+            if usable(entry) {
+                return Ok(Arc::clone(entry));
+            }
         }
 
         // Slow path: acquire write lock
@@ -51,18 +104,59 @@ impl SlidePool {
 
         // Re-check: another thread may have inserted while we waited for write lock
         if let Some(entry) = entries.get(&slide_id) {
-            return Ok(Arc::clone(entry));
+            if usable(entry) {
+                return Ok(Arc::clone(entry));
+            }
         }
 
         // Load from disk (holding write lock to prevent duplicate work)
-        let metadata = SlideMetadata::load(fastpath_dir)?;
-        let pack = TilePack::open(fastpath_dir)?;
-        let entry = Arc::new(SlideEntry { metadata, pack });
+        let mut metadata = SlideMetadata::load(fastpath_dir)?;
+        if reconcile_grid {
+            metadata.reconcile_grid(fastpath_dir)?;
+        }
+        let entry = Arc::new(self.open_entry(fastpath_dir, metadata, reconcile_grid)?);
 
         entries.insert(slide_id, Arc::clone(&entry));
         Ok(entry)
     }
 
+    /// The pooled entry for `slide_id`, if it has been loaded.
+    pub fn get(&self, slide_id: u64) -> Option<Arc<SlideEntry>> {
+        self.entries.read().get(&slide_id).map(Arc::clone)
+    }
+
+    /// Open the tile pack in `fastpath_dir` with metadata supplied by the
+    /// caller instead of its metadata.json.
+    ///
+    /// The entry is not pooled: metadata from elsewhere must not be served
+    /// to a later `load_or_get` of the same directory.
+    pub fn open_with_metadata(
+        &self,
+        fastpath_dir: &Path,
+        metadata: SlideMetadata,
+    ) -> TileResult<Arc<SlideEntry>> {
+        Ok(Arc::new(self.open_entry(fastpath_dir, metadata, false)?))
+    }
+
+    fn open_entry(
+        &self,
+        fastpath_dir: &Path,
+        metadata: SlideMetadata,
+        grid_reconciled: bool,
+    ) -> TileResult<SlideEntry> {
+        let pack = if self.mmap.load(Ordering::Relaxed) {
+            TilePack::open_mmap(fastpath_dir)?
+        } else {
+            TilePack::open(fastpath_dir)?
+        };
+        Ok(SlideEntry {
+            dir: fastpath_dir.to_path_buf(),
+            metadata,
+            pack,
+            grid_reconciled,
+        })
+    }
+
     /// Number of cached entries (for testing/diagnostics).
     #[cfg(test)]
     pub fn len(&self) -> usize {
@@ -79,7 +173,7 @@ impl Default for SlidePool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::create_test_fastpath;
+    use crate::test_utils::{create_test_fastpath, create_test_fastpath_under_reported};
     use tempfile::TempDir;
 
     #[test]
@@ -130,6 +224,29 @@ mod tests {
         assert_eq!(pool.len(), 2);
     }
 
+    #[test]
+    fn test_pool_reconcile_grid_extends_under_reported_level() {
+        let temp = TempDir::new().unwrap();
+        // metadata.json declares 1x1, but dzsave wrote a 3x2 grid of tiles
+        create_test_fastpath_under_reported(temp.path(), (1, 1), (3, 2));
+
+        let pool = SlidePool::new();
+        let plain = pool.load_or_get(1, temp.path()).unwrap();
+        let level = plain.metadata.get_level(0).unwrap();
+        assert_eq!((level.cols, level.rows), (1, 1));
+
+        // The unreconciled cache entry is replaced, not reused
+        let reconciled = pool.load_or_get_with(1, temp.path(), true).unwrap();
+        assert!(!Arc::ptr_eq(&plain, &reconciled));
+        let level = reconciled.metadata.get_level(0).unwrap();
+        assert_eq!((level.cols, level.rows), (3, 2));
+        assert!(reconciled.pack.tile_ref(0, 2, 1).is_some());
+
+        let again = pool.load_or_get(1, temp.path()).unwrap();
+        assert!(Arc::ptr_eq(&reconciled, &again));
+        assert_eq!(pool.len(), 1);
+    }
+
     #[test]
     fn test_pool_invalid_path_returns_error() {
         let pool = SlidePool::new();
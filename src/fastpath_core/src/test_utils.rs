@@ -8,6 +8,7 @@ use bytes::Bytes;
 
 use crate::cache::compute_slide_id;
 use crate::decoder::CompressedTileData;
+use crate::pack::{pack_dzsave_tiles, ZstdLevel};
 
 const LEVEL_MAGIC: &[u8; 8] = b"FPLIDX1\0";
 const LEVEL_VERSION: u32 = 1;
@@ -73,6 +74,46 @@ pub fn test_jpeg_bytes() -> Vec<u8> {
     ]
 }
 
+/// Encode a small 8-bit RGB PNG with the given pixels (row-major RGB triples).
+pub fn test_png_bytes(width: u32, height: u32, rgb: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(rgb).unwrap();
+    }
+    out
+}
+
+/// Encode a grayscale PNG of the given depth (samples big-endian, as PNG stores them).
+pub fn test_gray_png_bytes(
+    width: u32,
+    height: u32,
+    depth: png::BitDepth,
+    samples: &[u8],
+) -> Vec<u8> {
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(depth);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(samples).unwrap();
+    }
+    out
+}
+
+/// Encode an RGB buffer as a lossless WebP image.
+pub fn test_webp_bytes(width: u32, height: u32, rgb: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    image_webp::WebPEncoder::new(&mut out)
+        .encode(rgb, width, height, image_webp::ColorType::Rgb8)
+        .unwrap();
+    out
+}
+
 /// Create a `CompressedTileData` from the test JPEG bytes.
 pub fn test_compressed_tile() -> CompressedTileData {
     CompressedTileData {
@@ -83,11 +124,20 @@ pub fn test_compressed_tile() -> CompressedTileData {
 }
 
 fn write_test_pack(dir: &Path, levels: &[(u32, u32, u32)], with_tiles: bool) {
+    let tile_bytes = test_jpeg_bytes();
+    write_test_pack_with(dir, levels, with_tiles, &|_, _, _| tile_bytes.clone());
+}
+
+/// Write a pack where `tile_bytes(level, col, row)` supplies each tile.
+fn write_test_pack_with(
+    dir: &Path,
+    levels: &[(u32, u32, u32)],
+    with_tiles: bool,
+    tile_bytes: &dyn Fn(u32, u32, u32) -> Vec<u8>,
+) {
     let tiles_dir = dir.join("tiles");
     fs::create_dir_all(&tiles_dir).unwrap();
 
-    let tile_bytes = test_jpeg_bytes();
-
     for (level, cols, rows) in levels {
         let pack_path = tiles_dir.join(format!("level_{}.pack", level));
         let idx_path = tiles_dir.join(format!("level_{}.idx", level));
@@ -105,9 +155,10 @@ fn write_test_pack(dir: &Path, levels: &[(u32, u32, u32)], with_tiles: bool) {
             .unwrap();
 
         let mut offset = 0u64;
-        for _row in 0..*rows {
-            for _col in 0..*cols {
+        for row in 0..*rows {
+            for col in 0..*cols {
                 if with_tiles {
+                    let tile_bytes = tile_bytes(*level, col, row);
                     pack_file.write_all(&tile_bytes).unwrap();
                     idx_file.write_all(&offset.to_le_bytes()).unwrap();
                     idx_file
@@ -159,6 +210,222 @@ pub fn create_test_fastpath_with_tiles(dir: &Path) {
     write_test_pack(dir, &[(0, 1, 1), (1, 2, 2)], true);
 }
 
+/// Create a single-level test .fastpath directory with a `cols` x `rows` tile grid.
+pub fn create_test_fastpath_grid(dir: &Path, cols: u32, rows: u32) {
+    let metadata = format!(
+        r#"{{
+        "dimensions": [{}, {}],
+        "tile_size": 512,
+        "levels": [
+            {{"level": 0, "downsample": 1, "cols": {}, "rows": {}}}
+        ],
+        "target_mpp": 0.5,
+        "target_magnification": 20.0,
+        "tile_format": "pack_v2"
+    }}"#,
+        cols * 512,
+        rows * 512,
+        cols,
+        rows
+    );
+    fs::write(dir.join("metadata.json"), metadata).unwrap();
+
+    write_test_pack(dir, &[(0, cols, rows)], true);
+}
+
+/// Create an 8192x8192 test .fastpath directory (no tile data) with one
+/// level per entry of `downsamples`, numbered in the given order.
+pub fn create_test_fastpath_with_downsamples(dir: &Path, downsamples: &[u32]) {
+    let levels: Vec<(u32, u32, u32)> = downsamples
+        .iter()
+        .enumerate()
+        .map(|(level, &ds)| {
+            let tiles = 8192u32.div_ceil(512 * ds);
+            (level as u32, tiles, tiles)
+        })
+        .collect();
+    let level_json: Vec<String> = levels
+        .iter()
+        .zip(downsamples)
+        .map(|(&(level, cols, rows), ds)| {
+            format!(r#"{{"level": {level}, "downsample": {ds}, "cols": {cols}, "rows": {rows}}}"#)
+        })
+        .collect();
+    let metadata = format!(
+        r#"{{
+        "dimensions": [8192, 8192],
+        "tile_size": 512,
+        "levels": [{}],
+        "target_mpp": 0.5,
+        "target_magnification": 20.0,
+        "tile_format": "pack_v2"
+    }}"#,
+        level_json.join(", ")
+    );
+    fs::write(dir.join("metadata.json"), metadata).unwrap();
+    write_test_pack(dir, &levels, false);
+}
+
+/// Create a single-level 2x2 test .fastpath directory whose tiles are
+/// `tile_len` bytes each (the test JPEG padded after EOI, still decodable).
+///
+/// Used to fill a small L2 with few tiles.
+pub fn create_test_fastpath_sized_tiles(dir: &Path, tile_len: usize) {
+    let metadata = r#"{
+        "dimensions": [1024, 1024],
+        "tile_size": 512,
+        "levels": [
+            {"level": 0, "downsample": 1, "cols": 2, "rows": 2}
+        ],
+        "target_mpp": 0.5,
+        "target_magnification": 20.0,
+        "tile_format": "pack_v2"
+    }"#;
+    fs::write(dir.join("metadata.json"), metadata).unwrap();
+
+    let mut tile_bytes = test_jpeg_bytes();
+    tile_bytes.resize(tile_len.max(tile_bytes.len()), 0);
+    write_test_pack_with(dir, &[(0, 2, 2)], true, &|_, _, _| tile_bytes.clone());
+}
+
+/// Create a single-level 2x2 test .fastpath directory packed from dzsave
+/// tiles with zstd. Each tile is `tile_len` bytes before compression: a 1x1
+/// PNG zero-padded after IEND, so it compresses well and still decodes.
+pub fn create_test_fastpath_zstd_tiles(dir: &Path, tile_len: usize) {
+    let metadata = r#"{
+        "dimensions": [1024, 1024],
+        "tile_size": 512,
+        "levels": [
+            {"level": 0, "downsample": 1, "cols": 2, "rows": 2}
+        ],
+        "target_mpp": 0.5,
+        "target_magnification": 20.0,
+        "tile_format": "pack_v2"
+    }"#;
+    fs::write(dir.join("metadata.json"), metadata).unwrap();
+
+    let mut tile_bytes = test_png_bytes(1, 1, &[200, 100, 50]);
+    tile_bytes.resize(tile_len.max(tile_bytes.len()), 0);
+    let level_dir = dir.join("tiles_files").join("0");
+    fs::create_dir_all(&level_dir).unwrap();
+    for (col, row) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+        fs::write(level_dir.join(format!("{col}_{row}.png")), &tile_bytes).unwrap();
+    }
+    pack_dzsave_tiles(dir, &[(0, 2, 2)], Some(ZstdLevel::new(3).unwrap()), None).unwrap();
+}
+
+/// Create a single-level test .fastpath directory whose metadata.json
+/// declares a `declared` (cols, rows) grid, packed from dzsave tiles that
+/// fill the larger `on_disk` grid (converter rounding).
+pub fn create_test_fastpath_under_reported(
+    dir: &Path,
+    declared: (u32, u32),
+    on_disk: (u32, u32),
+) {
+    let metadata = format!(
+        r#"{{
+        "dimensions": [{}, {}],
+        "tile_size": 512,
+        "levels": [
+            {{"level": 0, "downsample": 1, "cols": {}, "rows": {}}}
+        ],
+        "target_mpp": 0.5,
+        "target_magnification": 20.0,
+        "tile_format": "pack_v2"
+    }}"#,
+        declared.0 * 512,
+        declared.1 * 512,
+        declared.0,
+        declared.1
+    );
+    fs::write(dir.join("metadata.json"), metadata).unwrap();
+
+    let level_dir = dir.join("tiles_files").join("0");
+    fs::create_dir_all(&level_dir).unwrap();
+    for row in 0..on_disk.1 {
+        for col in 0..on_disk.0 {
+            fs::write(level_dir.join(format!("{col}_{row}.jpg")), test_jpeg_bytes()).unwrap();
+        }
+    }
+    pack_dzsave_tiles(dir, &[(0, declared.0, declared.1)], None, None).unwrap();
+}
+
+/// Create a two-level test .fastpath directory with one 512x512 PNG tile per
+/// level (level 0 = downsample 2, level 1 = downsample 1).
+///
+/// Decoded tiles are 768 KB each, so a 1 MB L1 holds only one.
+pub fn create_test_fastpath_large_tiles(dir: &Path) {
+    let metadata = r#"{
+        "dimensions": [512, 512],
+        "tile_size": 512,
+        "levels": [
+            {"level": 0, "downsample": 2, "cols": 1, "rows": 1},
+            {"level": 1, "downsample": 1, "cols": 1, "rows": 1}
+        ],
+        "target_mpp": 0.5,
+        "target_magnification": 20.0,
+        "tile_format": "pack_v2"
+    }"#;
+    fs::write(dir.join("metadata.json"), metadata).unwrap();
+
+    let tile_bytes = test_png_bytes(512, 512, &vec![200u8; 512 * 512 * 3]);
+    write_test_pack_with(dir, &[(0, 1, 1), (1, 1, 1)], true, &|_, _, _| tile_bytes.clone());
+}
+
+/// Create a single-tile test .fastpath directory with a 16-bit grayscale
+/// 2x2 PNG tile holding samples 0, 1000, 40000, 65535.
+pub fn create_test_fastpath_gray16(dir: &Path) {
+    let metadata = r#"{
+        "dimensions": [2, 2],
+        "tile_size": 2,
+        "levels": [
+            {"level": 0, "downsample": 1, "cols": 1, "rows": 1}
+        ],
+        "target_mpp": 0.5,
+        "target_magnification": 20.0,
+        "tile_format": "pack_v2"
+    }"#;
+    fs::write(dir.join("metadata.json"), metadata).unwrap();
+
+    let samples: Vec<u8> = [0u16, 1000, 40000, 65535]
+        .iter()
+        .flat_map(|v| v.to_be_bytes())
+        .collect();
+    let tile_bytes = test_gray_png_bytes(2, 2, png::BitDepth::Sixteen, &samples);
+    write_test_pack_with(dir, &[(0, 1, 1)], true, &|_, _, _| tile_bytes.clone());
+}
+
+/// Create a single-level 2x1 test .fastpath directory with `tile_border: 1`.
+///
+/// `tile_size` is 2, so each stored PNG tile is 4x4: a black 1-pixel border
+/// around a 2x2 interior filled with `(col + 1) * 100` in every channel.
+pub fn create_test_fastpath_bordered(dir: &Path) {
+    let metadata = r#"{
+        "dimensions": [4, 2],
+        "tile_size": 2,
+        "tile_border": 1,
+        "levels": [
+            {"level": 0, "downsample": 1, "cols": 2, "rows": 1}
+        ],
+        "target_mpp": 0.5,
+        "target_magnification": 20.0,
+        "tile_format": "pack_v2"
+    }"#;
+    fs::write(dir.join("metadata.json"), metadata).unwrap();
+
+    write_test_pack_with(dir, &[(0, 2, 1)], true, &|_, col, _| {
+        let fill = (col as u8 + 1) * 100;
+        let rgb: Vec<u8> = (0..16)
+            .flat_map(|i| {
+                let (x, y) = (i % 4, i / 4);
+                let v = if (1..3).contains(&x) && (1..3).contains(&y) { fill } else { 0 };
+                [v, v, v]
+            })
+            .collect();
+        test_png_bytes(4, 4, &rgb)
+    });
+}
+
 /// Compute slide_id for a test directory (canonicalize + lowercase + hash).
 pub fn compute_test_slide_id(dir: &Path) -> u64 {
     let canonical = dir.canonicalize().unwrap();
@@ -1,7 +1,10 @@
 //! Python buffer wrapper for decoded tile bytes.
 //!
-//! This enables zero-copy transfer of decoded RGB tiles from Rust to Python by
-//! exposing `bytes::Bytes` through Python's buffer protocol.
+//! This enables zero-copy transfer of decoded tiles from Rust to Python by
+//! exposing `bytes::Bytes` through Python's buffer protocol. 16-bit tiles
+//! export 2-byte "H" items so `memoryview`/NumPy see the right element type.
+//! Buffers built with `with_shape` export an N-d shape, so `np.asarray`
+//! yields an `(h, w, channels)` array without a reshape.
 
 use std::ffi::CString;
 use std::os::raw::{c_int, c_void};
@@ -12,15 +15,62 @@ use pyo3::exceptions::PyBufferError;
 use pyo3::ffi;
 use pyo3::prelude::*;
 
+use crate::decoder::SampleFormat;
+
 /// Read-only buffer over tile pixel bytes.
 #[pyclass]
 pub struct TileBuffer {
     data: Bytes,
+    sample_format: SampleFormat,
+    /// Exported shape in items (1-D `[items]` unless built `with_shape`).
+    shape: Vec<isize>,
+    /// C-contiguous byte strides matching `shape`.
+    strides: Vec<isize>,
 }
 
 impl TileBuffer {
+    /// Buffer over 8-bit samples.
     pub fn new(data: Bytes) -> Self {
-        Self { data }
+        Self::with_format(data, SampleFormat::Rgb8)
+    }
+
+    pub fn with_format(data: Bytes, sample_format: SampleFormat) -> Self {
+        let items = data.len() / sample_format.bytes_per_sample();
+        Self::with_shape(data, sample_format, &[items])
+    }
+
+    /// Buffer exported as a C-contiguous array of `shape` items.
+    ///
+    /// `shape` must multiply out to the number of samples in `data`.
+    pub fn with_shape(data: Bytes, sample_format: SampleFormat, shape: &[usize]) -> Self {
+        debug_assert_eq!(
+            shape.iter().product::<usize>() * sample_format.bytes_per_sample(),
+            data.len()
+        );
+        let mut strides = vec![0isize; shape.len()];
+        let mut stride = sample_format.bytes_per_sample() as isize;
+        for (s, &dim) in strides.iter_mut().zip(shape).rev() {
+            *s = stride;
+            stride *= dim as isize;
+        }
+        Self {
+            data,
+            sample_format,
+            shape: shape.iter().map(|&dim| dim as isize).collect(),
+            strides,
+        }
+    }
+
+    /// Exported shape and byte strides (used in tests).
+    #[cfg(test)]
+    pub fn layout(&self) -> (&[isize], &[isize]) {
+        (&self.shape, &self.strides)
+    }
+
+    /// The exported bytes (used in tests).
+    #[cfg(test)]
+    pub fn bytes(&self) -> &Bytes {
+        &self.data
     }
 }
 
@@ -30,6 +80,12 @@ impl TileBuffer {
         self.data.len()
     }
 
+    /// Sample layout: "rgb8", "bgra8", "rgba8", "gray8" or "gray16".
+    #[getter]
+    fn sample_format(&self) -> &'static str {
+        self.sample_format.name()
+    }
+
     /// Python buffer protocol: fill `view` with a pointer to our bytes.
     ///
     /// # Safety
@@ -47,9 +103,17 @@ impl TileBuffer {
             return Err(PyBufferError::new_err("Object is not writable"));
         }
 
-        let (ptr, len) = {
+        let (ptr, len, format, ndim, shape, strides) = {
             let borrowed = slf.borrow();
-            (borrowed.data.as_ref().as_ptr(), borrowed.data.len())
+            (
+                borrowed.data.as_ref().as_ptr(),
+                borrowed.data.len(),
+                borrowed.sample_format,
+                borrowed.shape.len() as c_int,
+                // Live in the pyclass, which `view.obj` keeps alive
+                borrowed.shape.as_ptr() as *mut isize,
+                borrowed.strides.as_ptr() as *mut isize,
+            )
         };
 
         // Keep `self` alive for the lifetime of the exported buffer.
@@ -58,24 +122,26 @@ impl TileBuffer {
         (*view).buf = ptr as *mut c_void;
         (*view).len = len as isize;
         (*view).readonly = 1;
-        (*view).itemsize = 1;
+        (*view).itemsize = format.bytes_per_sample() as isize;
 
         // Optional PEP 3118 format string.
         (*view).format = if (flags & ffi::PyBUF_FORMAT) == ffi::PyBUF_FORMAT {
-            CString::new("B").unwrap().into_raw()
+            CString::new(format.buffer_format()).unwrap().into_raw()
         } else {
             ptr::null_mut()
         };
 
-        (*view).ndim = 1;
-        (*view).shape = if (flags & ffi::PyBUF_ND) == ffi::PyBUF_ND {
-            &mut (*view).len
+        // Without PyBUF_ND the consumer sees plain bytes
+        if (flags & ffi::PyBUF_ND) == ffi::PyBUF_ND {
+            (*view).ndim = ndim;
+            (*view).shape = shape;
         } else {
-            ptr::null_mut()
-        };
+            (*view).ndim = 1;
+            (*view).shape = ptr::null_mut();
+        }
 
         (*view).strides = if (flags & ffi::PyBUF_STRIDES) == ffi::PyBUF_STRIDES {
-            &mut (*view).itemsize
+            strides
         } else {
             ptr::null_mut()
         };
@@ -102,3 +168,19 @@ impl TileBuffer {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_shape_uses_c_contiguous_strides() {
+        let data = Bytes::from(vec![0u8; 2 * 3 * 3]);
+        let rgb = TileBuffer::with_shape(data, SampleFormat::Rgb8, &[2, 3, 3]);
+        assert_eq!(rgb.shape, [2, 3, 3]);
+        assert_eq!(rgb.strides, [9, 3, 1]);
+
+        let gray16 = TileBuffer::with_format(Bytes::from(vec![0u8; 8]), SampleFormat::Gray16);
+        assert_eq!(gray16.shape, [4]);
+        assert_eq!(gray16.strides, [2]);
+    }
+}
@@ -3,61 +3,411 @@
 //! These APIs provide high-performance tile decoding and region assembly for plugins,
 //! avoiding Python-level loops and libvips/PIL decoding when possible.
 
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 
 use bytes::Bytes;
+use rayon::prelude::*;
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3::types::PyBytes;
 
-use crate::decoder::{decode_jpeg_bytes, CompressedTileData};
-use crate::format::SlideMetadata;
-use crate::pack::TilePack;
+use crate::cancel::CancelToken;
+use crate::decoder::{decode_tile_trimmed, CompressedTileData, SampleFormat};
+use crate::format::{LevelInfo, SlideMetadata};
+use crate::pack::{PackTileRef, TilePack};
+use crate::tile_buffer::TileBuffer;
+
+/// Default cap on decoded region size (64 MP, ~192 MB of RGB).
+const DEFAULT_MAX_REGION_PIXELS: u64 = 64 * 1024 * 1024;
+
+/// Background for region pixels without tile data, unless a caller picks one.
+const WHITE_FILL: [u8; 3] = [255, 255, 255];
 
 #[pyclass]
 pub struct FastpathTileReader {
     metadata: SlideMetadata,
     pack: TilePack,
+    /// Largest `w * h` a region decode may allocate.
+    max_region_pixels: u64,
 }
 
 fn div_floor(a: i64, b: i64) -> i64 {
     a.div_euclid(b)
 }
 
-fn decode_tile_bytes(pack: &TilePack, level: u32, col: u32, row: u32) -> crate::error::TileResult<Option<(Bytes, u32, u32)>> {
-    let tile_ref = match pack.tile_ref(level, col, row) {
-        Some(r) => r,
-        None => return Ok(None),
-    };
+pub(crate) fn decode_pack_tile(pack: &TilePack, level: u32, col: u32, row: u32, border: u32) -> crate::error::TileResult<Option<(Bytes, u32, u32)>> {
+    match pack.tile_ref(level, col, row) {
+        Some(tile_ref) => decode_tile_ref(pack, tile_ref, border).map(Some),
+        None => Ok(None),
+    }
+}
 
+/// A tile's stored bytes (JPEG, or PNG for lossless slides), undecoded.
+///
+/// zstd-packed tiles are decompressed; None for missing or empty tiles.
+fn read_pack_tile_bytes(
+    pack: &TilePack,
+    level: u32,
+    col: u32,
+    row: u32,
+) -> crate::error::TileResult<Option<Bytes>> {
+    pack.tile_ref(level, col, row)
+        .map(|tile_ref| pack.read_tile_bytes(tile_ref))
+        .transpose()
+}
+
+fn decode_tile_ref(
+    pack: &TilePack,
+    tile_ref: PackTileRef,
+    border: u32,
+) -> crate::error::TileResult<(Bytes, u32, u32)> {
     let jpeg_bytes = pack.read_tile_bytes(tile_ref)?;
     let compressed = CompressedTileData {
         jpeg_bytes,
         width: 0,
         height: 0,
     };
-    let tile = decode_jpeg_bytes(&compressed)?;
-    Ok(Some((tile.data, tile.width, tile.height)))
+    let tile = decode_tile_trimmed(&compressed, border)?;
+    Ok((tile.data, tile.width, tile.height))
 }
 
-fn decode_region_bytes(
+/// Decode every present tile of `level` in parallel, passing each to `visit`
+/// as (col, row, rgb, width, height).
+///
+/// Tiles are visited in no particular order, possibly from several threads
+/// at once. The first error (decode or from `visit`) stops the walk.
+fn for_each_level_tile<E>(
     pack: &TilePack,
-    tile_size: i64,
+    metadata: &SlideMetadata,
+    level: u32,
+    visit: impl Fn(u32, u32, Bytes, u32, u32) -> Result<(), E> + Sync,
+) -> Result<(), E>
+where
+    E: From<crate::error::TileError> + Send,
+{
+    let level_info = metadata.get_level(level).ok_or_else(|| {
+        crate::error::TileError::Validation(format!("Unknown level {}", level))
+    })?;
+    let present: Vec<(u32, u32)> = (0..level_info.rows)
+        .flat_map(|row| (0..level_info.cols).map(move |col| (col, row)))
+        .filter(|&(col, row)| pack.tile_ref(level, col, row).is_some())
+        .collect();
+
+    present.into_par_iter().try_for_each(|(col, row)| {
+        match decode_pack_tile(pack, level, col, row, metadata.tile_border)? {
+            Some((data, w, h)) => visit(col, row, data, w, h),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Reject a `w` x `h` region above `max_pixels` before anything is allocated.
+fn check_region_pixels(w: u32, h: u32, max_pixels: u64) -> crate::error::TileResult<()> {
+    let pixels = w as u64 * h as u64;
+    if pixels > max_pixels {
+        return Err(crate::error::TileError::Validation(format!(
+            "Requested region {}x{} ({} pixels) exceeds the maximum of {} pixels",
+            w, h, pixels, max_pixels
+        )));
+    }
+    Ok(())
+}
+
+/// One level's tiles, decoded on demand for region assembly.
+struct LevelTiles<'a> {
+    pack: &'a TilePack,
+    metadata: &'a SlideMetadata,
     level: u32,
+    /// Checked before each tile; once set, the read fails with
+    /// `TileError::Cancelled`.
+    cancel: Option<&'a CancelToken>,
+}
+
+impl LevelTiles<'_> {
+    fn tile_size(&self) -> i64 {
+        self.metadata.tile_size as i64
+    }
+
+    fn fetch(&self, col: u32, row: u32) -> crate::error::TileResult<Option<(Bytes, u32, u32)>> {
+        if let Some(cancel) = self.cancel {
+            cancel.check()?;
+        }
+        decode_pack_tile(self.pack, self.level, col, row, self.metadata.tile_border)
+    }
+
+    /// Decode every tile covering a region in parallel, keyed by (col, row).
+    ///
+    /// Holds all of the region's tiles at once, so only for reads that
+    /// allocate the full-size region anyway.
+    fn fetch_region(
+        &self,
+        x: i64,
+        y: i64,
+        w: u32,
+        h: u32,
+    ) -> crate::error::TileResult<DecodedTiles> {
+        region_tile_positions(self.tile_size(), x, y, w, h)?
+            .into_par_iter()
+            .map(|(col, row)| Ok(((col, row), self.fetch(col, row)?)))
+            .collect()
+    }
+}
+
+/// Decoded tiles of one region read (see `LevelTiles::fetch_region`).
+type DecodedTiles = HashMap<(u32, u32), Option<(Bytes, u32, u32)>>;
+
+/// Serve `fetch_tile` calls from tiles decoded up front.
+fn from_decoded(
+    decoded: &DecodedTiles,
+) -> impl FnMut(u32, u32) -> crate::error::TileResult<Option<(Bytes, u32, u32)>> + '_ {
+    |col, row| Ok(decoded.get(&(col, row)).cloned().flatten())
+}
+
+/// Decode an RGB region; pixels without tile data are `fill`.
+///
+/// Tiles are decoded in parallel first; only the copy into the output
+/// runs serially.
+fn decode_region_bytes(
+    tiles: &LevelTiles<'_>,
     x: i64,
     y: i64,
     w: u32,
     h: u32,
+    fill: [u8; 3],
 ) -> crate::error::TileResult<Vec<u8>> {
-    if w == 0 || h == 0 {
-        return Err(crate::error::TileError::Validation(
-            "Region width and height must be positive".into(),
-        ));
+    let decoded = tiles.fetch_region(x, y, w, h)?;
+    assemble_region_into(tiles.tile_size(), x, y, w, h, fill, from_decoded(&decoded), None)
+}
+
+/// `decode_region_bytes` box-filtered down to `out_w` x `out_h`.
+///
+/// Tiles are decoded one at a time as they are folded in, so memory stays
+/// at the output size however large the region.
+#[allow(clippy::too_many_arguments)]
+fn decode_region_scaled_bytes(
+    tiles: &LevelTiles<'_>,
+    x: i64,
+    y: i64,
+    w: u32,
+    h: u32,
+    out_w: u32,
+    out_h: u32,
+    fill: [u8; 3],
+) -> crate::error::TileResult<Vec<u8>> {
+    assemble_region_scaled(tiles.tile_size(), x, y, w, h, out_w, out_h, fill, |col, row| {
+        tiles.fetch(col, row)
+    })
+}
+
+/// Output size for a `w` x `h` region given an optional target width and/or
+/// height; a missing side keeps the region's aspect ratio.
+fn scaled_region_size(w: u32, h: u32, out_w: Option<u32>, out_h: Option<u32>) -> (u32, u32) {
+    let keep_aspect = |target: u32, from: u32, to: u32| {
+        ((target as f64 * to as f64 / from as f64).round() as u32).max(1)
+    };
+    match (out_w, out_h) {
+        (Some(ow), Some(oh)) => (ow, oh),
+        (Some(ow), None) => (ow, keep_aspect(ow, w, h)),
+        (None, Some(oh)) => (keep_aspect(oh, h, w), oh),
+        (None, None) => (w, h),
     }
-    if tile_size <= 0 {
-        return Err(crate::error::TileError::Validation(
-            "tile_size must be positive".into(),
-        ));
+}
+
+/// Like `decode_region_bytes`, also returning a `w*h` coverage mask.
+fn decode_region_with_mask_bytes(
+    tiles: &LevelTiles<'_>,
+    x: i64,
+    y: i64,
+    w: u32,
+    h: u32,
+) -> crate::error::TileResult<(Vec<u8>, Vec<u8>)> {
+    let decoded = tiles.fetch_region(x, y, w, h)?;
+    assemble_region_with_mask(tiles.tile_size(), x, y, w, h, from_decoded(&decoded))
+}
+
+/// OpenSlide-style region read: the top-left is in level-0 pixels (floored
+/// to the level's grid), the size in level pixels. Returns premultiplied
+/// RGBA: covered pixels are opaque, anything without tile data is (0,0,0,0).
+fn read_region_rgba_bytes(
+    tiles: &LevelTiles<'_>,
+    downsample: u32,
+    x0: i64,
+    y0: i64,
+    w: u32,
+    h: u32,
+) -> crate::error::TileResult<Vec<u8>> {
+    let ds = downsample.max(1) as i64;
+    let (x, y) = (div_floor(x0, ds), div_floor(y0, ds));
+    let (rgb, mask) = decode_region_with_mask_bytes(tiles, x, y, w, h)?;
+    let mut rgba = vec![0u8; mask.len() * 4];
+    for ((dst, src), &alpha) in rgba.chunks_exact_mut(4).zip(rgb.chunks_exact(3)).zip(&mask) {
+        if alpha != 0 {
+            dst[..3].copy_from_slice(src);
+            dst[3] = 255;
+        }
     }
+    Ok(rgba)
+}
+
+/// Assemble an RGB region (level coordinates) from tiles supplied by `fetch_tile`.
+///
+/// `fetch_tile(col, row)` returns the decoded tile as (bytes, width, height),
+/// or None for a missing tile. Areas without tile data are left white.
+pub(crate) fn assemble_region(
+    tile_size: i64,
+    x: i64,
+    y: i64,
+    w: u32,
+    h: u32,
+    fetch_tile: impl FnMut(u32, u32) -> crate::error::TileResult<Option<(Bytes, u32, u32)>>,
+) -> crate::error::TileResult<Vec<u8>> {
+    assemble_region_into(tile_size, x, y, w, h, WHITE_FILL, fetch_tile, None)
+}
+
+/// `assemble_region` plus a `w*h` coverage mask: 255 where a tile supplied
+/// the pixel, 0 where it is background fill.
+pub(crate) fn assemble_region_with_mask(
+    tile_size: i64,
+    x: i64,
+    y: i64,
+    w: u32,
+    h: u32,
+    fetch_tile: impl FnMut(u32, u32) -> crate::error::TileResult<Option<(Bytes, u32, u32)>>,
+) -> crate::error::TileResult<(Vec<u8>, Vec<u8>)> {
+    let mut mask = Vec::new();
+    let out =
+        assemble_region_into(tile_size, x, y, w, h, WHITE_FILL, fetch_tile, Some(&mut mask))?;
+    Ok((out, mask))
+}
+
+/// One decoded tile covering part of a region, placed relative to the
+/// region origin. `dst_x`/`dst_y` are negative when the tile starts before it.
+pub(crate) struct RegionTile {
+    pub data: Bytes,
+    pub width: u32,
+    pub height: u32,
+    pub dst_x: i64,
+    pub dst_y: i64,
+}
+
+/// The tiles `assemble_region` would copy from, unstitched.
+///
+/// Each tile keeps its own buffer (no region-sized allocation) so a tiled
+/// renderer can upload them individually. Missing tiles are omitted.
+pub(crate) fn region_tiles(
+    tile_size: i64,
+    x: i64,
+    y: i64,
+    w: u32,
+    h: u32,
+    mut fetch_tile: impl FnMut(u32, u32) -> crate::error::TileResult<Option<(Bytes, u32, u32)>>,
+) -> crate::error::TileResult<Vec<RegionTile>> {
+    let span = TileSpan::new(tile_size, x, y, w, h)?;
+    let mut tiles = Vec::new();
+    for (c, r) in span.tiles() {
+        let Some((data, width, height)) = fetch_tile(c as u32, r as u32)? else {
+            continue;
+        };
+        let (tile_x, tile_y) = span.tile_origin(c, r)?;
+        let (right, bottom) = (tile_x + width as i64, tile_y + height as i64);
+        if width == 0 || height == 0 || right <= x || bottom <= y {
+            continue;
+        }
+        tiles.push(RegionTile {
+            data,
+            width,
+            height,
+            dst_x: tile_x - x,
+            dst_y: tile_y - y,
+        });
+    }
+    Ok(tiles)
+}
+
+/// Grid positions (col, row) of the tiles covering a region, row-major.
+///
+/// Positions left of or above the grid are skipped; callers clip to the
+/// level's column/row counts.
+pub(crate) fn region_tile_positions(
+    tile_size: i64,
+    x: i64,
+    y: i64,
+    w: u32,
+    h: u32,
+) -> crate::error::TileResult<Vec<(u32, u32)>> {
+    let span = TileSpan::new(tile_size, x, y, w, h)?;
+    Ok(span
+        .tiles()
+        .filter(|&(c, r)| c <= u32::MAX as i64 && r <= u32::MAX as i64)
+        .map(|(c, r)| (c as u32, r as u32))
+        .collect())
+}
+
+/// Range of grid tiles a region in level coordinates touches.
+struct TileSpan {
+    tile_size: i64,
+    cols: std::ops::Range<i64>,
+    rows: std::ops::Range<i64>,
+}
+
+impl TileSpan {
+    fn new(tile_size: i64, x: i64, y: i64, w: u32, h: u32) -> crate::error::TileResult<Self> {
+        if w == 0 || h == 0 {
+            return Err(crate::error::TileError::Validation(
+                "Region width and height must be positive".into(),
+            ));
+        }
+        if tile_size <= 0 {
+            return Err(crate::error::TileError::Validation(
+                "tile_size must be positive".into(),
+            ));
+        }
+        let x2 = x
+            .checked_add(w as i64)
+            .ok_or_else(|| crate::error::TileError::Validation("x+w overflow".into()))?;
+        let y2 = y
+            .checked_add(h as i64)
+            .ok_or_else(|| crate::error::TileError::Validation("y+h overflow".into()))?;
+        Ok(Self {
+            tile_size,
+            cols: div_floor(x, tile_size)..div_floor(x2 - 1, tile_size) + 1,
+            rows: div_floor(y, tile_size)..div_floor(y2 - 1, tile_size) + 1,
+        })
+    }
+
+    /// Grid positions in row-major order, skipping negative columns/rows.
+    fn tiles(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.rows
+            .clone()
+            .flat_map(move |r| self.cols.clone().map(move |c| (c, r)))
+            .filter(|&(c, r)| c >= 0 && r >= 0)
+    }
+
+    /// Level-coordinate origin of tile (c, r).
+    fn tile_origin(&self, c: i64, r: i64) -> crate::error::TileResult<(i64, i64)> {
+        let tile_x = c
+            .checked_mul(self.tile_size)
+            .ok_or_else(|| crate::error::TileError::Validation("tile_x overflow".into()))?;
+        let tile_y = r
+            .checked_mul(self.tile_size)
+            .ok_or_else(|| crate::error::TileError::Validation("tile_y overflow".into()))?;
+        Ok((tile_x, tile_y))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn assemble_region_into(
+    tile_size: i64,
+    x: i64,
+    y: i64,
+    w: u32,
+    h: u32,
+    fill: [u8; 3],
+    fetch_tile: impl FnMut(u32, u32) -> crate::error::TileResult<Option<(Bytes, u32, u32)>>,
+    mut mask: Option<&mut Vec<u8>>,
+) -> crate::error::TileResult<Vec<u8>> {
+    let span = TileSpan::new(tile_size, x, y, w, h)?;
 
     let out_w = w as usize;
     let out_h = h as usize;
@@ -68,77 +418,252 @@ fn decode_region_bytes(
             crate::error::TileError::Validation("Requested region is too large".into())
         })?;
 
-    let mut out = vec![255u8; out_len];
+    let mut out = fill.repeat(out_len / 3);
+    if let Some(mask) = mask.as_deref_mut() {
+        *mask = vec![0u8; out_w * out_h];
+    }
 
-    let x2 = x
-        .checked_add(w as i64)
-        .ok_or_else(|| crate::error::TileError::Validation("x+w overflow".into()))?;
-    let y2 = y
-        .checked_add(h as i64)
-        .ok_or_else(|| crate::error::TileError::Validation("y+h overflow".into()))?;
+    for_each_overlap(&span, x, y, w, h, fetch_tile, |overlap| {
+        for row in 0..overlap.copy_h {
+            let dst_row_start = ((overlap.dst_y + row) * out_w + overlap.dst_x) * 3;
+            let src = overlap.src_row(row);
+            out[dst_row_start..dst_row_start + src.len()].copy_from_slice(src);
+            if let Some(mask) = mask.as_deref_mut() {
+                let mask_start = (overlap.dst_y + row) * out_w + overlap.dst_x;
+                mask[mask_start..mask_start + overlap.copy_w].fill(255);
+            }
+        }
+    })?;
 
-    let col_start = div_floor(x, tile_size);
-    let col_end = div_floor(x2 - 1, tile_size) + 1;
-    let row_start = div_floor(y, tile_size);
-    let row_end = div_floor(y2 - 1, tile_size) + 1;
+    Ok(out)
+}
 
-    for r in row_start..row_end {
-        for c in col_start..col_end {
-            if c < 0 || r < 0 {
-                continue;
-            }
+/// The part of one decoded RGB tile that falls inside a region.
+struct TileOverlap<'a> {
+    tile: &'a [u8],
+    tile_w: usize,
+    src_x: usize,
+    src_y: usize,
+    /// Offset of the overlap from the region origin.
+    dst_x: usize,
+    dst_y: usize,
+    copy_w: usize,
+    copy_h: usize,
+}
 
-            let Some((tile_bytes, tile_w_u32, tile_h_u32)) =
-                decode_tile_bytes(pack, level, c as u32, r as u32)?
-            else {
-                continue;
-            };
+impl TileOverlap<'_> {
+    /// RGB bytes of overlap row `row`.
+    fn src_row(&self, row: usize) -> &[u8] {
+        let start = ((self.src_y + row) * self.tile_w + self.src_x) * 3;
+        &self.tile[start..start + self.copy_w * 3]
+    }
+}
 
-            let tile_w = tile_w_u32 as i64;
-            let tile_h = tile_h_u32 as i64;
-            if tile_w <= 0 || tile_h <= 0 {
-                continue;
-            }
+/// Fetch each tile of `span` and pass the part inside the region to `visit`.
+fn for_each_overlap(
+    span: &TileSpan,
+    x: i64,
+    y: i64,
+    w: u32,
+    h: u32,
+    mut fetch_tile: impl FnMut(u32, u32) -> crate::error::TileResult<Option<(Bytes, u32, u32)>>,
+    mut visit: impl FnMut(&TileOverlap<'_>),
+) -> crate::error::TileResult<()> {
+    // Already checked by TileSpan::new
+    let x2 = x + w as i64;
+    let y2 = y + h as i64;
 
-            let tile_x = c
-                .checked_mul(tile_size)
-                .ok_or_else(|| crate::error::TileError::Validation("tile_x overflow".into()))?;
-            let tile_y = r
-                .checked_mul(tile_size)
-                .ok_or_else(|| crate::error::TileError::Validation("tile_y overflow".into()))?;
-
-            // Intersection in level coordinates.
-            let left = x.max(tile_x);
-            let top = y.max(tile_y);
-            let right = x2.min(tile_x + tile_w);
-            let bottom = y2.min(tile_y + tile_h);
-
-            if left >= right || top >= bottom {
-                continue;
-            }
+    for (c, r) in span.tiles() {
+        let Some((tile_bytes, tile_w_u32, tile_h_u32)) = fetch_tile(c as u32, r as u32)? else {
+            continue;
+        };
 
-            let copy_w = (right - left) as usize;
-            let copy_h = (bottom - top) as usize;
-            let src_x = (left - tile_x) as usize;
-            let src_y = (top - tile_y) as usize;
-            let dst_x = (left - x) as usize;
-            let dst_y = (top - y) as usize;
-
-            let tile_w_usize: usize = tile_w_u32 as usize;
-
-            for row in 0..copy_h {
-                let src_row_start = ((src_y + row) * tile_w_usize + src_x) * 3;
-                let dst_row_start = ((dst_y + row) * out_w + dst_x) * 3;
-                let byte_len = copy_w * 3;
-                out[dst_row_start..dst_row_start + byte_len]
-                    .copy_from_slice(&tile_bytes[src_row_start..src_row_start + byte_len]);
-            }
+        let tile_w = tile_w_u32 as i64;
+        let tile_h = tile_h_u32 as i64;
+        if tile_w <= 0 || tile_h <= 0 {
+            continue;
+        }
+
+        let (tile_x, tile_y) = span.tile_origin(c, r)?;
+
+        // Intersection in level coordinates.
+        let left = x.max(tile_x);
+        let top = y.max(tile_y);
+        let right = x2.min(tile_x + tile_w);
+        let bottom = y2.min(tile_y + tile_h);
+
+        if left >= right || top >= bottom {
+            continue;
         }
+
+        visit(&TileOverlap {
+            tile: &tile_bytes,
+            tile_w: tile_w_u32 as usize,
+            src_x: (left - tile_x) as usize,
+            src_y: (top - tile_y) as usize,
+            dst_x: (left - x) as usize,
+            dst_y: (top - y) as usize,
+            copy_w: (right - left) as usize,
+            copy_h: (bottom - top) as usize,
+        });
+    }
+    Ok(())
+}
+
+/// Like `assemble_region`, but box-filtered down to `out_w` x `out_h` as
+/// tiles arrive, so the full-size region is never allocated.
+///
+/// Each output pixel averages the block of region pixels mapped onto it;
+/// areas without tile data count as `fill` in that average, matching a
+/// full-size decode with the same fill.
+#[allow(clippy::too_many_arguments)]
+fn assemble_region_scaled(
+    tile_size: i64,
+    x: i64,
+    y: i64,
+    w: u32,
+    h: u32,
+    out_w: u32,
+    out_h: u32,
+    fill: [u8; 3],
+    fetch_tile: impl FnMut(u32, u32) -> crate::error::TileResult<Option<(Bytes, u32, u32)>>,
+) -> crate::error::TileResult<Vec<u8>> {
+    if out_w == 0 || out_h == 0 || out_w > w || out_h > h {
+        return Err(crate::error::TileError::Validation(format!(
+            "Output size {}x{} must be positive and no larger than the {}x{} region",
+            out_w, out_h, w, h
+        )));
     }
+    let span = TileSpan::new(tile_size, x, y, w, h)?;
 
+    // Output column (row) of each region column (row), with block sizes
+    let (col_map, col_sizes) = box_map(w, out_w);
+    let (row_map, row_sizes) = box_map(h, out_h);
+
+    let out_pixels = out_w as usize * out_h as usize;
+    let mut sums = vec![0u64; out_pixels * 3];
+    let mut covered = vec![0u64; out_pixels];
+    for_each_overlap(&span, x, y, w, h, fetch_tile, |overlap| {
+        for row in 0..overlap.copy_h {
+            let out_row = row_map[overlap.dst_y + row] as usize * out_w as usize;
+            let cols = &col_map[overlap.dst_x..overlap.dst_x + overlap.copy_w];
+            for (px, &out_col) in overlap.src_row(row).chunks_exact(3).zip(cols) {
+                let o = out_row + out_col as usize;
+                for (sum, &v) in sums[o * 3..o * 3 + 3].iter_mut().zip(px) {
+                    *sum += v as u64;
+                }
+                covered[o] += 1;
+            }
+        }
+    })?;
+
+    let mut out = vec![0u8; out_pixels * 3];
+    for (o, px) in out.chunks_exact_mut(3).enumerate() {
+        let area = row_sizes[o / out_w as usize] * col_sizes[o % out_w as usize];
+        let uncovered = area - covered[o];
+        for (c, v) in px.iter_mut().enumerate() {
+            let background = uncovered * fill[c] as u64;
+            *v = ((sums[o * 3 + c] + background + area / 2) / area) as u8;
+        }
+    }
     Ok(out)
 }
 
+/// Map each of `len` positions onto one of `out_len` evenly sized blocks.
+///
+/// Returns the block of each position and each block's size; blocks differ
+/// in size by at most one and are never empty when `out_len <= len`.
+fn box_map(len: u32, out_len: u32) -> (Vec<u32>, Vec<u64>) {
+    let map: Vec<u32> = (0..len as u64)
+        .map(|i| (i * out_len as u64 / len as u64) as u32)
+        .collect();
+    let mut sizes = vec![0u64; out_len as usize];
+    for &b in &map {
+        sizes[b as usize] += 1;
+    }
+    (map, sizes)
+}
+
+impl FastpathTileReader {
+    /// `level`'s info, or the ValueError region reads raise for an unknown level.
+    fn level_info(&self, level: u32) -> PyResult<&LevelInfo> {
+        self.metadata
+            .get_level(level)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown level {level}")))
+    }
+
+    fn level_tiles<'a>(&'a self, level: u32, cancel: Option<&'a CancelToken>) -> LevelTiles<'a> {
+        LevelTiles {
+            pack: &self.pack,
+            metadata: &self.metadata,
+            level,
+            cancel,
+        }
+    }
+
+    /// Shared body of `decode_region` and `decode_region_buffer`: the RGB
+    /// bytes and their (width, height). Call with the GIL released.
+    #[allow(clippy::too_many_arguments)]
+    fn decode_region_rgb(
+        &self,
+        level: u32,
+        x: i64,
+        y: i64,
+        w: u32,
+        h: u32,
+        out_w: Option<u32>,
+        out_h: Option<u32>,
+        cancel: Option<&CancelToken>,
+        fill: Option<(u8, u8, u8)>,
+    ) -> crate::error::TileResult<(Vec<u8>, u32, u32)> {
+        let fill = fill.map_or(WHITE_FILL, |(r, g, b)| [r, g, b]);
+        let (ow, oh) = scaled_region_size(w, h, out_w, out_h);
+        check_region_pixels(ow, oh, self.max_region_pixels)?;
+        let tiles = self.level_tiles(level, cancel);
+        let data = if (ow, oh) == (w, h) {
+            decode_region_bytes(&tiles, x, y, w, h, fill)?
+        } else {
+            decode_region_scaled_bytes(&tiles, x, y, w, h, ow, oh, fill)?
+        };
+        Ok((data, ow, oh))
+    }
+}
+
+/// Wrap `width` x `height` RGB bytes in a buffer shaped (height, width, 3).
+fn region_buffer(data: Vec<u8>, width: u32, height: u32) -> TileBuffer {
+    let shape = [height as usize, width as usize, 3];
+    TileBuffer::with_shape(Bytes::from(data), SampleFormat::Rgb8, &shape)
+}
+
+/// (col, row, rgb, width, height) as yielded by `LevelTileIter`.
+type LevelTileEntry<'py> = (u32, u32, Bound<'py, PyBytes>, u32, u32);
+
+/// Iterator over a level's tiles in pack order, from
+/// `FastpathTileReader.iter_level_tiles`.
+#[pyclass]
+pub struct LevelTileIter {
+    reader: Py<FastpathTileReader>,
+    tiles: std::vec::IntoIter<(u32, u32, PackTileRef)>,
+}
+
+#[pymethods]
+impl LevelTileIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Decode the next tile (without the GIL) as (col, row, bytes, w, h).
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<LevelTileEntry<'py>>> {
+        let Some((col, row, tile_ref)) = self.tiles.next() else {
+            return Ok(None);
+        };
+        let reader = self.reader.borrow(py);
+        let (pack, border) = (&reader.pack, reader.metadata.tile_border);
+        let (data, w, h) = py.allow_threads(|| decode_tile_ref(pack, tile_ref, border))?;
+        Ok(Some((col, row, PyBytes::new(py, &data), w, h)))
+    }
+}
+
 #[pymethods]
 impl FastpathTileReader {
     #[new]
@@ -146,7 +671,30 @@ impl FastpathTileReader {
         let path_buf = PathBuf::from(path);
         let metadata = SlideMetadata::load(&path_buf)?;
         let pack = TilePack::open(&path_buf)?;
-        Ok(Self { metadata, pack })
+        Ok(Self {
+            metadata,
+            pack,
+            max_region_pixels: DEFAULT_MAX_REGION_PIXELS,
+        })
+    }
+
+    /// Largest region (width * height, in pixels) decode_region will produce.
+    #[getter]
+    fn max_region_pixels(&self) -> u64 {
+        self.max_region_pixels
+    }
+
+    /// Set the region size limit; larger decode requests raise instead of
+    /// attempting the allocation.
+    ///
+    /// Raises:
+    ///   ValueError: If n is zero.
+    fn set_max_region_pixels(&mut self, n: u64) -> PyResult<()> {
+        if n == 0 {
+            return Err(PyValueError::new_err("max_region_pixels must be positive"));
+        }
+        self.max_region_pixels = n;
+        Ok(())
     }
 
     /// Tile size in pixels.
@@ -155,6 +703,14 @@ impl FastpathTileReader {
         self.metadata.tile_size
     }
 
+    /// Stored tile bytes per level, read from the pack index (no tile reads).
+    ///
+    /// Returns:
+    ///   Dict mapping level number to total bytes of its tiles.
+    fn level_byte_sizes(&self) -> BTreeMap<u32, u64> {
+        self.pack.level_byte_sizes().into_iter().collect()
+    }
+
     /// Decode a single tile to raw RGB bytes.
     ///
     /// Returns (bytes, width, height) or None if missing/out-of-bounds.
@@ -165,22 +721,111 @@ impl FastpathTileReader {
         col: u32,
         row: u32,
     ) -> PyResult<Option<(Bound<'py, PyBytes>, u32, u32)>> {
-        let decoded = py.allow_threads(|| decode_tile_bytes(&self.pack, level, col, row));
+        let decoded = py.allow_threads(|| decode_pack_tile(&self.pack, level, col, row, self.metadata.tile_border));
         match decoded? {
             Some((data, w, h)) => Ok(Some((PyBytes::new(py, &data), w, h))),
             None => Ok(None),
         }
     }
 
+    /// Read a tile's compressed bytes as stored in the pack, without decoding.
+    ///
+    /// For pipelines that decode elsewhere (e.g. nvJPEG on the GPU). The
+    /// bytes are the full stored tile, including any tile_border overlap.
+    ///
+    /// Returns:
+    ///   JPEG bytes (PNG for lossless slides), or None if the tile is
+    ///   missing, empty or out of bounds.
+    ///
+    /// Raises:
+    ///   RuntimeError: If the pack can't be read.
+    fn read_tile_jpeg<'py>(
+        &self,
+        py: Python<'py>,
+        level: u32,
+        col: u32,
+        row: u32,
+    ) -> PyResult<Option<Bound<'py, PyBytes>>> {
+        let stored = py.allow_threads(|| read_pack_tile_bytes(&self.pack, level, col, row))?;
+        Ok(stored.map(|bytes| PyBytes::new(py, &bytes)))
+    }
+
+    /// Decode every present tile of a level in parallel and pass each to
+    /// ``callback(col, row, rgb_buffer, width, height)``.
+    ///
+    /// For whole-level passes (e.g. tissue masks) without holding the level
+    /// in memory. Calls arrive in no particular order; each holds the GIL, so
+    /// they never overlap. An exception from the callback stops the walk and
+    /// is re-raised.
+    ///
+    /// Args:
+    ///   level: Pyramid level number.
+    ///   callback: Called with (col, row, TileBuffer of RGB bytes, w, h).
+    ///
+    /// Raises:
+    ///   RuntimeError: If the level is unknown or a tile fails to decode.
+    fn for_each_tile(&self, py: Python<'_>, level: u32, callback: PyObject) -> PyResult<()> {
+        py.allow_threads(|| {
+            for_each_level_tile(&self.pack, &self.metadata, level, |col, row, data, w, h| {
+                Python::with_gil(|py| {
+                    let buf = Py::new(py, TileBuffer::new(data))?;
+                    callback.call1(py, (col, row, buf, w, h))?;
+                    Ok::<(), PyErr>(())
+                })
+            })
+        })
+    }
+
+    /// Iterate every present tile of a level once, in pack file order.
+    ///
+    /// Tiles are read sequentially from the level's .pack, so OS readahead
+    /// works for whole-level passes (e.g. feature extraction). Each tile is
+    /// decoded with the GIL released.
+    ///
+    /// Args:
+    ///   level: Pyramid level number.
+    ///
+    /// Returns:
+    ///   Iterator of (col, row, bytes, width, height) with RGB bytes.
+    ///
+    /// Raises:
+    ///   RuntimeError: If the level is unknown, or (while iterating) a tile
+    ///     fails to read or decode.
+    fn iter_level_tiles(slf: &Bound<'_, Self>, level: u32) -> PyResult<LevelTileIter> {
+        let tiles = slf.borrow().pack.level_tiles_by_offset(level).ok_or_else(|| {
+            crate::error::TileError::Validation(format!("Unknown level {}", level))
+        })?;
+        Ok(LevelTileIter {
+            reader: slf.clone().unbind(),
+            tiles: tiles.into_iter(),
+        })
+    }
+
     /// Decode a region (level coordinates) to raw RGB bytes.
     ///
+    /// With out_w and/or out_h the region is box-filtered down while tiles
+    /// are assembled, so a large region can be rendered small without
+    /// allocating it at full size. Giving only one side keeps the aspect ratio.
+    ///
     /// Args:
     ///   level: Pyramid level number.
     ///   x, y: Top-left in level pixels (may be negative).
     ///   w, h: Region size in pixels (must be positive).
+    ///   out_w, out_h: Output size, at most w x h (default: w x h).
+    ///   cancel: Optional CancelToken; cancelling it from another thread
+    ///     stops the read before the next tile.
+    ///   fill: (r, g, b) for pixels outside the slide or in missing tiles
+    ///     (default: white). Use (0, 0, 0) for darkfield/fluorescence slides.
     ///
     /// Returns:
-    ///   bytes of length w*h*3 in row-major RGB order.
+    ///   bytes of length out_w*out_h*3 in row-major RGB order.
+    ///
+    /// Raises:
+    ///   ValueError: If the level doesn't exist.
+    ///   RuntimeError: If the output size exceeds max_region_pixels or is
+    ///     larger than the region, or the read was cancelled.
+    #[pyo3(signature = (level, x, y, w, h, out_w=None, out_h=None, cancel=None, fill=None))]
+    #[allow(clippy::too_many_arguments)]
     fn decode_region<'py>(
         &self,
         py: Python<'py>,
@@ -189,10 +834,379 @@ impl FastpathTileReader {
         y: i64,
         w: u32,
         h: u32,
+        out_w: Option<u32>,
+        out_h: Option<u32>,
+        cancel: Option<Py<CancelToken>>,
+        fill: Option<(u8, u8, u8)>,
     ) -> PyResult<Bound<'py, PyBytes>> {
-        let tile_size = self.metadata.tile_size as i64;
-        let data = py.allow_threads(|| decode_region_bytes(&self.pack, tile_size, level, x, y, w, h))?;
+        self.level_info(level)?;
+        let cancel = cancel.as_ref().map(Py::get);
+        let (data, _, _) = py.allow_threads(|| {
+            self.decode_region_rgb(level, x, y, w, h, out_w, out_h, cancel, fill)
+        })?;
         Ok(PyBytes::new(py, &data))
     }
+
+    /// Decode a region like decode_region into a buffer, without copying.
+    ///
+    /// The assembled pixels are handed to Python as they are instead of
+    /// being copied into ``bytes``, which saves a multi-megabyte copy per
+    /// call on large regions. The buffer exports shape (height, width, 3),
+    /// so ``np.asarray(buf)`` or a QImage can wrap it directly.
+    ///
+    /// Args:
+    ///   Same as decode_region.
+    ///
+    /// Returns:
+    ///   TileBuffer of out_h*out_w*3 RGB bytes shaped (out_h, out_w, 3).
+    ///
+    /// Raises:
+    ///   ValueError, RuntimeError: As for decode_region.
+    #[pyo3(signature = (level, x, y, w, h, out_w=None, out_h=None, cancel=None, fill=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn decode_region_buffer(
+        &self,
+        py: Python<'_>,
+        level: u32,
+        x: i64,
+        y: i64,
+        w: u32,
+        h: u32,
+        out_w: Option<u32>,
+        out_h: Option<u32>,
+        cancel: Option<Py<CancelToken>>,
+        fill: Option<(u8, u8, u8)>,
+    ) -> PyResult<TileBuffer> {
+        self.level_info(level)?;
+        let cancel = cancel.as_ref().map(Py::get);
+        let (data, ow, oh) = py.allow_threads(|| {
+            self.decode_region_rgb(level, x, y, w, h, out_w, out_h, cancel, fill)
+        })?;
+        Ok(region_buffer(data, ow, oh))
+    }
+
+    /// Read a region the way OpenSlide's ``read_region`` does.
+    ///
+    /// Unlike decode_region, the location is in level-0 pixels and the result
+    /// is RGBA: pixels with tile data have alpha 255, pixels outside the
+    /// slide or in missing tiles are transparent (0, 0, 0, 0) rather than
+    /// white. Values are premultiplied, as OpenSlide returns them.
+    ///
+    /// Args:
+    ///   location: (x, y) top-left in level-0 pixels; divided by the level's
+    ///     downsample (rounding down).
+    ///   level: Pyramid level number.
+    ///   size: (w, h) region size in level pixels (must be positive).
+    ///   cancel: Optional CancelToken, as for decode_region.
+    ///
+    /// Returns:
+    ///   bytes of length w*h*4 in row-major RGBA order.
+    ///
+    /// Raises:
+    ///   ValueError: If the level doesn't exist.
+    ///   RuntimeError: If w*h exceeds max_region_pixels or the read was
+    ///     cancelled.
+    #[pyo3(signature = (location, level, size, cancel=None))]
+    fn read_region<'py>(
+        &self,
+        py: Python<'py>,
+        location: (i64, i64),
+        level: u32,
+        size: (u32, u32),
+        cancel: Option<Py<CancelToken>>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let downsample = self.level_info(level)?.downsample;
+        let ((x0, y0), (w, h)) = (location, size);
+        check_region_pixels(w, h, self.max_region_pixels)?;
+        let tiles = self.level_tiles(level, cancel.as_ref().map(Py::get));
+        let data =
+            py.allow_threads(|| read_region_rgba_bytes(&tiles, downsample, x0, y0, w, h))?;
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Decode a region like decode_region, plus a coverage mask.
+    ///
+    /// Args:
+    ///   level: Pyramid level number.
+    ///   x, y: Top-left in level pixels (may be negative).
+    ///   w, h: Region size in pixels (must be positive).
+    ///   cancel: Optional CancelToken, as for decode_region.
+    ///
+    /// Returns:
+    ///   (rgb, mask): rgb as from decode_region; mask is w*h bytes, 255 where
+    ///   a tile covered the pixel and 0 where it is white background fill.
+    ///
+    /// Raises:
+    ///   ValueError: If the level doesn't exist.
+    ///   RuntimeError: If w*h exceeds max_region_pixels or the read was
+    ///     cancelled.
+    #[pyo3(signature = (level, x, y, w, h, cancel=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn decode_region_with_mask<'py>(
+        &self,
+        py: Python<'py>,
+        level: u32,
+        x: i64,
+        y: i64,
+        w: u32,
+        h: u32,
+        cancel: Option<Py<CancelToken>>,
+    ) -> PyResult<(Bound<'py, PyBytes>, Bound<'py, PyBytes>)> {
+        self.level_info(level)?;
+        check_region_pixels(w, h, self.max_region_pixels)?;
+        let tiles = self.level_tiles(level, cancel.as_ref().map(Py::get));
+        let (data, mask) =
+            py.allow_threads(|| decode_region_with_mask_bytes(&tiles, x, y, w, h))?;
+        Ok((PyBytes::new(py, &data), PyBytes::new(py, &mask)))
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::test_utils::{
+        create_test_fastpath_bordered, create_test_fastpath_with_tiles, test_jpeg_bytes,
+    };
+
+    #[test]
+    fn test_for_each_level_tile_visits_present_tiles() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Mutex;
+
+        use crate::error::TileError;
+
+        let temp = TempDir::new().unwrap();
+        create_test_fastpath_bordered(temp.path());
+        let metadata = SlideMetadata::load(temp.path()).unwrap();
+        let pack = TilePack::open(temp.path()).unwrap();
+
+        let calls = AtomicUsize::new(0);
+        let seen = Mutex::new(Vec::new());
+        for_each_level_tile::<TileError>(&pack, &metadata, 0, |col, row, data, w, h| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            assert_eq!(data.len(), (w * h * 3) as usize);
+            seen.lock().unwrap().push((col, row));
+            Ok(())
+        })
+        .unwrap();
+
+        let present = (0..2).filter(|&col| pack.tile_ref(0, col, 0).is_some()).count();
+        assert_eq!(calls.load(Ordering::Relaxed), present);
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort_unstable();
+        assert_eq!(seen, vec![(0, 0), (1, 0)]);
+
+        let unknown_level =
+            for_each_level_tile::<TileError>(&pack, &metadata, 9, |_, _, _, _, _| Ok(()));
+        assert!(unknown_level.is_err());
+    }
+
+    #[test]
+    fn test_read_pack_tile_bytes_returns_stored_jpeg() {
+        let temp = TempDir::new().unwrap();
+        create_test_fastpath_with_tiles(temp.path());
+        let pack = TilePack::open(temp.path()).unwrap();
+
+        let stored = read_pack_tile_bytes(&pack, 1, 1, 1).unwrap().unwrap();
+        assert_eq!(stored.as_ref(), test_jpeg_bytes().as_slice());
+        assert!(read_pack_tile_bytes(&pack, 1, 2, 0).unwrap().is_none());
+        assert!(read_pack_tile_bytes(&pack, 7, 0, 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_region_over_pixel_limit_is_rejected() {
+        // 100k x 100k would need ~30 GB; refused without allocating
+        let err = check_region_pixels(100_000, 100_000, DEFAULT_MAX_REGION_PIXELS).unwrap_err();
+        assert!(matches!(err, crate::error::TileError::Validation(_)));
+        assert!(err.to_string().contains("exceeds the maximum"), "{err}");
+
+        assert!(check_region_pixels(8192, 8192, DEFAULT_MAX_REGION_PIXELS).is_ok());
+        assert!(check_region_pixels(32, 32, 1024).is_ok());
+        assert!(check_region_pixels(32, 33, 1024).is_err());
+    }
+
+    #[test]
+    fn test_decode_region_with_mask_partially_outside() {
+        let temp = TempDir::new().unwrap();
+        create_test_fastpath_bordered(temp.path());
+        let metadata = SlideMetadata::load(temp.path()).unwrap();
+        let pack = TilePack::open(temp.path()).unwrap();
+        let tiles = LevelTiles { pack: &pack, metadata: &metadata, level: 0, cancel: None };
+
+        // Level 0 is two 2x2 tiles wide; start one pixel up-left of the slide
+        // and run one pixel past its right edge
+        let (rgb, mask) =
+            decode_region_with_mask_bytes(&tiles, -1, -1, 6, 3).unwrap();
+        assert_eq!(rgb.len(), 6 * 3 * 3);
+        assert_eq!(mask.len(), 6 * 3);
+
+        #[rustfmt::skip]
+        let expected = [
+            0,   0,   0,   0,   0, 0,
+            0, 255, 255, 255, 255, 0,
+            0, 255, 255, 255, 255, 0,
+        ];
+        assert_eq!(mask, expected);
+
+        // Matches decode_region, with uncovered pixels left white
+        assert_eq!(rgb, decode_region_bytes(&tiles, -1, -1, 6, 3, WHITE_FILL).unwrap());
+        assert_eq!(&rgb[..3], &[255, 255, 255]);
+        let covered = (6 + 1) * 3;
+        assert_ne!(&rgb[covered..covered + 3], &[255, 255, 255]);
+    }
+
+    #[test]
+    fn test_decode_region_buffer_shape_and_bytes_match_decode_region() {
+        let temp = TempDir::new().unwrap();
+        create_test_fastpath_bordered(temp.path());
+        let reader = FastpathTileReader {
+            metadata: SlideMetadata::load(temp.path()).unwrap(),
+            pack: TilePack::open(temp.path()).unwrap(),
+            max_region_pixels: DEFAULT_MAX_REGION_PIXELS,
+        };
+        let tiles = reader.level_tiles(0, None);
+
+        // Full size and scaled down, both wider than tall so a swapped
+        // width/height would show
+        for (out_w, out_h) in [(None, None), (Some(3), None)] {
+            let (data, w, h) = reader
+                .decode_region_rgb(0, -1, -1, 6, 3, out_w, out_h, None, None)
+                .unwrap();
+            let expected = match out_w {
+                None => decode_region_bytes(&tiles, -1, -1, 6, 3, WHITE_FILL),
+                Some(_) => decode_region_scaled_bytes(&tiles, -1, -1, 6, 3, w, h, WHITE_FILL),
+            };
+            assert_eq!(data, expected.unwrap());
+
+            let buffer = region_buffer(data.clone(), w, h);
+            let (shape, strides) = buffer.layout();
+            assert_eq!(shape, [h as isize, w as isize, 3]);
+            assert_eq!(strides, [w as isize * 3, 3, 1]);
+            assert_eq!(buffer.bytes().as_ref(), data.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_decode_region_uses_fill_for_uncovered_pixels() {
+        let temp = TempDir::new().unwrap();
+        create_test_fastpath_bordered(temp.path());
+        let metadata = SlideMetadata::load(temp.path()).unwrap();
+        let pack = TilePack::open(temp.path()).unwrap();
+        let tiles = LevelTiles { pack: &pack, metadata: &metadata, level: 0, cancel: None };
+
+        let black = decode_region_bytes(&tiles, -1, -1, 6, 3, [0, 0, 0]).unwrap();
+        let (white, mask) = decode_region_with_mask_bytes(&tiles, -1, -1, 6, 3).unwrap();
+        for (i, &covered) in mask.iter().enumerate() {
+            let px = &black[i * 3..i * 3 + 3];
+            if covered == 0 {
+                assert_eq!(px, [0, 0, 0], "pixel {i}");
+            } else {
+                assert_eq!(px, &white[i * 3..i * 3 + 3], "pixel {i}");
+            }
+        }
+
+        // Wholly outside the slide, a scaled read is all fill
+        let teal = decode_region_scaled_bytes(&tiles, 10, 10, 4, 4, 2, 2, [0, 128, 128]).unwrap();
+        assert_eq!(teal, [0, 128, 128].repeat(4));
+    }
+
+    #[test]
+    fn test_read_region_rgba_uses_level0_origin_and_clear_fill() {
+        let temp = TempDir::new().unwrap();
+        create_test_fastpath_bordered(temp.path());
+        let metadata = SlideMetadata::load(temp.path()).unwrap();
+        let pack = TilePack::open(temp.path()).unwrap();
+        let tiles = LevelTiles { pack: &pack, metadata: &metadata, level: 0, cancel: None };
+
+        // With downsample 2, level-0 (-2, -3) is level (-1, -2): the region
+        // has two clear rows, then the slide's first row after a clear pixel
+        let rgba = read_region_rgba_bytes(&tiles, 2, -2, -3, 6, 3).unwrap();
+        assert_eq!(rgba.len(), 6 * 3 * 4);
+        let (rgb, mask) = decode_region_with_mask_bytes(&tiles, -1, -2, 6, 3).unwrap();
+        for (i, px) in rgba.chunks_exact(4).enumerate() {
+            if mask[i] == 0 {
+                assert_eq!(px, [0, 0, 0, 0], "pixel {i}");
+            } else {
+                assert_eq!(&px[..3], &rgb[i * 3..i * 3 + 3], "pixel {i}");
+                assert_eq!(px[3], 255);
+            }
+        }
+        assert_eq!(mask.iter().filter(|&&m| m != 0).count(), 4);
+    }
+
+    #[test]
+    fn test_assemble_region_scaled_matches_box_filtered_full_region() {
+        // 3x3 px tiles with a per-tile gradient; tile (1, 0) is missing
+        let fetch = |col: u32, row: u32| {
+            if (col, row) == (1, 0) {
+                return Ok(None);
+            }
+            let data: Vec<u8> = (0..9u8)
+                .flat_map(|i| [i * 20 + col as u8, i * 10 + row as u8, 100])
+                .collect();
+            Ok(Some((Bytes::from(data), 3, 3)))
+        };
+        let (x, y, w, h) = (-1, 1, 8, 6);
+        let full = assemble_region(3, x, y, w, h, fetch).unwrap();
+
+        for (out_w, out_h) in [(4, 3), (3, 2), (1, 1), (8, 6)] {
+            let scaled =
+                assemble_region_scaled(3, x, y, w, h, out_w, out_h, WHITE_FILL, fetch).unwrap();
+            assert_eq!(scaled.len(), (out_w * out_h * 3) as usize);
+
+            // Reference: average each output pixel's block of the full region
+            let (col_map, _) = box_map(w, out_w);
+            let (row_map, _) = box_map(h, out_h);
+            let mut sums = vec![0u64; (out_w * out_h * 3) as usize];
+            let mut counts = vec![0u64; (out_w * out_h) as usize];
+            for ry in 0..h as usize {
+                for rx in 0..w as usize {
+                    let o = (row_map[ry] * out_w + col_map[rx]) as usize;
+                    counts[o] += 1;
+                    for c in 0..3 {
+                        sums[o * 3 + c] += full[(ry * w as usize + rx) * 3 + c] as u64;
+                    }
+                }
+            }
+            let expected: Vec<u8> = sums
+                .iter()
+                .enumerate()
+                .map(|(i, &sum)| ((sum + counts[i / 3] / 2) / counts[i / 3]) as u8)
+                .collect();
+            assert_eq!(scaled, expected, "{out_w}x{out_h}");
+        }
+
+        // Upscaling is not supported
+        assert!(assemble_region_scaled(3, x, y, w, h, 9, 6, WHITE_FILL, fetch).is_err());
+        assert!(assemble_region_scaled(3, x, y, w, h, 0, 6, WHITE_FILL, fetch).is_err());
+    }
+
+    #[test]
+    fn test_scaled_region_size_keeps_aspect_for_one_side() {
+        assert_eq!(scaled_region_size(4000, 3000, Some(1024), None), (1024, 768));
+        assert_eq!(scaled_region_size(4000, 3000, None, Some(300)), (400, 300));
+        assert_eq!(scaled_region_size(4000, 3000, Some(10), Some(20)), (10, 20));
+        assert_eq!(scaled_region_size(4000, 3000, None, None), (4000, 3000));
+        assert_eq!(scaled_region_size(4000, 10, Some(100), None), (100, 1));
+    }
+
+    #[test]
+    fn test_cancelled_region_read_stops() {
+        let temp = TempDir::new().unwrap();
+        create_test_fastpath_bordered(temp.path());
+        let metadata = SlideMetadata::load(temp.path()).unwrap();
+        let pack = TilePack::open(temp.path()).unwrap();
+        let token = CancelToken::default();
+        let tiles = LevelTiles { pack: &pack, metadata: &metadata, level: 0, cancel: Some(&token) };
+
+        assert!(decode_region_bytes(&tiles, 0, 0, 4, 2, WHITE_FILL).is_ok());
+
+        token.flag().store(true, std::sync::atomic::Ordering::Release);
+        let err = decode_region_bytes(&tiles, 0, 0, 4, 2, WHITE_FILL).unwrap_err();
+        assert!(matches!(err, crate::error::TileError::Cancelled), "{err}");
+        assert!(decode_region_scaled_bytes(&tiles, 0, 0, 4, 2, 2, 1, WHITE_FILL).is_err());
+        assert!(decode_region_with_mask_bytes(&tiles, 0, 0, 4, 2).is_err());
+    }
+}
@@ -0,0 +1,26 @@
+//! Where the scheduler gets compressed tile bytes from.
+//!
+//! Every scheduler disk read goes through a `TileSource`. Production uses
+//! `PackSource`, a straight `TilePack::read_tile_bytes`; tests substitute
+//! sources that block or delay reads to hit the generation/in-flight races
+//! deterministically instead of relying on timing.
+
+use bytes::Bytes;
+
+use crate::error::TileResult;
+use crate::pack::{PackTileRef, TilePack};
+
+/// Reads a tile's compressed bytes from a slide's pack.
+pub trait TileSource: Send + Sync {
+    fn read_tile_bytes(&self, pack: &TilePack, tile_ref: PackTileRef) -> TileResult<Bytes>;
+}
+
+/// Reads directly from the pack file.
+#[derive(Debug, Default)]
+pub struct PackSource;
+
+impl TileSource for PackSource {
+    fn read_tile_bytes(&self, pack: &TilePack, tile_ref: PackTileRef) -> TileResult<Bytes> {
+        pack.read_tile_bytes(tile_ref)
+    }
+}
@@ -0,0 +1,191 @@
+//! Lock-free latency histograms for tile load steps.
+//!
+//! When per-tile timing is on (`FASTPATH_TILE_TIMING` or profiling), every
+//! pack load records its disk read, L2 insert and decode durations here, so
+//! telemetry can read p50/p95 latencies without parsing log output.
+//!
+//! Buckets are log-linear: exact below 8us, then 8 sub-buckets per power of
+//! two, so a reported percentile is within 1/16 of the true value. Recording
+//! is two relaxed atomic adds and a `fetch_max`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Sub-buckets per power of two (as bits).
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+/// Enough buckets for any u64 microsecond value.
+const NUM_BUCKETS: usize = ((64 - SUB_BUCKET_BITS as usize) + 1) * SUB_BUCKETS as usize;
+
+/// Bucket holding `us`.
+fn bucket_index(us: u64) -> usize {
+    if us < SUB_BUCKETS {
+        return us as usize;
+    }
+    let exp = 63 - us.leading_zeros();
+    let sub = (us >> (exp - SUB_BUCKET_BITS)) & (SUB_BUCKETS - 1);
+    ((exp - SUB_BUCKET_BITS + 1) as u64 * SUB_BUCKETS + sub) as usize
+}
+
+/// Midpoint of bucket `index`, the value reported for samples in it.
+fn bucket_value(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let lower = (SUB_BUCKETS + index % SUB_BUCKETS) << shift;
+    lower + (1u64 << shift) / 2
+}
+
+/// Latency percentiles in microseconds (all 0 with no samples).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Percentiles {
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+/// Histogram of durations in microseconds; safe to record from any thread.
+pub struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: (0..NUM_BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            max: AtomicU64::new(0),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&self, duration: Duration) {
+        let us = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+        self.buckets[bucket_index(us)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.max.fetch_max(us, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// p50/p95/p99 and the exact maximum.
+    ///
+    /// Samples recorded while this runs may or may not be counted.
+    pub fn percentiles(&self) -> Percentiles {
+        let counts: Vec<u64> = self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return Percentiles::default();
+        }
+        let max = self.max.load(Ordering::Relaxed);
+        let at = |pct: u64| {
+            // Rank of the sample at `pct`, 1-based
+            let rank = (total * pct).div_ceil(100).max(1);
+            let mut seen = 0;
+            for (index, &count) in counts.iter().enumerate() {
+                seen += count;
+                if seen >= rank {
+                    return bucket_value(index).min(max);
+                }
+            }
+            max
+        };
+        Percentiles {
+            p50: at(50),
+            p95: at(95),
+            p99: at(99),
+            max,
+        }
+    }
+
+    pub fn reset(&self) {
+        for bucket in self.buckets.iter() {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.count.store(0, Ordering::Relaxed);
+        self.max.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Step histograms for pack loads.
+#[derive(Default)]
+pub struct LoadTimings {
+    pub disk: LatencyHistogram,
+    pub l2_insert: LatencyHistogram,
+    pub decode: LatencyHistogram,
+}
+
+/// Snapshot of [`LoadTimings`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimingStats {
+    /// Loads recorded (one sample per step each).
+    pub samples: u64,
+    pub disk: Percentiles,
+    pub l2_insert: Percentiles,
+    pub decode: Percentiles,
+}
+
+impl LoadTimings {
+    pub fn record(&self, disk: Duration, l2_insert: Duration, decode: Duration) {
+        self.disk.record(disk);
+        self.l2_insert.record(l2_insert);
+        self.decode.record(decode);
+    }
+
+    pub fn stats(&self) -> TimingStats {
+        TimingStats {
+            samples: self.decode.count(),
+            disk: self.disk.percentiles(),
+            l2_insert: self.l2_insert.percentiles(),
+            decode: self.decode.percentiles(),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.disk.reset();
+        self.l2_insert.reset();
+        self.decode.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_value_stays_within_a_sixteenth() {
+        for us in [0, 1, 7, 8, 9, 15, 16, 100, 1_000, 12_345, 1 << 40, u64::MAX] {
+            let value = bucket_value(bucket_index(us));
+            assert!(value.abs_diff(us) <= us / 16 + 1, "{us} -> {value}");
+        }
+        assert!(bucket_index(u64::MAX) < NUM_BUCKETS);
+    }
+
+    #[test]
+    fn test_percentiles_follow_recorded_samples() {
+        let hist = LatencyHistogram::default();
+        assert_eq!(hist.percentiles(), Percentiles::default());
+
+        // 1..=100 ms: p50 ~ 50ms, p95 ~ 95ms, max exact
+        for ms in 1..=100 {
+            hist.record(Duration::from_millis(ms));
+        }
+        let p = hist.percentiles();
+        assert_eq!(hist.count(), 100);
+        assert!(p.p50.abs_diff(50_000) <= 50_000 / 16, "{p:?}");
+        assert!(p.p95.abs_diff(95_000) <= 95_000 / 16, "{p:?}");
+        assert!(p.p99 <= p.max);
+        assert_eq!(p.max, 100_000);
+
+        hist.reset();
+        assert_eq!((hist.count(), hist.percentiles()), (0, Percentiles::default()));
+    }
+}